@@ -6,242 +6,598 @@ use crate::engine::{
 use crate::{fonts, images};
 use args::ConwayArgs;
 use board::BoardView;
-use clap::Parser;
-use sdl2::{event::Event, keyboard::Keycode, pixels::Color, rect::Rect};
+use census_view::CensusView;
+use clap::CommandFactory;
+use config::{Preferences, Speed};
+use error::ConwayError;
+use help::HelpView;
+use import::ImportView;
+use keymap::Action;
+use pattern::image;
+use recent::RecentView;
+use run::RunView;
+use selection::SelectionView;
+use session::Session;
+use settings::SettingsView;
+use stats::StatsView;
+use theme::Theme;
+use toast::ToastView;
+use sdl2::{event::Event, keyboard::Mod, rect::Rect, sys::SDL_RendererFlags};
 use std::{
     cell::{Cell, RefCell},
+    collections::HashMap,
+    path::Path,
     rc::Rc,
+    time::Instant,
 };
 
 mod args;
 mod board;
+mod census;
+mod census_view;
+mod config;
+mod error;
+mod export;
 mod game_state;
+mod help;
+mod import;
+mod keymap;
+mod pattern;
+mod raster;
+mod recent;
+mod rule;
+mod run;
+mod selection;
+mod session;
+mod settings;
+mod snapshot;
+mod stats;
+mod theme;
+mod toast;
+mod topology;
 
-const BACKGROUND_COLOR: Color = Color::WHITE;
+pub use census::census_headless;
+pub use config::Config;
+pub use export::export_headless;
+pub use snapshot::export_image_headless;
+
+/// Generations per second the speed buttons step through. Speeding up past
+/// the last one runs as fast as possible.
+const SPEEDS: [f64; 18] = [
+    0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0,
+    10000.0, 20000.0, 50000.0,
+];
 
 pub struct ConwayApp {
+    config: Config,
+    theme: Theme,
     components: ViewChain,
+    board_view: Rc<RefCell<BoardView>>,
+    session: Session,
     fps_limiter: Rc<RefCell<FrameLimiter>>,
     pause: Rc<Cell<bool>>,
     running: Rc<Cell<bool>>,
+    run_view: Option<Rc<RefCell<RunView>>>,
+    actions: HashMap<Action, Rc<dyn Fn()>>,
+    help_visible: Rc<Cell<bool>>,
+    recent_view: Rc<RefCell<RecentView>>,
+    recent_visible: Rc<Cell<bool>>,
+    import_view: Rc<RefCell<ImportView>>,
+    import_visible: Rc<Cell<bool>>,
+    census_view: Rc<RefCell<CensusView>>,
+    census_visible: Rc<Cell<bool>>,
+    toasts: Rc<RefCell<ToastView>>,
 }
 
-impl App for ConwayApp {
-    fn create(_viewport: Rect) -> Self {
-        let args = ConwayArgs::parse();
+impl ConwayApp {
+    /// Lays the app out in the viewport, with the board between the
+    /// sidebars taking the space left by them.
+    pub fn new(viewport: Rect, mut config: Config) -> ConwayApp {
+        let preferences = config.get_preferences().clone();
+        let theme = preferences.theme;
+        let paused = config.is_paused();
+        let sidebar_x = viewport.width() as i32 - 180;
+        let board_bound = Rect::new(200, 0, viewport.width() - 400, viewport.height());
+        let toast_width = (board_bound.width() - 40).min(600);
+        let toast_bound = Rect::new(
+            board_bound.center().x() - toast_width as i32 / 2,
+            20,
+            toast_width,
+            board_bound.height() - 40,
+        );
+
+        let toasts = ToastView::new(toast_bound, theme).wrap();
+        for warning in config.take_warnings() {
+            toasts.borrow_mut().warn(warning);
+        }
+
+        let mut fps_limiter = FrameLimiter::new(60, 1.0);
+        match preferences.speed {
+            Speed::PerSecond(speed) => fps_limiter.set_tick_rate(speed),
+            Speed::Max => fps_limiter.set_unlimited(true),
+        }
+
+        let mut board_view = BoardView::new(
+            preferences.width,
+            preferences.height,
+            preferences.topology,
+            preferences.rule,
+            board_bound,
+        );
+        board_view.set_last_directory(preferences.last_directory);
+        board_view.set_recent_files(preferences.recent_files);
+        if let Some(seed) = config.get_seed() {
+            board_view.set_seed(seed);
+        }
+        if let Some(path) = config.get_file() {
+            if let Err(e) = board_view.load_file(path) {
+                ConwayArgs::command()
+                    .error(
+                        clap::error::ErrorKind::ValueValidation,
+                        format!("Cannot load {}: {}", path.display(), e),
+                    )
+                    .exit();
+            }
+        } else if config.get_seed().is_some() {
+            board_view.randomize();
+        }
+
+        // Offer to restore a session that ended with unsaved changes, unless
+        // a file is being opened, the board filled from a seed or a
+        // screenshot taken
+        let mut session = Session::new();
+        if config.get_file().is_none()
+            && config.get_seed().is_none()
+            && config.get_screenshot_path().is_none()
+            && session.has_autosave()
+        {
+            let answer = rfd::MessageDialog::new()
+                .set_title("Restore the previous session?")
+                .set_description("The previous session ended with unsaved changes to the board.")
+                .set_buttons(rfd::MessageButtons::YesNo)
+                .show();
+            let restored = match answer {
+                rfd::MessageDialogResult::Yes => session.restore(&mut board_view),
+                _ => session.clear(),
+            };
+            if let Err(e) = restored {
+                toasts.borrow_mut().error(e);
+            }
+        }
+        let board_view = board_view.wrap();
+        let recent_visible = Rc::new(Cell::new(false));
+        let recent_view = RecentView::new(
+            Rect::from_center(board_bound.center(), 600, 480),
+            board_view.clone(),
+            recent_visible.clone(),
+            theme,
+            toasts.clone(),
+        )
+        .wrap();
+        let import_visible = Rc::new(Cell::new(false));
+        let import_view = ImportView::new(
+            Rect::from_center(board_bound.center(), 600, 520),
+            board_view.clone(),
+            import_visible.clone(),
+            theme,
+            toasts.clone(),
+        )
+        .wrap();
+        let census_visible = Rc::new(Cell::new(false));
+        let census_view = CensusView::new(
+            Rect::from_center(board_bound.center(), 600, 520),
+            census_visible.clone(),
+            theme,
+            toasts.clone(),
+        )
+        .wrap();
 
         let mut app = ConwayApp {
+            config,
+            theme,
             components: ViewChain::new(),
-            fps_limiter: Rc::new(RefCell::new(FrameLimiter::new(60, 1))),
-            pause: Rc::new(Cell::new(false)),
+            board_view: board_view.clone(),
+            session,
+            fps_limiter: Rc::new(RefCell::new(fps_limiter)),
+            pause: Rc::new(Cell::new(paused)),
             running: Rc::new(Cell::new(false)),
+            run_view: None,
+            actions: HashMap::new(),
+            help_visible: Rc::new(Cell::new(false)),
+            recent_view,
+            recent_visible,
+            import_view,
+            import_visible,
+            census_view,
+            census_visible,
+            toasts,
         };
 
-        let board_bound = Rect::new(200, 0, 600, 600);
         let speed_text_bound = Rect::new(20, 20, 150, 40);
+        let stats_text_bound = Rect::new(20, 62, 170, 30);
         let pause_button_bound = Rect::new(20, 100, 100, 40);
         let speedup_button_bound = Rect::new(20, 150, 100, 40);
         let speeddown_button_bound = Rect::new(20, 200, 100, 40);
         let clear_button_bound = Rect::new(20, 250, 100, 40);
         let load_button_bound = Rect::new(20, 300, 100, 40);
         let save_button_bound = Rect::new(20, 350, 100, 40);
-        let exit_button_bound = Rect::new(20, 400, 100, 40);
-        let logo_bound = Rect::new(20, 460, 120, 120);
+        let recent_button_bound = Rect::new(20, 400, 100, 40);
+        let exit_button_bound = Rect::new(20, 450, 100, 40);
+        let logo_bound = Rect::new(20, 500, 80, 80);
+        let settings_bound = Rect::new(sidebar_x, 20, 160, 220);
+        let run_bound = Rect::new(sidebar_x, 260, 160, 240);
+        let selection_bound = Rect::new(sidebar_x, 510, 160, 52);
+        let help_bound = Rect::from_center(board_bound.center(), 600, 520);
 
-        let board_view = BoardView::new(args.width, args.height, args.periodic, board_bound).wrap();
         app.components.add_view(board_view.clone());
 
+        let settings = SettingsView::new(
+            settings_bound,
+            board_view.clone(),
+            theme,
+            app.toasts.clone(),
+        )
+        .wrap();
+        app.components.add_view(settings);
+
+        let run_view = RunView::new(
+            run_bound,
+            board_view.clone(),
+            app.pause.clone(),
+            theme,
+            app.toasts.clone(),
+        )
+        .wrap();
+        app.components.add_view(run_view.clone());
+        app.run_view = Some(run_view.clone());
+        if let Some(generation) = app.config.get_generation() {
+            run_view.borrow().run_until(generation);
+        }
+
+        let selection = SelectionView::new(
+            selection_bound,
+            board_view.clone(),
+            theme,
+            app.toasts.clone(),
+        )
+        .wrap();
+        app.components.add_view(selection);
+
         let speed_text = TextView::new(
             speed_text_bound,
-            format!("Speed: {}", app.fps_limiter.borrow().get_tick_rate()),
+            format_speed(&app.fps_limiter.borrow()),
             &fonts::ARK_PIXEL_FONT,
             26,
-            Color::BLACK,
+            theme.get_text_color(),
         )
         .wrap();
         app.components.add_view(speed_text.clone());
 
+        let stats_text = StatsView::new(stats_text_bound, app.fps_limiter.clone(), theme).wrap();
+        app.components.add_view(stats_text);
+
         let speedup_button_text = TextView::new(
             speedup_button_bound,
             "Speed Up".to_string(),
             &fonts::ARK_PIXEL_FONT,
             18,
-            Color::BLACK,
+            theme.get_text_color(),
         )
         .wrap();
         app.components.add_view(speedup_button_text);
-        let speedup_button = ButtonView::new(
-            speedup_button_bound,
-            Box::new({
-                let speed_text = speed_text.clone();
-                let fps_limiter = app.fps_limiter.clone();
-                move || {
-                    let current_speed = fps_limiter.borrow().get_tick_rate();
-                    let next_speed = (current_speed + 1).min(5);
-                    fps_limiter.borrow_mut().set_tick_rate(next_speed);
-                    speed_text
-                        .borrow_mut()
-                        .set_text(format!("Speed: {}", next_speed));
-                }
-            }),
-        )
-        .wrap();
+        let speed_up: Rc<dyn Fn()> = Rc::new({
+            let speed_text = speed_text.clone();
+            let fps_limiter = app.fps_limiter.clone();
+            move || {
+                let mut fps_limiter = fps_limiter.borrow_mut();
+                change_speed(&mut fps_limiter, true);
+                speed_text.borrow_mut().set_text(format_speed(&fps_limiter));
+            }
+        });
+        let speedup_button = action_button(speedup_button_bound, &speed_up).wrap();
         app.components.add_view(speedup_button);
+        app.actions.insert(Action::SpeedUp, speed_up);
 
         let speeddown_button_text = TextView::new(
             speeddown_button_bound,
             "Speed Down".to_string(),
             &fonts::ARK_PIXEL_FONT,
             18,
-            Color::BLACK,
+            theme.get_text_color(),
         )
         .wrap();
         app.components.add_view(speeddown_button_text);
-        let speeddown_button = ButtonView::new(
-            speeddown_button_bound,
-            Box::new({
-                let speed_text = speed_text.clone();
-                let fps_limiter = app.fps_limiter.clone();
-                move || {
-                    let current_speed = fps_limiter.borrow().get_tick_rate();
-                    let next_speed = (current_speed - 1).max(1);
-                    fps_limiter.borrow_mut().set_tick_rate(next_speed);
-                    speed_text
-                        .borrow_mut()
-                        .set_text(format!("Speed: {}", next_speed));
-                }
-            }),
-        )
-        .wrap();
+        let speed_down: Rc<dyn Fn()> = Rc::new({
+            let speed_text = speed_text.clone();
+            let fps_limiter = app.fps_limiter.clone();
+            move || {
+                let mut fps_limiter = fps_limiter.borrow_mut();
+                change_speed(&mut fps_limiter, false);
+                speed_text.borrow_mut().set_text(format_speed(&fps_limiter));
+            }
+        });
+        let speeddown_button = action_button(speeddown_button_bound, &speed_down).wrap();
         app.components.add_view(speeddown_button);
+        app.actions.insert(Action::SpeedDown, speed_down);
 
         let pause_button_text = TextView::new(
             pause_button_bound,
-            "Pause".to_string(),
+            if app.pause.get() { "Resume" } else { "Pause" }.to_string(),
             &fonts::ARK_PIXEL_FONT,
             18,
-            Color::RGB(0, 0, 0),
+            theme.get_text_color(),
         )
         .wrap();
         app.components.add_view(pause_button_text.clone());
 
-        let pause_button = ButtonView::new(
-            pause_button_bound,
-            Box::new({
-                let pause = app.pause.clone();
-                let pause_button_text = pause_button_text.clone();
-                move || {
-                    let is_paused = pause.get();
-                    if is_paused {
-                        pause.set(false);
-                        pause_button_text.borrow_mut().set_text("Pause".to_string());
-                    } else {
-                        pause.set(true);
-                        pause_button_text
-                            .borrow_mut()
-                            .set_text("Resume".to_string());
-                    }
+        let toggle_pause: Rc<dyn Fn()> = Rc::new({
+            let pause = app.pause.clone();
+            let pause_button_text = pause_button_text.clone();
+            move || {
+                let is_paused = pause.get();
+                if is_paused {
+                    pause.set(false);
+                    pause_button_text.borrow_mut().set_text("Pause".to_string());
+                } else {
+                    pause.set(true);
+                    pause_button_text
+                        .borrow_mut()
+                        .set_text("Resume".to_string());
                 }
-            }),
-        )
-        .wrap();
+            }
+        });
+        let pause_button = action_button(pause_button_bound, &toggle_pause).wrap();
         app.components.add_view(pause_button);
+        app.actions.insert(Action::Pause, toggle_pause);
 
         let clear_button_text = TextView::new(
             clear_button_bound,
             "Clear".to_string(),
             &fonts::ARK_PIXEL_FONT,
             18,
-            Color::BLACK,
+            theme.get_text_color(),
         )
         .wrap();
         app.components.add_view(clear_button_text);
-        let clear_button = ButtonView::new(
-            clear_button_bound,
-            Box::new({
-                let board_view = board_view.clone();
-                move || {
-                    board_view.borrow_mut().clear();
-                }
-            }),
-        )
-        .wrap();
+        let clear: Rc<dyn Fn()> = Rc::new({
+            let board_view = board_view.clone();
+            move || {
+                board_view.borrow_mut().clear();
+            }
+        });
+        let clear_button = action_button(clear_button_bound, &clear).wrap();
         app.components.add_view(clear_button);
+        app.actions.insert(Action::Clear, clear);
 
         let load_button_text = TextView::new(
             load_button_bound,
             "Load".to_string(),
             &fonts::ARK_PIXEL_FONT,
             18,
-            Color::BLACK,
+            theme.get_text_color(),
         )
         .wrap();
         app.components.add_view(load_button_text);
-        let load_button = ButtonView::new(
-            load_button_bound,
-            Box::new({
-                let board_view = board_view.clone();
-                move || {
-                    if let Err(e) = board_view.borrow_mut().import_from_file() {
-                        println!("Error: {}", e);
-                    }
+        // Images are previewed before they replace the board
+        let load: Rc<dyn Fn()> = Rc::new({
+            let board_view = board_view.clone();
+            let import_view = app.import_view.clone();
+            let toasts = app.toasts.clone();
+            move || {
+                let Some(path) = board_view.borrow_mut().pick_file() else {
+                    return;
+                };
+                let loaded = if image::is_image(&path) {
+                    import_view.borrow_mut().open(&path)
+                } else {
+                    board_view.borrow_mut().load_file(&path)
+                };
+                if let Err(e) = loaded {
+                    toasts.borrow_mut().error(e);
                 }
-            }),
-        )
-        .wrap();
+            }
+        });
+        let load_button = action_button(load_button_bound, &load).wrap();
         app.components.add_view(load_button);
+        app.actions.insert(Action::Load, load);
 
         let save_button_text = TextView::new(
             save_button_bound,
             "Save".to_string(),
             &fonts::ARK_PIXEL_FONT,
             18,
-            Color::BLACK,
+            theme.get_text_color(),
         )
         .wrap();
         app.components.add_view(save_button_text);
-        let save_button = ButtonView::new(
-            save_button_bound,
-            Box::new({
-                let board_view = board_view.clone();
-                move || {
-                    if let Err(e) = board_view.borrow().export_to_file() {
-                        println!("Error: {}", e);
-                    }
+        let save: Rc<dyn Fn()> = Rc::new({
+            let board_view = board_view.clone();
+            let toasts = app.toasts.clone();
+            move || {
+                if let Err(e) = board_view.borrow_mut().export_to_file() {
+                    toasts.borrow_mut().error(e);
                 }
-            }),
+            }
+        });
+        let save_button = action_button(save_button_bound, &save).wrap();
+        app.components.add_view(save_button);
+        app.actions.insert(Action::Save, save);
+
+        let recent_button_text = TextView::new(
+            recent_button_bound,
+            "Recent".to_string(),
+            &fonts::ARK_PIXEL_FONT,
+            18,
+            theme.get_text_color(),
         )
         .wrap();
-        app.components.add_view(save_button);
+        app.components.add_view(recent_button_text);
+        let show_recent: Rc<dyn Fn()> = Rc::new({
+            let recent_visible = app.recent_visible.clone();
+            move || recent_visible.set(true)
+        });
+        let recent_button = action_button(recent_button_bound, &show_recent).wrap();
+        app.components.add_view(recent_button);
+        app.actions.insert(Action::Recent, show_recent);
 
         let exit_button_text = TextView::new(
             exit_button_bound,
             "Exit".to_string(),
             &fonts::ARK_PIXEL_FONT,
             18,
-            Color::BLACK,
+            theme.get_text_color(),
         )
         .wrap();
         app.components.add_view(exit_button_text);
 
-        let exit_button = ButtonView::new(
-            exit_button_bound,
-            Box::new({
-                let running = app.running.clone();
-                move || {
+        let exit: Rc<dyn Fn()> = Rc::new({
+            let running = app.running.clone();
+            let board_view = board_view.clone();
+            let toasts = app.toasts.clone();
+            move || {
+                if confirm_exit(&board_view, &toasts) {
                     running.set(false);
                 }
-            }),
-        )
-        .wrap();
+            }
+        });
+        let exit_button = action_button(exit_button_bound, &exit).wrap();
         app.components.add_view(exit_button);
+        app.actions.insert(Action::Exit, exit);
 
         // Put a logo to the top right corner
         let logo = ImageView::new(logo_bound, &images::RUST_LOGO_IMG).wrap();
         app.components.add_view(logo);
 
+        // Actions without a button
+        app.actions
+            .insert(Action::Step, Rc::new(move || run_view.borrow().step()));
+        app.actions.insert(
+            Action::Randomize,
+            Rc::new({
+                let board_view = board_view.clone();
+                move || board_view.borrow_mut().randomize()
+            }),
+        );
+        app.actions.insert(
+            Action::Census,
+            Rc::new({
+                let board_view = board_view.clone();
+                let census_view = app.census_view.clone();
+                let toasts = app.toasts.clone();
+                // Taking a census again while one runs cancels it
+                move || {
+                    let mut census_view = census_view.borrow_mut();
+                    if census_view.is_running() {
+                        census_view.cancel();
+                    } else if let Err(e) = census_view.start(board_view.borrow().get_game_state())
+                    {
+                        toasts.borrow_mut().error(e);
+                    }
+                }
+            }),
+        );
+        app.actions.insert(
+            Action::Export,
+            Rc::new({
+                let board_view = board_view.clone();
+                let toasts = app.toasts.clone();
+                let options = app.config.get_export_options().clone();
+                move || {
+                    if let Err(e) = board_view.borrow_mut().export_run(&options) {
+                        toasts.borrow_mut().error(e);
+                    }
+                }
+            }),
+        );
+        app.actions.insert(
+            Action::ExportImage,
+            Rc::new({
+                let board_view = board_view.clone();
+                let toasts = app.toasts.clone();
+                let options = app.config.get_export_options().clone();
+                move || {
+                    if let Err(e) = board_view.borrow_mut().export_image(&options) {
+                        toasts.borrow_mut().error(e);
+                    }
+                }
+            }),
+        );
+        app.actions.insert(
+            Action::Undo,
+            Rc::new({
+                let board_view = board_view.clone();
+                let toasts = app.toasts.clone();
+                move || {
+                    if let Err(e) = board_view.borrow_mut().undo() {
+                        toasts.borrow_mut().warn(e);
+                    }
+                }
+            }),
+        );
+        for (action, zoom_in) in [(Action::ZoomIn, true), (Action::ZoomOut, false)] {
+            let board_view = board_view.clone();
+            app.actions.insert(
+                action,
+                Rc::new(move || board_view.borrow_mut().zoom(zoom_in)),
+            );
+        }
+        app.actions.insert(
+            Action::Help,
+            Rc::new({
+                let help_visible = app.help_visible.clone();
+                move || help_visible.set(!help_visible.get())
+            }),
+        );
+
+        // The overlays are added last to be drawn over the other views
+        app.components.add_view(app.recent_view.clone());
+        app.components.add_view(app.import_view.clone());
+        app.components.add_view(app.census_view.clone());
+        let help = HelpView::new(
+            help_bound,
+            app.config.get_keymap(),
+            app.help_visible.clone(),
+            theme,
+        )
+        .wrap();
+        app.components.add_view(help);
+        app.components.add_view(app.toasts.clone());
+
+        // The size and rule of a file loaded on start last for the session
+        if app.config.get_file().is_some() {
+            let preferences = app.get_preferences();
+            app.config.set_preferences(preferences);
+        }
+
         app
     }
+
+    /// The preferences as the session leaves them.
+    fn get_preferences(&self) -> Preferences {
+        let board_view = self.board_view.borrow();
+        let fps_limiter = self.fps_limiter.borrow();
+        Preferences {
+            width: board_view.get_width(),
+            height: board_view.get_height(),
+            topology: board_view.get_topology(),
+            rule: board_view.get_rule().clone(),
+            speed: if fps_limiter.is_unlimited() {
+                Speed::Max
+            } else {
+                Speed::PerSecond(fps_limiter.get_tick_rate())
+            },
+            last_directory: board_view.get_last_directory().map(|dir| dir.to_path_buf()),
+            recent_files: board_view.get_recent_files().to_vec(),
+            ..self.config.get_preferences().clone()
+        }
+    }
+}
+
+impl App for ConwayApp {
+    fn create(viewport: Rect) -> Self {
+        ConwayApp::new(viewport, Config::load())
+    }
+
     fn run(
         &mut self,
         sdl_context: &mut sdl2::Sdl,
@@ -252,23 +608,100 @@ impl App for ConwayApp {
         self.running.set(true);
         let mut event_pump = sdl_context.event_pump()?;
 
-        canvas.set_draw_color(BACKGROUND_COLOR);
+        // With vsync presenting paces the renders, at the display's rate
+        let vsync = canvas.info().flags & SDL_RendererFlags::SDL_RENDERER_PRESENTVSYNC as u32 != 0;
+        if vsync {
+            match canvas.window().display_mode() {
+                Ok(mode) if mode.refresh_rate > 0 => self
+                    .fps_limiter
+                    .borrow_mut()
+                    .set_render_rate(mode.refresh_rate as u32),
+                _ => {}
+            }
+        }
+        self.fps_limiter.borrow_mut().set_vsync(vsync);
+
+        canvas.set_draw_color(self.theme.get_background_color());
         canvas.clear();
         canvas.present();
 
+        // Closing the window exits without asking about unsaved changes
+        let mut closed = false;
         loop {
-            let step_type = self.fps_limiter.borrow_mut().step()?;
-            if let FrameStepType::Render | FrameStepType::RenderAndTick = step_type {
+            let step_type = self.fps_limiter.borrow_mut().step();
+            if let FrameStepType::Render | FrameStepType::RenderAndTick(_) = step_type {
                 for event in event_pump.poll_iter() {
                     match event {
-                        Event::Quit { .. }
-                        | Event::KeyDown {
-                            keycode: Some(Keycode::Escape),
-                            ..
-                        } => {
+                        Event::Quit { .. } => {
                             self.running.set(false);
+                            closed = true;
                             break;
                         }
+                        // Clicking a toast dismisses it, whatever is below
+                        Event::MouseButtonDown { x, y, .. }
+                            if self.toasts.borrow().contains(x, y) =>
+                        {
+                            self.toasts.borrow_mut().on_event(&event);
+                        }
+                        // Any key or click closes the help
+                        Event::KeyDown { .. } | Event::MouseButtonDown { .. }
+                            if self.help_visible.get() =>
+                        {
+                            self.help_visible.set(false);
+                        }
+                        Event::KeyDown { .. } | Event::MouseButtonDown { .. }
+                            if self.recent_visible.get() =>
+                        {
+                            self.recent_view.borrow_mut().on_event(&event);
+                        }
+                        Event::KeyDown { .. } | Event::MouseButtonDown { .. }
+                            if self.import_visible.get() =>
+                        {
+                            self.import_view.borrow_mut().on_event(&event);
+                        }
+                        Event::KeyDown { .. } | Event::MouseButtonDown { .. }
+                            if self.census_visible.get() =>
+                        {
+                            self.census_view.borrow_mut().on_event(&event);
+                        }
+                        // Keys go to text being typed before they are shortcuts
+                        Event::KeyDown {
+                            keycode: Some(key),
+                            keymod,
+                            ..
+                        } if !self.components.has_focus() => {
+                            match self
+                                .config
+                                .get_keymap()
+                                .get_action(key, keymod)
+                                .and_then(|action| self.actions.get(&action))
+                            {
+                                Some(callback) => callback(),
+                                None => self.components.on_event(&event),
+                            }
+                        }
+                        // Holding a modifier pastes a dropped pattern, and
+                        // images are previewed unless they are pasted
+                        Event::DropFile { filename, .. } => {
+                            let keymod = sdl_context.keyboard().mod_state();
+                            let paste = keymod.intersects(
+                                Mod::LCTRLMOD
+                                    | Mod::RCTRLMOD
+                                    | Mod::LSHIFTMOD
+                                    | Mod::RSHIFTMOD
+                                    | Mod::LALTMOD
+                                    | Mod::RALTMOD,
+                            );
+                            let path = Path::new(&filename);
+                            let dropped = if !paste && image::is_image(path) {
+                                self.import_view.borrow_mut().open(path)
+                            } else {
+                                self.board_view.borrow_mut().drop_file(path, paste)
+                            };
+                            if let Err(e) = dropped {
+                                self.toasts.borrow_mut().error(e);
+                            }
+                        }
                         e @ _ => {
                             self.components.on_event(&e);
                         }
@@ -280,20 +713,149 @@ impl App for ConwayApp {
                 }
 
                 // Refresh the screen.
-                canvas.set_draw_color(BACKGROUND_COLOR);
+                canvas.set_draw_color(self.theme.get_background_color());
                 canvas.clear();
 
                 self.components
-                    .render(canvas, texture_creator, font_manager)?;
+                    .render(canvas, texture_creator, font_manager)
+                    .map_err(|e| ConwayError::Sdl(e.to_string()))?;
+
+                // The screenshot waits for any run to the start generation
+                let is_running = self
+                    .run_view
+                    .as_ref()
+                    .is_some_and(|run_view| run_view.borrow().is_running());
+                if let Some(path) = self.config.get_screenshot_path() {
+                    if !is_running {
+                        snapshot::save_screenshot(canvas, path)?;
+                        self.running.set(false);
+                    }
+                }
 
                 canvas.present();
+
+                if let Err(e) = self.session.autosave(&self.board_view.borrow()) {
+                    self.toasts
+                        .borrow_mut()
+                        .error(format!("Cannot autosave: {}", e));
+                }
+
+                // Runs use the time until the next render, shared with a
+                // census in progress
+                let mut budget = self.fps_limiter.borrow().get_render_interval();
+                if self.census_view.borrow().is_running() {
+                    budget /= 2;
+                    self.census_view.borrow_mut().advance(budget);
+                }
+                if let Some(run_view) = &self.run_view {
+                    run_view.borrow_mut().advance(budget);
+                }
             }
-            if let FrameStepType::Tick | FrameStepType::RenderAndTick = step_type {
-                if !self.pause.get() {
-                    self.components.on_tick();
+            if let FrameStepType::Tick(ticks) | FrameStepType::RenderAndTick(ticks) = step_type {
+                let is_running = self
+                    .run_view
+                    .as_ref()
+                    .is_some_and(|run_view| run_view.borrow().is_running());
+                if !self.pause.get() && !is_running {
+                    // A batch sized while paused may be far too large, so
+                    // it is cut off after a frame to keep the UI responsive
+                    let started = Instant::now();
+                    let render_interval = self.fps_limiter.borrow().get_render_interval();
+                    for _ in 0..ticks {
+                        self.components.on_tick();
+                        if started.elapsed() >= render_interval {
+                            break;
+                        }
+                    }
                 }
             }
         }
+
+        // The changes are kept to restore if the window was closed, and were
+        // saved or discarded if the app was exited. Screenshots leave the
+        // autosave of the last session alone.
+        let kept = if self.config.get_screenshot_path().is_some() {
+            Ok(())
+        } else if closed && self.board_view.borrow().is_modified() {
+            self.session.save(&self.board_view.borrow())
+        } else {
+            self.session.clear()
+        };
+        // The window is gone, so these errors are only logged
+        if let Err(e) = kept {
+            log::error!("Cannot update the autosave: {}", e);
+        }
+
+        let preferences = self.get_preferences();
+        if let Err(e) = self.config.save(&preferences) {
+            log::error!("{}", e);
+        }
         Ok(())
     }
 }
+
+/// Moves the speed one step along `SPEEDS`, or in and out of running as fast
+/// as possible.
+fn change_speed(fps_limiter: &mut FrameLimiter, faster: bool) {
+    if fps_limiter.is_unlimited() {
+        if !faster {
+            fps_limiter.set_unlimited(false);
+        }
+        return;
+    }
+    let speed = fps_limiter.get_tick_rate();
+    let index = SPEEDS
+        .iter()
+        .position(|&other| other >= speed * 0.999)
+        .unwrap_or(SPEEDS.len() - 1);
+    if faster && index + 1 == SPEEDS.len() {
+        fps_limiter.set_unlimited(true);
+    } else if faster {
+        fps_limiter.set_tick_rate(SPEEDS[index + 1]);
+    } else {
+        fps_limiter.set_tick_rate(SPEEDS[index.saturating_sub(1)]);
+    }
+}
+
+/// Asks whether to save the unsaved changes to the board before exiting,
+/// returning whether to exit.
+fn confirm_exit(board_view: &RefCell<BoardView>, toasts: &RefCell<ToastView>) -> bool {
+    if !board_view.borrow().is_modified() {
+        return true;
+    }
+    let answer = rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Unsaved changes")
+        .set_description("The board has unsaved changes. Save them before exiting?")
+        .set_buttons(rfd::MessageButtons::YesNoCancel)
+        .show();
+    match answer {
+        // Cancelling the save dialog stays, as there is nothing saved yet
+        rfd::MessageDialogResult::Yes => match board_view.borrow_mut().export_to_file() {
+            Ok(saved) => saved,
+            Err(e) => {
+                toasts.borrow_mut().error(e);
+                false
+            }
+        },
+        rfd::MessageDialogResult::No => true,
+        _ => false,
+    }
+}
+
+/// A button running the same callback as a key.
+fn action_button(bound: Rect, callback: &Rc<dyn Fn()>) -> ButtonView {
+    let callback = callback.clone();
+    ButtonView::new(bound, Box::new(move || callback()))
+}
+
+fn format_speed(fps_limiter: &FrameLimiter) -> String {
+    let speed = fps_limiter.get_tick_rate();
+    if fps_limiter.is_unlimited() {
+        "Speed: Max".to_string()
+    } else if speed >= 1000.0 {
+        format!("Speed: {}k", speed / 1000.0)
+    } else {
+        format!("Speed: {}", speed)
+    }
+}
@@ -1,16 +1,154 @@
 extern crate clap;
 
+use std::path::PathBuf;
+
 use clap::Parser;
 
+use super::config::{Speed, WindowSize};
+use super::rule::Rule;
+use super::theme::Theme;
+use super::topology::Topology;
+
 #[derive(Parser, Debug)]
-#[command(version, about = "Conway's Game of Life in Rust!", long_about = None)]
+#[command(
+    version,
+    about = "Conway's Game of Life in Rust!",
+    long_about = None,
+    after_help = "Options left out are read from the config file, and default to the values shown."
+)]
 pub(super) struct ConwayArgs {
-    #[arg(long, short = 'W', default_value = "32", help = "The width of the board")]
-    pub width: usize,
+    #[arg(help = "A save, RLE pattern, macrocell, PNG image or rule table to load")]
+    pub file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        short = 'W',
+        help = "The width of the board, from 8 to 4096 [default: 32]"
+    )]
+    pub width: Option<usize>,
+
+    #[arg(
+        long,
+        short = 'H',
+        help = "The height of the board, from 8 to 4096 [default: 32]"
+    )]
+    pub height: Option<usize>,
+
+    #[arg(
+        long,
+        short,
+        help = "How the edges are joined: P (plane), T (torus), T,0 or T0, (cylinders), T+5, (shifted torus), K (Klein bottle), C (cross-surface), S (sphere) or M (mirror) [default: P]"
+    )]
+    pub topology: Option<Topology>,
+
+    #[arg(
+        long,
+        short,
+        help = "The rule, e.g. B36/S23, B2/S34H, R5,C0,M1,S34..58,B34..45,NM, WireWorld or a .rule file [default: B3/S23]"
+    )]
+    pub rule: Option<Rule>,
+
+    #[arg(
+        long,
+        help = "The colors of the window: light or dark [default: light]"
+    )]
+    pub theme: Option<Theme>,
+
+    #[arg(
+        long,
+        short,
+        help = "Generations per second, or max to run as fast as possible [default: 1]"
+    )]
+    pub speed: Option<Speed>,
+
+    #[arg(
+        long,
+        value_name = "WIDTHxHEIGHT",
+        help = "The size of the window, at least 1000x600 [default: 1000x600]"
+    )]
+    pub window_size: Option<WindowSize>,
+
+    #[arg(
+        long,
+        help = "The config file [default: conway-rs/config.toml in the user config directory]"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "A TOML file binding actions to keys, e.g. undo = [\"Ctrl+Z\", \"Backspace\"], over the keys of the config"
+    )]
+    pub keymap: Option<PathBuf>,
+
+    #[arg(long, help = "Start paused")]
+    pub paused: bool,
+
+    #[arg(
+        long,
+        short,
+        value_name = "N",
+        help = "Run the board to generation N on start"
+    )]
+    pub generation: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Fill the board randomly from a seed on start unless a FILE is given, so the same seed gives the same fill"
+    )]
+    pub seed: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Export a run to an animated .gif, or to numbered .png images named after PATH, instead of opening the window"
+    )]
+    pub export: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with = "export",
+        help = "Export the board to an .svg or .png image cropped to its live cells, instead of opening the window"
+    )]
+    pub image: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["export", "image"],
+        help = "Save a .png of the whole window once it is drawn, then exit"
+    )]
+    pub screenshot: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["export", "image", "screenshot"],
+        help = "Run the board until it repeats itself and write a census of its objects to a .json or .csv file, instead of opening the window. Without a FILE the board is filled randomly"
+    )]
+    pub census: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "The number of generations to export, the first being the board as loaded [default: 100]"
+    )]
+    pub frames: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "PIXELS",
+        help = "The side of a cell in exported images, from 1 to 64 [default: 4]"
+    )]
+    pub cell_size: Option<usize>,
 
-    #[arg(long, short = 'H', default_value = "32", help = "The height of the board")]
-    pub height: usize,
+    #[arg(long, help = "Draw the grid between the cells of exported SVG images")]
+    pub grid: bool,
 
-    #[arg(short, long, help = "Enable periodic boundary conditions")]
-    pub periodic: bool,
+    #[arg(
+        long,
+        value_name = "MS",
+        help = "The delay between the frames of an exported GIF in milliseconds [default: 100]"
+    )]
+    pub frame_delay: Option<u32>,
 }
@@ -1,15 +1,26 @@
 extern crate rfd;
-use std::{
-    cmp::min,
-    fs::File,
-    io::{BufRead, Write},
-};
+use std::path::{Path, PathBuf};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use sdl2::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+use sdl2::{
+    pixels::{Color, PixelFormatEnum},
+    rect::Rect,
+    render::{Texture, TextureCreator},
+    video::WindowContext,
+};
 
 use crate::engine::view::View;
 
-use super::game_state::ConwayGameState;
+use super::error::ConwayError;
+use super::export::{self, ExportOptions};
+use super::game_state::{
+    ConwayGameState, DirtyCells, Region, ResizeAnchor, ALIVE, DEAD, MIN_BOARD_SIZE,
+};
+use super::pattern::{apgcode, image, macrocell, rle, save, Pattern};
+use super::rule::Rule;
+use super::snapshot;
+use super::topology::Topology;
 
 const BOARD_BACKGROUND_COLOR: Color = Color::RGB(0, 0, 0);
 const BOARD_CELL_COLOR: Color = Color::RGB(255, 255, 255);
@@ -17,196 +28,905 @@ const BOARD_HOVERING_ALIVE_CELL_COLOR: Color = Color::RGB(255, 0, 0);
 const BOARD_HOVERING_DEAD_CELL_COLOR: Color = Color::RGB(0, 255, 0);
 const BOARD_NEWLY_ALIVE_CELL_COLOR: Color = Color::RGB(255, 255, 255);
 const BOARD_NEWLY_DEAD_CELL_COLOR: Color = Color::RGB(50, 50, 50);
+const BOARD_DYING_CELL_FIRST_COLOR: Color = Color::RGB(255, 160, 0);
+const BOARD_DYING_CELL_LAST_COLOR: Color = Color::RGB(80, 0, 0);
+const BOARD_SELECTION_COLOR: Color = Color::RGB(0, 160, 255);
 
-const MIN_BOARD_SIZE: usize = 8;
-const MAX_BOARD_SIZE: usize = 64;
+/// The chance of a cell to be alive after randomizing the board.
+const RANDOM_FILL_DENSITY: f64 = 0.5;
+/// The undo history keeps at most this many cells, but always one board.
+const MAX_UNDO_CELLS: usize = 1 << 26;
+const MAX_ZOOM: u32 = 64;
+const MAX_RECENT_FILES: usize = 10;
+
+/// The state of the board before an edit, to undo it.
+struct UndoState {
+    width: usize,
+    height: usize,
+    topology: Topology,
+    rule: Rule,
+    cells: Vec<u8>,
+    generation: u64,
+}
 
 pub struct BoardView {
     view_bound: Rect,
     render_bound: Rect,
-    cell_length: usize,
     game_state: ConwayGameState,
     hovering_cell: Option<(usize, usize)>,
+    mouse_position: Option<(i32, i32)>,
+    // The corners of the selected rectangle of cells, the first where the
+    // drag started
+    selection: Option<((usize, usize), (usize, usize))>,
+    // Whether the selection follows the mouse, while the right button is held
+    selecting: bool,
+    // Cells are `zoom` times as large as when the board fits into the view
+    zoom: u32,
+    // How far the center of the zoomed board is from the center of the view
+    pan: (i32, i32),
+    undo_states: Vec<UndoState>,
+    // Where the file dialogs open, the directory of the last file picked
+    last_directory: Option<PathBuf>,
+    // The files last loaded or saved, the most recent first
+    recent_files: Vec<PathBuf>,
+    // Fills the board randomly, from a seed to repeat the fills
+    rng: StdRng,
+    // Whether the board changed since it was last loaded or saved
+    modified: bool,
+    // Counts the changes to the board, to tell when it changed
+    version: u64,
+    // The size of `texture`, one texel per cell unless cells are smaller
+    // than a pixel
+    texture_size: (usize, usize),
+    // One RGB24 pixel per texel, mirrored into `texture`
+    pixels: Vec<u8>,
+    texture: Option<Texture>,
 }
 
 impl BoardView {
     pub fn new(
         board_width: usize,
         board_height: usize,
-        periodic: bool,
+        topology: Topology,
+        rule: Rule,
         view_bound: Rect,
     ) -> BoardView {
-        // Calculate the cell width and height
-        let cell_length = min(
-            view_bound.width() as usize / board_width,
-            view_bound.height() as usize / board_height,
-        );
-
-        // Calculate the actual view bound
-        let render_bound = Rect::from_center(
-            view_bound.center(),
-            (cell_length * board_width) as u32,
-            (cell_length * board_height) as u32,
-        );
-
-        BoardView {
+        let mut board = BoardView {
             view_bound,
-            render_bound,
-            cell_length,
-            game_state: ConwayGameState::new(board_width, board_height, periodic),
+            render_bound: view_bound,
+            game_state: ConwayGameState::new(board_width, board_height, topology, rule),
             hovering_cell: None,
-        }
+            mouse_position: None,
+            selection: None,
+            selecting: false,
+            zoom: 1,
+            pan: (0, 0),
+            undo_states: Vec::new(),
+            last_directory: None,
+            recent_files: Vec::new(),
+            rng: StdRng::from_entropy(),
+            modified: false,
+            version: 0,
+            texture_size: (0, 0),
+            pixels: Vec::new(),
+            texture: None,
+        };
+        board.update_render_bound();
+        board
     }
 
     pub fn step(&mut self) {
+        // A board that cannot change stays unmodified
+        if self.game_state.get_active_region().is_some() {
+            self.mark_modified();
+        }
         self.game_state.step();
     }
 
-    pub fn resize(&mut self, width: usize, height: usize) {
-        self.game_state.resize(width, height);
+    pub fn get_game_state(&self) -> &ConwayGameState {
+        &self.game_state
+    }
+
+    pub fn get_generation(&self) -> u64 {
+        self.game_state.get_generation()
+    }
 
-        self.cell_length = min(
-            self.view_bound.width() as usize / width,
-            self.view_bound.height() as usize / height,
-        );
+    pub fn get_width(&self) -> usize {
+        self.game_state.get_width()
+    }
 
-        self.render_bound = Rect::from_center(
-            self.view_bound.center(),
-            (self.cell_length * width) as u32,
-            (self.cell_length * height) as u32,
-        );
+    pub fn get_height(&self) -> usize {
+        self.game_state.get_height()
     }
 
-    pub fn set_periodic(&mut self, periodic: bool) {
-        self.game_state.set_is_periodic(periodic);
+    pub fn resize(&mut self, width: usize, height: usize, anchor: ResizeAnchor) {
+        self.game_state.resize(width, height, anchor);
+        self.update_render_bound();
+        self.hovering_cell = None;
+        self.selection = None;
+        self.destroy_texture();
     }
 
-    pub fn export_to_file(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let file = rfd::FileDialog::new()
-            .set_directory("./")
-            .add_filter("Conway's Game Save", &["conway"])
-            .save_file()
-            .ok_or("No file selected")?;
+    /// Resizes the board if the new size is valid, keeping the cells.
+    pub fn set_size(
+        &mut self,
+        width: usize,
+        height: usize,
+        anchor: ResizeAnchor,
+    ) -> Result<(), String> {
+        ConwayGameState::validate_size(width, height, self.game_state.get_topology())?;
+        self.save_undo_state();
+        self.resize(width, height, anchor);
+        Ok(())
+    }
 
-        let mut file = File::create(file)?;
+    pub fn get_topology(&self) -> Topology {
+        self.game_state.get_topology()
+    }
 
-        let width = self.game_state.get_width();
-        let height = self.game_state.get_height();
-        let periodic = self.game_state.get_is_periodic();
+    pub fn set_topology(&mut self, topology: Topology) -> Result<(), String> {
+        topology.validate(self.get_width(), self.get_height())?;
+        self.save_undo_state();
+        self.game_state.set_topology(topology);
+        Ok(())
+    }
 
-        file.write_all(format!("{} {} {}\n", width, height, periodic).as_bytes())?;
+    pub fn get_rule(&self) -> &Rule {
+        self.game_state.get_rule()
+    }
 
-        file.write_all(
-            self.game_state
-                .get_cells()
-                .iter()
-                .map(|cell| if *cell { '1' } else { '0' })
-                .collect::<String>()
-                .as_bytes(),
-        )?;
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.save_undo_state();
+        self.apply_rule(rule);
+    }
+
+    fn apply_rule(&mut self, rule: Rule) {
+        self.game_state.set_rule(rule);
+        // The hexagonal layout is half a cell wider
+        self.update_render_bound();
+        self.hovering_cell = None;
+    }
+
+    pub fn get_last_directory(&self) -> Option<&Path> {
+        self.last_directory.as_deref()
+    }
+
+    pub fn set_last_directory(&mut self, directory: Option<PathBuf>) {
+        self.last_directory = directory;
+    }
+
+    pub fn get_recent_files(&self) -> &[PathBuf] {
+        &self.recent_files
+    }
+
+    pub fn set_recent_files(&mut self, recent_files: Vec<PathBuf>) {
+        self.recent_files = recent_files;
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
 
+    /// Moves a file to the top of the recent files.
+    fn remember_file(&mut self, path: &Path) {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.recent_files.retain(|other| *other != path);
+        self.recent_files.insert(0, path);
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Whether the board changed since it was last loaded or saved.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// A number that grows whenever the board changes.
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    fn mark_modified(&mut self) {
+        self.modified = true;
+        self.version += 1;
+    }
+
+    fn file_dialog(&self) -> rfd::FileDialog {
+        let directory = self.last_directory.as_deref().unwrap_or(Path::new("./"));
+        rfd::FileDialog::new().set_directory(directory)
+    }
+
+    /// Asks where to save the board, returning whether it was saved. The
+    /// board is left unsaved if the dialog is cancelled.
+    pub fn export_to_file(&mut self) -> Result<bool, ConwayError> {
+        let Some(path) = self
+            .file_dialog()
+            .add_filter("Conway's Game Save", &["conway"])
+            .add_filter("Run Length Encoded", &["rle"])
+            .add_filter("Macrocell", &["mc"])
+            .save_file()
+        else {
+            return Ok(false);
+        };
+        self.last_directory = path.parent().map(Path::to_path_buf);
+        self.save_file(&path)?;
+        Ok(true)
+    }
+
+    /// Saves the board in RLE if the extension of the file is `.rle`, as a
+    /// macrocell if it is `.mc`, and as a save otherwise.
+    pub fn save_file(&mut self, path: &Path) -> Result<(), ConwayError> {
+        let pattern = Pattern {
+            width: self.game_state.get_width(),
+            height: self.game_state.get_height(),
+            cells: self.game_state.get_cells().clone(),
+            rule: Some(self.game_state.get_rule().clone()),
+            topology: Some(self.game_state.get_topology()),
+        };
+        let content = if BoardView::has_extension(path, "rle") {
+            rle::write(&pattern)
+        } else if BoardView::has_extension(path, "mc") {
+            macrocell::write(&pattern)
+        } else {
+            save::write(&pattern)
+        };
+        std::fs::write(path, content).map_err(ConwayError::io(path))?;
+        self.modified = false;
+        self.remember_file(path);
         Ok(())
     }
 
-    pub fn import_from_file(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let file = rfd::FileDialog::new()
-            .set_directory("./")
+    /// Asks where to export a run from the board to, as a GIF or PNG images.
+    pub fn export_run(&mut self, options: &ExportOptions) -> Result<(), ConwayError> {
+        let Some(path) = self
+            .file_dialog()
+            .add_filter("Animated GIF", &["gif"])
+            .add_filter("PNG Images", &["png"])
+            .save_file()
+        else {
+            return Ok(());
+        };
+        self.last_directory = path.parent().map(Path::to_path_buf);
+        export::export(&self.game_state, &path, options)
+    }
+
+    /// Asks where to export the board to, as an SVG or PNG image.
+    pub fn export_image(&mut self, options: &ExportOptions) -> Result<(), ConwayError> {
+        let Some(path) = self
+            .file_dialog()
+            .add_filter("SVG Image", &["svg"])
+            .add_filter("PNG Image", &["png"])
+            .save_file()
+        else {
+            return Ok(());
+        };
+        self.last_directory = path.parent().map(Path::to_path_buf);
+        snapshot::export_image(&self.game_state, &path, options)
+    }
+
+    /// Asks for a file to load, returning `None` if the dialog is cancelled.
+    pub fn pick_file(&mut self) -> Option<PathBuf> {
+        let path = self
+            .file_dialog()
             .add_filter("Conway's Game Save", &["conway"])
-            .pick_file()
-            .ok_or("No file selected")?;
+            .add_filter("Run Length Encoded", &["rle"])
+            .add_filter("Macrocell", &["mc"])
+            .add_filter("Golly Rule Table", &["rule"])
+            .add_filter("PNG Image", &["png"])
+            .pick_file()?;
+        self.last_directory = path.parent().map(Path::to_path_buf);
+        Some(path)
+    }
+
+    /// Loads a file by its extension: a rule table replaces the rule and
+    /// keeps the board, an RLE pattern, a save or an image replaces the
+    /// board.
+    pub fn load_file(&mut self, path: &Path) -> Result<(), ConwayError> {
+        if BoardView::has_extension(path, "rule") {
+            let rule = Rule::load_table(path)?;
+            self.set_rule(rule);
+        } else {
+            let pattern = self.read_pattern(path)?;
+            self.load_pattern(pattern)?;
+            self.modified = false;
+        }
+        self.remember_file(path);
+        Ok(())
+    }
 
-        let file = File::open(file)?;
+    /// Loads a file dropped on the window. With `paste` set, a pattern
+    /// dropped on the board is pasted where it is dropped instead.
+    pub fn drop_file(&mut self, path: &Path, paste: bool) -> Result<(), ConwayError> {
+        match self.mouse_position {
+            Some((x, y)) if paste && !BoardView::has_extension(path, "rule") => {
+                let pattern = self.read_pattern(path)?;
+                let cell = self.get_cell_index(x, y);
+                self.paste_pattern(pattern, cell)
+            }
+            _ => self.load_file(path),
+        }
+    }
 
-        let mut lines = std::io::BufReader::new(file).lines();
+    /// Replaces the board with the cells scanned from an image. The board
+    /// is left modified, as the cells are not in a pattern file yet.
+    pub fn import_image(&mut self, path: &Path, pattern: Pattern) -> Result<(), ConwayError> {
+        self.load_pattern(pattern)?;
+        self.remember_file(path);
+        Ok(())
+    }
 
-        let (width, height, periodic): (usize, usize, bool) = {
-            let line = lines.next().ok_or("Invalid file format")??;
-            let mut parts = line.split_whitespace();
-            let width = parts.next().ok_or("Invalid file format")?.parse()?;
-            let height = parts.next().ok_or("Invalid file format")?.parse()?;
-            let periodic = parts.next().ok_or("Invalid file format")?.parse()?;
-            (width, height, periodic)
+    /// Reads an RLE pattern if the extension of the file is `.rle`, a
+    /// macrocell flattened to its live cells if it is `.mc`, the cells of an
+    /// image with the threshold set from it if it is `.png`, and a save
+    /// otherwise.
+    fn read_pattern(&self, path: &Path) -> Result<Pattern, ConwayError> {
+        if image::is_image(path) {
+            let scan = image::read(path)?;
+            let (threshold, dark) = scan.get_auto_threshold();
+            return Ok(scan.to_pattern(threshold, dark));
+        }
+        let content = std::fs::read_to_string(path).map_err(ConwayError::io(path))?;
+        let pattern = if BoardView::has_extension(path, "rle") {
+            rle::parse(&content)
+        } else if BoardView::has_extension(path, "mc") {
+            macrocell::parse(&content).and_then(|macrocell| macrocell.flatten())
+        } else {
+            save::parse(&content, self.game_state.get_rule())
         };
+        pattern.map_err(|e| e.in_file(path))
+    }
 
-        // Check if the width and height are within the valid range
-        if width < MIN_BOARD_SIZE
-            || width > MAX_BOARD_SIZE
-            || height < MIN_BOARD_SIZE
-            || height > MAX_BOARD_SIZE
-        {
-            return Err("Invalid board size".into());
+    /// Pastes the live cells of a pattern centered on a cell, keeping the
+    /// rule and topology of the board. Cells falling off the board are
+    /// dropped.
+    fn paste_pattern(
+        &mut self,
+        pattern: Pattern,
+        (x, y): (usize, usize),
+    ) -> Result<(), ConwayError> {
+        let states = self.game_state.get_rule().get_states();
+        if pattern.cells.iter().any(|&cell| cell as usize >= states) {
+            return Err(ConwayError::Validation(
+                "The pattern has more states than the rule".to_string(),
+            ));
         }
 
-        let cells: Vec<bool> = {
-            let line = lines.next().ok_or("Invalid file format")??;
-            line.chars()
-                .map(|c| match c {
-                    '0' => false,
-                    '1' => true,
-                    _ => false,
-                })
-                .collect()
+        self.save_undo_state();
+        let (width, height) = (self.get_width(), self.get_height());
+        let left = x as isize - (pattern.width / 2) as isize;
+        let top = y as isize - (pattern.height / 2) as isize;
+        for (i, &cell) in pattern.cells.iter().enumerate() {
+            let x = left + (i % pattern.width) as isize;
+            let y = top + (i / pattern.width) as isize;
+            if cell != DEAD && (0..width as isize).contains(&x) && (0..height as isize).contains(&y)
+            {
+                self.game_state.set_cell(x as usize, y as usize, cell);
+            }
+        }
+        Ok(())
+    }
+
+    /// The selected rectangle of cells, if any.
+    pub fn get_selection(&self) -> Option<Region> {
+        let ((x0, y0), (x1, y1)) = self.selection?;
+        Some(Region {
+            min_x: x0.min(x1),
+            min_y: y0.min(y1),
+            max_x: x0.max(x1),
+            max_y: y0.max(y1),
+        })
+    }
+
+    /// The cells of the selection, without a rule or topology.
+    pub fn get_selected_pattern(&self) -> Option<Pattern> {
+        let region = self.get_selection()?;
+        let cells = (region.min_y..=region.max_y)
+            .flat_map(|y| (region.min_x..=region.max_x).map(move |x| (x, y)))
+            .map(|(x, y)| self.game_state.get_cell(x, y))
+            .collect();
+        Some(Pattern {
+            width: region.max_x - region.min_x + 1,
+            height: region.max_y - region.min_y + 1,
+            cells,
+            rule: None,
+            topology: None,
+        })
+    }
+
+    /// Pastes the object named by an apgcode centered on the selection, or
+    /// on the board without one, and selects it.
+    pub fn paste_code(&mut self, code: &str) -> Result<(), ConwayError> {
+        let pattern = apgcode::decode(code)?;
+        let (width, height) = (self.get_width(), self.get_height());
+        let (x, y) = match self.get_selection() {
+            Some(region) => (
+                (region.min_x + region.max_x).div_ceil(2),
+                (region.min_y + region.max_y).div_ceil(2),
+            ),
+            None => (width / 2, height / 2),
         };
+        let left = x as isize - (pattern.width / 2) as isize;
+        let top = y as isize - (pattern.height / 2) as isize;
+        let (right, bottom) = (
+            left + pattern.width as isize - 1,
+            top + pattern.height as isize - 1,
+        );
+        self.paste_pattern(pattern, (x, y))?;
 
-        // Check if the number of cells match the width and height
-        if cells.len() != width * height {
-            return Err("Invalid number of cells".into());
+        let clamp = |i: isize, size: usize| i.clamp(0, size as isize - 1) as usize;
+        self.selection = Some((
+            (clamp(left, width), clamp(top, height)),
+            (clamp(right, width), clamp(bottom, height)),
+        ));
+        Ok(())
+    }
+
+    /// Replaces the board with a pattern, centered on a board large enough
+    /// to hold it. The current rule and topology are kept if the pattern has
+    /// none.
+    fn load_pattern(&mut self, pattern: Pattern) -> Result<(), ConwayError> {
+        let topology = pattern
+            .topology
+            .unwrap_or_else(|| self.game_state.get_topology());
+        let mut width = pattern.width.max(MIN_BOARD_SIZE);
+        let mut height = pattern.height.max(MIN_BOARD_SIZE);
+        if topology == Topology::Sphere {
+            width = width.max(height);
+            height = width;
         }
+        ConwayGameState::validate_size(width, height, topology)
+            .map_err(|e| ConwayError::Validation(format!("Pattern is too large: {}", e)))?;
 
-        self.resize(width, height);
-        self.set_periodic(periodic);
+        let rule = pattern
+            .rule
+            .unwrap_or_else(|| self.game_state.get_rule().clone());
+        if pattern
+            .cells
+            .iter()
+            .any(|&cell| cell as usize >= rule.get_states())
+        {
+            return Err(ConwayError::Validation(
+                "The pattern has more states than its rule".to_string(),
+            ));
+        }
+
+        let mut cells = vec![DEAD; width * height];
+        let (left, top) = ((width - pattern.width) / 2, (height - pattern.height) / 2);
+        for y in 0..pattern.height {
+            let row = &pattern.cells[y * pattern.width..(y + 1) * pattern.width];
+            let start = (top + y) * width + left;
+            cells[start..start + pattern.width].copy_from_slice(row);
+        }
+
+        self.save_undo_state();
+        self.replace(width, height, topology, rule, cells);
+        Ok(())
+    }
+
+    /// Replaces the whole board, which must be valid.
+    fn replace(
+        &mut self,
+        width: usize,
+        height: usize,
+        topology: Topology,
+        rule: Rule,
+        cells: Vec<u8>,
+    ) {
+        self.resize(width, height, ResizeAnchor::TopLeft);
+        self.game_state.set_topology(topology);
+        self.apply_rule(rule);
         self.game_state.set_cells(cells);
+    }
+
+    /// Remembers the board before an edit, dropping the oldest states when
+    /// the history grows too large.
+    fn save_undo_state(&mut self) {
+        // Every edit saves an undo state first
+        self.mark_modified();
+        self.undo_states.push(UndoState {
+            width: self.get_width(),
+            height: self.get_height(),
+            topology: self.get_topology(),
+            rule: self.get_rule().clone(),
+            cells: self.game_state.get_cells().clone(),
+            generation: self.get_generation(),
+        });
+        let mut cells: usize = self.undo_states.iter().map(|state| state.cells.len()).sum();
+        while cells > MAX_UNDO_CELLS && self.undo_states.len() > 1 {
+            cells -= self.undo_states.remove(0).cells.len();
+        }
+    }
 
+    /// Restores the board from before the last edit.
+    pub fn undo(&mut self) -> Result<(), String> {
+        let state = self.undo_states.pop().ok_or("Nothing to undo")?;
+        self.mark_modified();
+        self.replace(
+            state.width,
+            state.height,
+            state.topology,
+            state.rule,
+            state.cells,
+        );
+        self.game_state.set_generation(state.generation);
+        Ok(())
+    }
+
+    /// The states to undo to followed by the board, as patterns with their
+    /// generations. The oldest states are left out past `max_cells` cells.
+    pub fn get_snapshots(&self, max_cells: usize) -> Vec<(Pattern, u64)> {
+        let mut cells = self.game_state.get_cells().len();
+        let history = self
+            .undo_states
+            .iter()
+            .rev()
+            .take_while(|state| {
+                cells += state.cells.len();
+                cells <= max_cells
+            })
+            .collect::<Vec<_>>();
+        history
+            .into_iter()
+            .rev()
+            .map(|state| {
+                let pattern = Pattern {
+                    width: state.width,
+                    height: state.height,
+                    cells: state.cells.clone(),
+                    rule: Some(state.rule.clone()),
+                    topology: Some(state.topology),
+                };
+                (pattern, state.generation)
+            })
+            .chain(std::iter::once((
+                Pattern {
+                    width: self.get_width(),
+                    height: self.get_height(),
+                    cells: self.game_state.get_cells().clone(),
+                    rule: Some(self.get_rule().clone()),
+                    topology: Some(self.get_topology()),
+                },
+                self.get_generation(),
+            )))
+            .collect()
+    }
+
+    /// Restores the snapshots of `get_snapshots`, the last becoming the
+    /// board and the others the states to undo to. The board counts as
+    /// modified, since it was not saved.
+    pub fn restore_snapshots(&mut self, snapshots: Vec<(Pattern, u64)>) -> Result<(), String> {
+        let mut states = Vec::with_capacity(snapshots.len());
+        for (pattern, generation) in snapshots {
+            let topology = pattern.topology.unwrap_or(Topology::Plane);
+            let rule = pattern.rule.unwrap_or_default();
+            ConwayGameState::validate_size(pattern.width, pattern.height, topology)?;
+            if pattern.cells.len() != pattern.width * pattern.height
+                || pattern
+                    .cells
+                    .iter()
+                    .any(|&cell| cell as usize >= rule.get_states())
+            {
+                return Err("Invalid snapshot".to_string());
+            }
+            states.push(UndoState {
+                width: pattern.width,
+                height: pattern.height,
+                topology,
+                rule,
+                cells: pattern.cells,
+                generation,
+            });
+        }
+        let board = states.pop().ok_or("No snapshots to restore")?;
+        self.undo_states = states;
+        self.replace(
+            board.width,
+            board.height,
+            board.topology,
+            board.rule,
+            board.cells,
+        );
+        self.game_state.set_generation(board.generation);
+        self.mark_modified();
         Ok(())
     }
 
+    fn has_extension(path: &Path, extension: &str) -> bool {
+        path.extension()
+            .is_some_and(|other| other.eq_ignore_ascii_case(extension))
+    }
+
     pub fn clear(&mut self) {
+        self.save_undo_state();
         self.game_state.clear();
     }
 
-    fn get_cell_index(&self, x: i32, y: i32) -> (usize, usize) {
-        let cell_width = self.view_bound.width() as f32 / self.game_state.get_width() as f32;
-        let cell_height = self.view_bound.height() as f32 / self.game_state.get_height() as f32;
-        let x = ((x - self.view_bound.x()) as f32 / cell_width) as usize;
-        let y = ((y - self.view_bound.y()) as f32 / cell_height) as usize;
-        (x, y)
+    /// Starts the random fills over from a seed.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
     }
 
-    fn map_cells_to_rect(&self, cells: Vec<(usize, usize)>, offset: bool) -> Vec<Rect> {
-        cells
-            .iter()
-            .map(|(x, y)| {
-                if offset {
-                    Rect::new(
-                        (x * self.cell_length) as i32 + self.render_bound.x(),
-                        (y * self.cell_length) as i32 + self.render_bound.y(),
-                        self.cell_length as u32,
-                        self.cell_length as u32,
-                    )
+    /// Fills the board with random live cells.
+    pub fn randomize(&mut self) {
+        self.save_undo_state();
+        let cells = (0..self.get_width() * self.get_height())
+            .map(|_| {
+                if self.rng.gen::<f64>() < RANDOM_FILL_DENSITY {
+                    ALIVE
                 } else {
-                    Rect::new(
-                        (x * self.cell_length) as i32,
-                        (y * self.cell_length) as i32,
-                        self.cell_length as u32,
-                        self.cell_length as u32,
-                    )
+                    DEAD
                 }
             })
-            .collect()
+            .collect();
+        self.game_state.set_cells(cells);
+    }
+
+    /// Zooms in or out by a factor of two, keeping the cell under the mouse
+    /// in place.
+    pub fn zoom(&mut self, zoom_in: bool) {
+        let zoom = if zoom_in {
+            (self.zoom * 2).min(MAX_ZOOM)
+        } else {
+            (self.zoom / 2).max(1)
+        };
+        let (focus_x, focus_y) = self
+            .mouse_position
+            .unwrap_or(self.view_bound.center().into());
+        let center = self.render_bound.center();
+        let factor = zoom as f32 / self.zoom as f32;
+        let view_center = self.view_bound.center();
+        self.pan = (
+            focus_x + ((center.x() - focus_x) as f32 * factor) as i32 - view_center.x(),
+            focus_y + ((center.y() - focus_y) as f32 * factor) as i32 - view_center.y(),
+        );
+        self.zoom = zoom;
+        self.update_render_bound();
+        self.hovering_cell = None;
     }
 
-    fn render_cells(
+    fn is_hexagonal(&self) -> bool {
+        self.game_state.get_rule().get_neighborhood().is_hexagonal()
+    }
+
+    fn get_cell_size(&self) -> (f32, f32) {
+        let columns =
+            self.game_state.get_width() as f32 + if self.is_hexagonal() { 0.5 } else { 0.0 };
+        (
+            self.render_bound.width() as f32 / columns,
+            self.render_bound.height() as f32 / self.game_state.get_height() as f32,
+        )
+    }
+
+    /// The horizontal shift of a row, odd rows of a hexagonal board are
+    /// shifted right by half a cell.
+    fn get_row_shift(&self, y: usize) -> f32 {
+        if self.is_hexagonal() && y % 2 == 1 {
+            self.get_cell_size().0 / 2.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether a point is on the visible part of the board.
+    fn is_on_board(&self, x: i32, y: i32) -> bool {
+        self.view_bound.contains_point((x, y)) && self.render_bound.contains_point((x, y))
+    }
+
+    fn get_cell_index(&self, x: i32, y: i32) -> (usize, usize) {
+        let (cell_width, cell_height) = self.get_cell_size();
+        let y = (((y - self.render_bound.y()) as f32 / cell_height) as usize)
+            .min(self.game_state.get_height() - 1);
+        let x = ((x - self.render_bound.x()) as f32 - self.get_row_shift(y)).max(0.0);
+        let x = ((x / cell_width) as usize).min(self.game_state.get_width() - 1);
+        (x, y)
+    }
+
+    fn get_cell_rect(&self, x: usize, y: usize) -> Rect {
+        let (cell_width, cell_height) = self.get_cell_size();
+        Rect::new(
+            self.render_bound.x() + (x as f32 * cell_width + self.get_row_shift(y)) as i32,
+            self.render_bound.y() + (y as f32 * cell_height) as i32,
+            (cell_width as u32).max(1),
+            (cell_height as u32).max(1),
+        )
+    }
+
+    /// Fits the board into the view, keeping cells square, and scales it by
+    /// the zoom. Cells get an integer side length when they are at least one
+    /// pixel wide.
+    fn update_render_bound(&mut self) {
+        let width = self.game_state.get_width() as f32;
+        let height = self.game_state.get_height() as f32;
+        let columns = width + if self.is_hexagonal() { 0.5 } else { 0.0 };
+
+        let scale = (self.view_bound.width() as f32 / columns)
+            .min(self.view_bound.height() as f32 / height);
+        let scale = if scale >= 1.0 { scale.floor() } else { scale };
+
+        let render_width = ((scale * columns) as u32).max(1) * self.zoom;
+        let render_height = ((scale * height) as u32).max(1) * self.zoom;
+
+        // A zoomed board always covers the view
+        let clamp_pan = |pan: i32, size: u32, view_size: u32| {
+            let max_pan = (size.saturating_sub(view_size) / 2) as i32;
+            pan.clamp(-max_pan, max_pan)
+        };
+        self.pan = (
+            clamp_pan(self.pan.0, render_width, self.view_bound.width()),
+            clamp_pan(self.pan.1, render_height, self.view_bound.height()),
+        );
+        self.render_bound = Rect::from_center(
+            self.view_bound.center().offset(self.pan.0, self.pan.1),
+            render_width,
+            render_height,
+        );
+
+        // Boards with more cells than pixels are drawn from a texture with
+        // one texel per pixel, so no cells are skipped by the scaling
+        let texture_size = (
+            self.game_state
+                .get_width()
+                .min(self.render_bound.width() as usize),
+            self.game_state
+                .get_height()
+                .min(self.render_bound.height() as usize),
+        );
+        if texture_size != self.texture_size {
+            self.texture_size = texture_size;
+            self.pixels = vec![0; texture_size.0 * texture_size.1 * 3];
+            self.destroy_texture();
+        }
+    }
+
+    fn is_downsampled(&self) -> bool {
+        self.texture_size != (self.game_state.get_width(), self.game_state.get_height())
+    }
+
+    /// The cells covered by a texel, as ranges of columns and rows.
+    fn get_texel_cells(
         &self,
-        cells: Vec<(usize, usize)>,
-        color: Color,
-        offset: bool,
-        texture_target: &mut Canvas<Window>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let rects = self.map_cells_to_rect(cells, offset);
-        texture_target.set_draw_color(color);
-        texture_target.fill_rects(&rects)?;
+        x: usize,
+        y: usize,
+    ) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let (width, height) = (self.game_state.get_width(), self.game_state.get_height());
+        let (texture_width, texture_height) = self.texture_size;
+        (
+            (x * width).div_ceil(texture_width)..((x + 1) * width).div_ceil(texture_width),
+            (y * height).div_ceil(texture_height)..((y + 1) * height).div_ceil(texture_height),
+        )
+    }
+
+    fn get_cell_color(&self, idx: usize) -> Color {
+        let state = self.game_state.get_cells()[idx];
+        let prev_state = self.game_state.get_prev_cells()[idx];
+        // Rule tables may bring their own palette
+        if let Some((r, g, b)) = self
+            .game_state
+            .get_rule()
+            .get_table()
+            .and_then(|table| table.get_color(state))
+        {
+            return Color::RGB(r, g, b);
+        }
+        match (state, prev_state) {
+            (ALIVE, ALIVE) => BOARD_CELL_COLOR,
+            (ALIVE, _) => BOARD_NEWLY_ALIVE_CELL_COLOR,
+            (DEAD, ALIVE) => BOARD_NEWLY_DEAD_CELL_COLOR,
+            (DEAD, _) => BOARD_BACKGROUND_COLOR,
+            (state, _) => {
+                // Fade the dying states from the first to the last color
+                let states = self.game_state.get_rule().get_states();
+                let t = (state - 2) as f32 / (states - 3).max(1) as f32;
+                let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+                let (first, last) = (BOARD_DYING_CELL_FIRST_COLOR, BOARD_DYING_CELL_LAST_COLOR);
+                Color::RGB(
+                    lerp(first.r, last.r),
+                    lerp(first.g, last.g),
+                    lerp(first.b, last.b),
+                )
+            }
+        }
+    }
+
+    /// Paints a texel with the color of the first live cell it covers, or
+    /// of its first cell if they are all dead.
+    fn paint_texel(&mut self, x: usize, y: usize) {
+        let width = self.game_state.get_width();
+        let (columns, rows) = self.get_texel_cells(x, y);
+        let first = rows.start * width + columns.start;
+        let cells = self.game_state.get_cells();
+        let idx = rows
+            .flat_map(|y| columns.clone().map(move |x| y * width + x))
+            .find(|&idx| cells[idx] != DEAD)
+            .unwrap_or(first);
+        let (r, g, b) = self.get_cell_color(idx).rgb();
+        let texel = (y * self.texture_size.0 + x) * 3;
+        self.pixels[texel..texel + 3].copy_from_slice(&[r, g, b]);
+    }
+
+    /// Repaints the cells changed since the last frame and uploads the
+    /// smallest region of the texture covering them.
+    fn update_texture(
+        &mut self,
+        texture_creator: &TextureCreator<WindowContext>,
+    ) -> Result<(), ConwayError> {
+        let board_width = self.game_state.get_width();
+        let board_height = self.game_state.get_height();
+        let (width, height) = self.texture_size;
+
+        let dirty_cells = if self.texture.is_none() {
+            // Drop the pending dirty set, the whole texture is uploaded anyway
+            self.game_state.take_dirty_cells();
+            DirtyCells::All
+        } else {
+            self.game_state.take_dirty_cells()
+        };
+
+        let update_rect = match dirty_cells {
+            DirtyCells::All => {
+                for y in 0..height {
+                    for x in 0..width {
+                        self.paint_texel(x, y);
+                    }
+                }
+                Rect::new(0, 0, width as u32, height as u32)
+            }
+            DirtyCells::Cells(cells) => {
+                if cells.is_empty() {
+                    return Ok(());
+                }
+                let (mut min_x, mut min_y, mut max_x, mut max_y) = (width, height, 0, 0);
+                let mut texels = Vec::with_capacity(cells.len());
+                for idx in cells {
+                    let x = idx % board_width * width / board_width;
+                    let y = idx / board_width * height / board_height;
+                    texels.push((x, y));
+                }
+                // Neighboring cells often share a texel when downsampled
+                texels.sort_unstable();
+                texels.dedup();
+                for (x, y) in texels {
+                    self.paint_texel(x, y);
+                    min_x = min_x.min(x);
+                    min_y = min_y.min(y);
+                    max_x = max_x.max(x);
+                    max_y = max_y.max(y);
+                }
+                Rect::new(
+                    min_x as i32,
+                    min_y as i32,
+                    (max_x - min_x + 1) as u32,
+                    (max_y - min_y + 1) as u32,
+                )
+            }
+        };
+
+        if self.texture.is_none() {
+            let texture = texture_creator
+                .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+                .map_err(|e| ConwayError::Sdl(e.to_string()))?;
+            self.texture = Some(texture);
+        }
+
+        let offset = (update_rect.y() as usize * width + update_rect.x() as usize) * 3;
+        self.texture
+            .as_mut()
+            .unwrap()
+            .update(update_rect, &self.pixels[offset..], width * 3)
+            .map_err(|e| ConwayError::Sdl(e.to_string()))?;
         Ok(())
     }
+
+    fn destroy_texture(&mut self) {
+        let texture = self.texture.take();
+        if let Some(texture) = texture {
+            unsafe {
+                texture.destroy();
+            }
+        }
+    }
+}
+
+impl Drop for BoardView {
+    fn drop(&mut self) {
+        self.destroy_texture();
+    }
 }
 
 impl View for BoardView {
@@ -216,48 +936,59 @@ impl View for BoardView {
         texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
         _font_manager: &mut crate::engine::resource_manager::FontManager,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let mut texture = texture_creator.create_texture_target(
-            None,
-            self.render_bound.width(),
-            self.render_bound.height(),
-        )?;
-
-        canvas.with_texture_canvas(&mut texture, |texture_target| {
-            texture_target.set_draw_color(BOARD_BACKGROUND_COLOR);
-            texture_target.clear();
-
-            let _ = self.render_cells(
-                self.game_state.get_retained_cells(),
-                BOARD_CELL_COLOR,
-                false,
-                texture_target,
-            );
-            let _ = self.render_cells(
-                self.game_state.get_newly_born_cells(),
-                BOARD_NEWLY_ALIVE_CELL_COLOR,
-                false,
-                texture_target,
-            );
-            let _ = self.render_cells(
-                self.game_state.get_newly_dead_cells(),
-                BOARD_NEWLY_DEAD_CELL_COLOR,
-                false,
-                texture_target,
-            );
-
-            if let Some((x, y)) = self.hovering_cell {
-                let color = if self.game_state.get_cell(x, y) {
-                    BOARD_HOVERING_ALIVE_CELL_COLOR
-                } else {
-                    BOARD_HOVERING_DEAD_CELL_COLOR
-                };
-                let _ = self.render_cells(vec![(x, y)], color, false, texture_target);
+        self.update_texture(texture_creator)?;
+        canvas.set_clip_rect(self.view_bound);
+
+        let texture = self.texture.as_ref().unwrap();
+        // Rows of a downsampled texture mix cells of both row shifts
+        if self.is_hexagonal() && !self.is_downsampled() {
+            // Copy row by row to shift the odd rows
+            let width = self.game_state.get_width();
+            let (cell_width, cell_height) = self.get_cell_size();
+            for y in 0..self.game_state.get_height() {
+                let top = (y as f32 * cell_height) as i32;
+                let bottom = ((y + 1) as f32 * cell_height) as i32;
+                canvas.copy(
+                    texture,
+                    Rect::new(0, y as i32, width as u32, 1),
+                    Rect::new(
+                        self.render_bound.x() + self.get_row_shift(y) as i32,
+                        self.render_bound.y() + top,
+                        ((width as f32 * cell_width) as u32).max(1),
+                        ((bottom - top) as u32).max(1),
+                    ),
+                )?;
             }
-        })?;
-        canvas.copy(&texture, None, self.view_bound)?;
-        unsafe {
-            texture.destroy();
+        } else {
+            canvas.copy(texture, None, self.render_bound)?;
+        }
+
+        if let Some(region) = self.get_selection() {
+            // The corners of hexagonal boards are in rows shifted apart
+            let bound = [
+                (region.min_x, region.min_y),
+                (region.max_x, region.min_y),
+                (region.min_x, region.max_y),
+                (region.max_x, region.max_y),
+            ]
+            .into_iter()
+            .map(|(x, y)| self.get_cell_rect(x, y))
+            .reduce(|bound, rect| bound.union(rect))
+            .unwrap();
+            canvas.set_draw_color(BOARD_SELECTION_COLOR);
+            canvas.draw_rect(bound)?;
         }
+
+        if let Some((x, y)) = self.hovering_cell {
+            let color = if self.game_state.get_cell(x, y) != DEAD {
+                BOARD_HOVERING_ALIVE_CELL_COLOR
+            } else {
+                BOARD_HOVERING_DEAD_CELL_COLOR
+            };
+            canvas.set_draw_color(color);
+            canvas.fill_rect(self.get_cell_rect(x, y))?;
+        }
+        canvas.set_clip_rect(None);
         Ok(())
     }
 
@@ -270,19 +1001,51 @@ impl View for BoardView {
     }
 
     fn on_mouse_motion(&mut self, x: i32, y: i32) {
-        if self.view_bound.contains_point((x, y)) {
+        // The selection is clamped to the board when dragged past its edges
+        if let (true, Some((start, _))) = (self.selecting, self.selection) {
+            self.selection = Some((start, self.get_cell_index(x, y)));
+        }
+        if self.is_on_board(x, y) {
             self.hovering_cell = Some(self.get_cell_index(x, y));
+            self.mouse_position = Some((x, y));
         } else {
             self.hovering_cell = None;
+            self.mouse_position = None;
         }
     }
 
     fn on_mouse_button_down(&mut self, button: sdl2::mouse::MouseButton, x: i32, y: i32) {
-        if self.view_bound.contains_point((x, y)) {
+        if self.is_on_board(x, y) {
             if let sdl2::mouse::MouseButton::Left = button {
                 let (x, y) = self.get_cell_index(x, y);
-                self.game_state
-                    .set_cell(x, y, !self.game_state.get_cell(x, y));
+                let state = self.game_state.get_cell(x, y);
+                // Rule tables have no dying states, so clicks cycle through all
+                let rule = self.game_state.get_rule();
+                let state = if rule.get_table().is_some() {
+                    ((state as usize + 1) % rule.get_states()) as u8
+                } else if state == DEAD {
+                    ALIVE
+                } else {
+                    DEAD
+                };
+                self.save_undo_state();
+                self.game_state.set_cell(x, y, state);
+            } else if let sdl2::mouse::MouseButton::Right = button {
+                let cell = self.get_cell_index(x, y);
+                self.selection = Some((cell, cell));
+                self.selecting = true;
+            }
+        }
+    }
+
+    fn on_mouse_button_up(&mut self, button: sdl2::mouse::MouseButton, _x: i32, _y: i32) {
+        if let (sdl2::mouse::MouseButton::Right, true) = (button, self.selecting) {
+            self.selecting = false;
+            // A right click without dragging clears the selection
+            if let Some((start, corner)) = self.selection {
+                if start == corner {
+                    self.selection = None;
+                }
             }
         }
     }
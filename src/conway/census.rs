@@ -0,0 +1,397 @@
+//! Censuses of the objects a board settles into, as apgsearch takes of
+//! soups: the board is run until it repeats itself, and what is left is
+//! split into objects that are named by their apgcodes and counted.
+
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt::Write,
+    hash::{Hash, Hasher},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use super::config::Config;
+use super::error::ConwayError;
+use super::export;
+use super::game_state::{ConwayGameState, DEAD};
+use super::pattern::apgcode::{self, Kind, Object};
+use super::pattern::Pattern;
+
+/// The most generations a board is run for to settle.
+pub const MAX_GENERATIONS: u64 = 100_000;
+
+/// How many times an object was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tally {
+    pub code: String,
+    pub kind: Kind,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Census {
+    pub rule: String,
+    /// The first generation the board repeats from.
+    pub settled: u64,
+    /// How many generations the board takes to repeat itself.
+    pub period: u64,
+    /// The objects found, the most common first.
+    pub tallies: Vec<Tally>,
+    /// Groups of cells that are not periodic on their own.
+    pub unidentified: usize,
+}
+
+impl Census {
+    /// The number of objects found.
+    pub fn get_total(&self) -> usize {
+        self.tallies.iter().map(|tally| tally.count).sum()
+    }
+}
+
+/// A census in progress, run a few generations at a time so the window
+/// stays responsive.
+pub struct CensusRun {
+    // The board as the census started, to replay generations from
+    start: ConwayGameState,
+    state: ConwayGameState,
+    // The generations seen so far by the hash of their cells
+    seen: HashMap<u64, Vec<u64>>,
+}
+
+impl CensusRun {
+    /// Starts from a copy of the board, if its rule allows censuses.
+    pub fn new(state: &ConwayGameState) -> Result<CensusRun, ConwayError> {
+        let rule = state.get_rule();
+        if rule.get_states() != 2 {
+            return Err(ConwayError::Validation(
+                "Censuses need a rule with two states".to_string(),
+            ));
+        }
+        if rule.get_neighborhood().is_hexagonal() {
+            return Err(ConwayError::Validation(
+                "Censuses need a square grid".to_string(),
+            ));
+        }
+        let state = export::copy_state(state);
+        let seen = HashMap::from([(hash_cells(&state), vec![state.get_generation()])]);
+        Ok(CensusRun {
+            start: export::copy_state(&state),
+            state,
+            seen,
+        })
+    }
+
+    /// How many generations the board has been run for.
+    pub fn get_generations(&self) -> u64 {
+        self.state.get_generation() - self.start.get_generation()
+    }
+
+    /// Runs the board for at most `budget`, and counts its objects once it
+    /// comes back to a generation seen before.
+    pub fn advance(&mut self, budget: Duration) -> Result<Option<Census>, ConwayError> {
+        let started = Instant::now();
+        while started.elapsed() < budget {
+            if self.get_generations() >= MAX_GENERATIONS {
+                return Err(ConwayError::Validation(format!(
+                    "The board does not settle within {} generations",
+                    MAX_GENERATIONS
+                )));
+            }
+            self.state.step();
+            let generation = self.state.get_generation();
+            let hash = hash_cells(&self.state);
+            // Hashes may collide, so generations with the same hash are
+            // compared cell by cell
+            let previous = self.seen.get(&hash).and_then(|generations| {
+                generations
+                    .iter()
+                    .copied()
+                    .find(|&previous| self.replay(previous) == *self.state.get_cells())
+            });
+            if let Some(previous) = previous {
+                return Ok(Some(self.count(previous, generation - previous)));
+            }
+            self.seen.entry(hash).or_default().push(generation);
+        }
+        Ok(None)
+    }
+
+    /// The cells of a generation, run again from the start.
+    fn replay(&self, generation: u64) -> Vec<u8> {
+        let mut state = export::copy_state(&self.start);
+        while state.get_generation() < generation {
+            state.step();
+        }
+        state.get_cells().clone()
+    }
+
+    fn count(&mut self, settled: u64, period: u64) -> Census {
+        let state = &mut self.state;
+        // Objects are split by the cells they cover over a whole period, so
+        // the phases of an oscillator stay together
+        let mut envelope = vec![false; state.get_cells().len()];
+        for _ in 0..period {
+            for (covered, &cell) in envelope.iter_mut().zip(state.get_cells()) {
+                *covered |= cell != DEAD;
+            }
+            state.step();
+        }
+
+        let mut tallies: HashMap<String, Tally> = HashMap::new();
+        let mut count = |object: Object| {
+            tallies
+                .entry(object.code.clone())
+                .or_insert(Tally {
+                    code: object.code,
+                    kind: object.kind,
+                    count: 0,
+                })
+                .count += 1;
+        };
+        // Objects whose cells do not touch, like the aircraft carrier, are
+        // identified again with the cells around them
+        let mut unidentified = vec![false; envelope.len()];
+        for component in get_components(state, &envelope, 1) {
+            match identify(state, &component) {
+                Ok(object) => count(object),
+                Err(_) => {
+                    for &(_, i) in &component {
+                        unidentified[i] = true;
+                    }
+                }
+            }
+        }
+        let mut failures = 0;
+        for component in get_components(state, &unidentified, 2) {
+            match identify(state, &component) {
+                Ok(object) => count(object),
+                Err(_) => failures += 1,
+            }
+        }
+
+        let mut tallies: Vec<Tally> = tallies.into_values().collect();
+        tallies.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.code.cmp(&b.code)));
+        Census {
+            rule: state.get_rule().to_string(),
+            settled,
+            period,
+            tallies,
+            unidentified: failures,
+        }
+    }
+}
+
+/// Runs a copy of the board until it repeats itself and counts its objects.
+pub fn take(state: &ConwayGameState) -> Result<Census, ConwayError> {
+    let mut run = CensusRun::new(state)?;
+    loop {
+        if let Some(census) = run.advance(Duration::MAX)? {
+            return Ok(census);
+        }
+    }
+}
+
+fn hash_cells(state: &ConwayGameState) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    state.get_cells().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Groups the marked cells with those at most `radius` cells away, across
+/// the edges the topology joins. Each cell comes with where it is relative
+/// to the first cell of its group, so groups crossing an edge stay whole.
+fn get_components(
+    state: &ConwayGameState,
+    marked: &[bool],
+    radius: isize,
+) -> Vec<Vec<((isize, isize), usize)>> {
+    let width = state.get_width();
+    let mut visited = vec![false; marked.len()];
+    let mut components = Vec::new();
+    for start in 0..marked.len() {
+        if !marked[start] || visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut component = vec![((0, 0), start)];
+        let mut next = 0;
+        while let Some(&((x, y), i)) = component.get(next) {
+            next += 1;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let Some(neighbor) = state.get_neighbor_index(i % width, i / width, dx, dy)
+                    else {
+                        continue;
+                    };
+                    if marked[neighbor] && !visited[neighbor] {
+                        visited[neighbor] = true;
+                        component.push(((x + dx, y + dy), neighbor));
+                    }
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+/// Names the live cells of a group as they are now.
+fn identify(
+    state: &ConwayGameState,
+    component: &[((isize, isize), usize)],
+) -> Result<Object, String> {
+    let left = component.iter().map(|&((x, _), _)| x).min().unwrap_or(0);
+    let top = component.iter().map(|&((_, y), _)| y).min().unwrap_or(0);
+    let width = component
+        .iter()
+        .map(|&((x, _), _)| x - left + 1)
+        .max()
+        .unwrap_or(0) as usize;
+    let height = component
+        .iter()
+        .map(|&((_, y), _)| y - top + 1)
+        .max()
+        .unwrap_or(0) as usize;
+    let mut cells = vec![DEAD; width * height];
+    for &((x, y), i) in component {
+        cells[(y - top) as usize * width + (x - left) as usize] = state.get_cells()[i];
+    }
+    let pattern = Pattern {
+        width,
+        height,
+        cells,
+        rule: None,
+        topology: None,
+    };
+    apgcode::identify(&pattern, state.get_rule())
+}
+
+/// Fills a board randomly unless a file is given, takes its census and
+/// writes it to the path, without opening a window. Fills without a seed
+/// get a random one, which the census records.
+pub fn census_headless(config: &Config, path: &Path) -> Result<Census, ConwayError> {
+    let seed = match config.get_file() {
+        Some(_) => None,
+        None => Some(config.get_seed().unwrap_or_else(rand::random)),
+    };
+    let board = export::load_headless(config, seed)?;
+    let census = take(board.get_game_state())?;
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    let content = match extension.as_deref() {
+        Some("json") => write_json(&census, seed),
+        Some("csv") => write_csv(&census),
+        _ => {
+            return Err(ConwayError::Validation(format!(
+                "Censuses are written to .json or .csv files, not {}",
+                path.display()
+            )))
+        }
+    };
+    std::fs::write(path, content).map_err(ConwayError::io(path))?;
+    Ok(census)
+}
+
+fn write_json(census: &Census, seed: Option<u64>) -> String {
+    // Writing to a string cannot fail
+    let mut json = String::new();
+    json.push_str("{\n");
+    writeln!(json, "  \"rule\": {},", quote(&census.rule)).unwrap();
+    if let Some(seed) = seed {
+        writeln!(json, "  \"seed\": {},", seed).unwrap();
+    }
+    writeln!(json, "  \"settled\": {},", census.settled).unwrap();
+    writeln!(json, "  \"period\": {},", census.period).unwrap();
+    json.push_str("  \"objects\": [");
+    for (i, tally) in census.tallies.iter().enumerate() {
+        write!(
+            json,
+            "{}\n    {{\"apgcode\": {}, \"kind\": {}, \"count\": {}}}",
+            if i > 0 { "," } else { "" },
+            quote(&tally.code),
+            quote(&tally.kind.get_name().to_ascii_lowercase()),
+            tally.count
+        )
+        .unwrap();
+    }
+    if !census.tallies.is_empty() {
+        json.push_str("\n  ");
+    }
+    json.push_str("],\n");
+    writeln!(json, "  \"unidentified\": {}", census.unidentified).unwrap();
+    json.push_str("}\n");
+    json
+}
+
+/// Quotes a string for JSON.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => write!(quoted, "\\u{:04x}", c as u32).unwrap(),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A row per object, and a last row counting the unidentified groups of
+/// cells if there are any. Apgcodes and kinds need no quoting.
+fn write_csv(census: &Census) -> String {
+    let mut csv = String::from("apgcode,kind,count\n");
+    for tally in &census.tallies {
+        writeln!(
+            csv,
+            "{},{},{}",
+            tally.code,
+            tally.kind.get_name().to_ascii_lowercase(),
+            tally.count
+        )
+        .unwrap();
+    }
+    if census.unidentified > 0 {
+        writeln!(csv, "unidentified,,{}", census.unidentified).unwrap();
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conway::game_state::ALIVE;
+    use crate::conway::topology::Topology;
+
+    #[test]
+    fn counts_a_block_and_a_blinker() {
+        let mut state = ConwayGameState::new(32, 32, Topology::Plane, "B3/S23".parse().unwrap());
+        for (x, y) in [(4, 4), (5, 4), (4, 5), (5, 5), (20, 20), (21, 20), (22, 20)] {
+            state.set_cell(x, y, ALIVE);
+        }
+        let census = take(&state).unwrap();
+        assert_eq!((census.settled, census.period), (0, 2));
+        let tallies: Vec<(&str, usize)> = census
+            .tallies
+            .iter()
+            .map(|tally| (tally.code.as_str(), tally.count))
+            .collect();
+        assert_eq!(tallies, [("xp2_7", 1), ("xs4_33", 1)]);
+        assert_eq!(census.unidentified, 0);
+    }
+
+    #[test]
+    fn runs_a_few_generations_at_a_time() {
+        let mut state = ConwayGameState::new(32, 32, Topology::Plane, "B3/S23".parse().unwrap());
+        // An R-pentomino runs for a while before it settles
+        for (x, y) in [(15, 14), (16, 14), (14, 15), (15, 15), (15, 16)] {
+            state.set_cell(x, y, ALIVE);
+        }
+        let mut run = CensusRun::new(&state).unwrap();
+        assert!(run.advance(Duration::ZERO).unwrap().is_none());
+        assert_eq!(run.get_generations(), 0);
+        assert!(take(&state).unwrap().settled > 0);
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::Duration,
+};
+
+use sdl2::{keyboard::Keycode, mouse::MouseButton, rect::Rect};
+
+use crate::engine::{
+    resource_manager::FontManager,
+    view::{TextView, View, ViewChain},
+};
+use crate::fonts;
+
+use super::census::{Census, CensusRun, MAX_GENERATIONS};
+use super::error::ConwayError;
+use super::game_state::ConwayGameState;
+use super::theme::Theme;
+use super::toast::ToastView;
+
+const ROW_HEIGHT: i32 = 24;
+const KIND_WIDTH: u32 = 130;
+const COUNT_WIDTH: u32 = 70;
+/// Longer apgcodes are cut short to fit their column.
+const MAX_CODE_LENGTH: usize = 40;
+
+/// An overlay with a table of the objects of a census, shown while
+/// `visible` is set. Up and Down scroll the table, and any other key or
+/// click closes it. Censuses are taken over several frames with their
+/// progress in a toast, and the table opens once they are done.
+pub struct CensusView {
+    bound: Rect,
+    visible: Rc<Cell<bool>>,
+    theme: Theme,
+    toasts: Rc<RefCell<ToastView>>,
+    components: ViewChain,
+    run: Option<CensusRun>,
+    census: Option<Census>,
+    // The first row shown
+    scroll: usize,
+}
+
+impl CensusView {
+    pub fn new(
+        bound: Rect,
+        visible: Rc<Cell<bool>>,
+        theme: Theme,
+        toasts: Rc<RefCell<ToastView>>,
+    ) -> CensusView {
+        CensusView {
+            bound,
+            visible,
+            theme,
+            toasts,
+            components: ViewChain::new(),
+            run: None,
+            census: None,
+            scroll: 0,
+        }
+    }
+
+    /// Starts a census of a copy of the board.
+    pub fn start(&mut self, state: &ConwayGameState) -> Result<(), ConwayError> {
+        self.run = Some(CensusRun::new(state)?);
+        self.toasts.borrow_mut().progress("Taking a census");
+        Ok(())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    /// Stops the census in progress.
+    pub fn cancel(&mut self) {
+        if self.run.take().is_some() {
+            self.toasts.borrow_mut().end_progress();
+        }
+    }
+
+    /// Advances the census in progress for at most `budget`, and shows it
+    /// once it is done.
+    pub fn advance(&mut self, budget: Duration) {
+        let Some(run) = self.run.as_mut() else {
+            return;
+        };
+        let advanced = run.advance(budget);
+        let generations = run.get_generations();
+        match advanced {
+            Ok(None) => {
+                self.toasts.borrow_mut().progress(format!(
+                    "Taking a census: {} of at most {} generations",
+                    generations, MAX_GENERATIONS
+                ));
+                return;
+            }
+            Ok(Some(census)) => self.open(census),
+            Err(e) => self.toasts.borrow_mut().error(e),
+        }
+        self.run = None;
+        self.toasts.borrow_mut().end_progress();
+    }
+
+    /// Shows a census from its first row.
+    fn open(&mut self, census: Census) {
+        self.census = Some(census);
+        self.scroll = 0;
+        self.update_rows();
+        self.visible.set(true);
+    }
+
+    fn get_rows_top(&self) -> i32 {
+        self.bound.y() + 124
+    }
+
+    /// How many rows fit above the hint at the bottom.
+    fn get_visible_rows(&self) -> usize {
+        ((self.bound.bottom() - 50 - self.get_rows_top()) / ROW_HEIGHT).max(0) as usize
+    }
+
+    /// Rebuilds the texts from the scrolled position.
+    fn update_rows(&mut self) {
+        let Some(census) = &self.census else {
+            return;
+        };
+        let (x, width) = (self.bound.x() + 20, self.bound.width() - 40);
+        let code_width = width - KIND_WIDTH - COUNT_WIDTH;
+        let text = |bound: Rect, text: String, size: u16| {
+            TextView::new(
+                bound,
+                text,
+                fonts::ARK_PIXEL_FONT,
+                size,
+                self.theme.get_text_color(),
+            )
+            .wrap()
+        };
+        // The columns of a row: apgcode, kind and count
+        let columns = |y: i32| {
+            [
+                Rect::new(x, y, code_width, ROW_HEIGHT as u32),
+                Rect::new(x + code_width as i32, y, KIND_WIDTH, ROW_HEIGHT as u32),
+                Rect::new(
+                    x + (code_width + KIND_WIDTH) as i32,
+                    y,
+                    COUNT_WIDTH,
+                    ROW_HEIGHT as u32,
+                ),
+            ]
+        };
+
+        self.components = ViewChain::new();
+        self.components.add_view(text(
+            Rect::new(x, self.bound.y() + 20, width, 40),
+            "Census".to_string(),
+            26,
+        ));
+        let mut summary = format!(
+            "{} objects in {}, settled at generation {} with period {}",
+            census.get_total(),
+            census.rule,
+            census.settled,
+            census.period
+        );
+        if census.unidentified > 0 {
+            summary.push_str(&format!(", {} unidentified", census.unidentified));
+        }
+        self.components.add_view(text(
+            Rect::new(x, self.bound.y() + 64, width, 20),
+            summary,
+            14,
+        ));
+
+        let header = ["apgcode", "Kind", "Count"];
+        for (bound, label) in columns(self.get_rows_top() - 30).into_iter().zip(header) {
+            self.components.add_view(text(bound, label.to_string(), 18));
+        }
+        if census.tallies.is_empty() {
+            self.components.add_view(text(
+                Rect::new(x, self.get_rows_top(), width, 24),
+                "No objects".to_string(),
+                18,
+            ));
+        }
+        let rows = census
+            .tallies
+            .iter()
+            .skip(self.scroll)
+            .take(self.get_visible_rows());
+        for (i, tally) in rows.enumerate() {
+            let code = if tally.code.len() > MAX_CODE_LENGTH {
+                format!("{}...", &tally.code[..MAX_CODE_LENGTH - 3])
+            } else {
+                tally.code.clone()
+            };
+            let texts = [
+                code,
+                tally.kind.get_name().to_string(),
+                tally.count.to_string(),
+            ];
+            let y = self.get_rows_top() + i as i32 * ROW_HEIGHT;
+            for (bound, label) in columns(y).into_iter().zip(texts) {
+                self.components.add_view(text(bound, label, 14));
+            }
+        }
+        self.components.add_view(text(
+            Rect::new(x, self.bound.bottom() - 38, width, 18),
+            "Up/Down: scroll  Any other key: close".to_string(),
+            12,
+        ));
+    }
+
+    fn scroll_by(&mut self, rows: isize) {
+        let Some(census) = &self.census else {
+            return;
+        };
+        let last = census.tallies.len().saturating_sub(self.get_visible_rows());
+        let scroll = self.scroll.saturating_add_signed(rows).min(last);
+        if scroll != self.scroll {
+            self.scroll = scroll;
+            self.update_rows();
+        }
+    }
+}
+
+impl View for CensusView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.visible.get() {
+            return Ok(());
+        }
+        canvas.set_draw_color(self.theme.get_background_color());
+        canvas.fill_rect(self.bound)?;
+        canvas.set_draw_color(self.theme.get_text_color());
+        canvas.draw_rect(self.bound)?;
+        self.components
+            .render(canvas, texture_creator, font_manager)
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.bound
+    }
+
+    fn on_key_down(&mut self, key: Keycode) {
+        match key {
+            Keycode::Up => self.scroll_by(-1),
+            Keycode::Down => self.scroll_by(1),
+            Keycode::PageUp => self.scroll_by(-(self.get_visible_rows() as isize)),
+            Keycode::PageDown => self.scroll_by(self.get_visible_rows() as isize),
+            _ => self.visible.set(false),
+        }
+    }
+
+    fn on_mouse_button_down(&mut self, _button: MouseButton, _x: i32, _y: i32) {
+        self.visible.set(false);
+    }
+}
@@ -0,0 +1,490 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use clap::{CommandFactory, Parser};
+use toml_edit::{value, Document};
+
+use super::args::ConwayArgs;
+use super::error::ConwayError;
+use super::export::{ExportOptions, DEFAULT_CELL_SIZE, DEFAULT_FRAMES, DEFAULT_FRAME_DELAY};
+use super::game_state::ConwayGameState;
+use super::keymap::Keymap;
+use super::rule::Rule;
+use super::theme::Theme;
+use super::topology::Topology;
+
+/// Where the config file is, under the user's config directory.
+const CONFIG_PATH: [&str; 2] = ["conway-rs", "config.toml"];
+const DEFAULT_BOARD_SIZE: usize = 32;
+const DEFAULT_SPEED: f64 = 1.0;
+/// The window must fit the sidebars and a board between them.
+pub const MIN_WINDOW_WIDTH: u32 = 1000;
+pub const MIN_WINDOW_HEIGHT: u32 = 600;
+
+/// How many generations run per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+    PerSecond(f64),
+    Max,
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("max") {
+            return Ok(Speed::Max);
+        }
+        match s.parse::<f64>() {
+            Ok(speed) if speed.is_finite() && speed > 0.0 => Ok(Speed::PerSecond(speed)),
+            _ => Err(format!(
+                "The speed must be a positive number or max, not {}",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Speed::PerSecond(speed) => write!(f, "{}", speed),
+            Speed::Max => write!(f, "max"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for WindowSize {
+    fn default() -> Self {
+        WindowSize {
+            width: MIN_WINDOW_WIDTH,
+            height: MIN_WINDOW_HEIGHT,
+        }
+    }
+}
+
+impl FromStr for WindowSize {
+    type Err = String;
+
+    /// Parses `<WIDTH>x<HEIGHT>`, e.g. `1280x720`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once('x')
+            .ok_or_else(|| format!("Invalid window size: {}", s))?;
+        let width: u32 = width
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid window size: {}", s))?;
+        let height: u32 = height
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid window size: {}", s))?;
+        if width < MIN_WINDOW_WIDTH || height < MIN_WINDOW_HEIGHT {
+            return Err(format!(
+                "The window must be at least {}x{}, not {}",
+                MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT, s
+            ));
+        }
+        Ok(WindowSize { width, height })
+    }
+}
+
+impl fmt::Display for WindowSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// The settings remembered between sessions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preferences {
+    pub width: usize,
+    pub height: usize,
+    pub topology: Topology,
+    pub rule: Rule,
+    pub theme: Theme,
+    pub speed: Speed,
+    pub window_size: WindowSize,
+    pub last_directory: Option<PathBuf>,
+    pub recent_files: Vec<PathBuf>,
+}
+
+/// The settings of a session. Each comes from the command line, else the
+/// config file, else its default. The config file is TOML, e.g.
+///
+/// ```toml
+/// width = 64
+/// rule = "B36/S23"
+/// theme = "dark"
+/// speed = "max"
+/// window-size = "1280x720"
+///
+/// [keys]
+/// undo = ["Ctrl+Z", "Backspace"]
+/// ```
+pub struct Config {
+    preferences: Preferences,
+    keymap: Keymap,
+    // How the session starts, only given on the command line
+    file: Option<PathBuf>,
+    paused: bool,
+    generation: Option<u64>,
+    seed: Option<u64>,
+    export_path: Option<PathBuf>,
+    image_path: Option<PathBuf>,
+    screenshot_path: Option<PathBuf>,
+    census_path: Option<PathBuf>,
+    export_options: ExportOptions,
+    // Why values of the config file were ignored
+    warnings: Vec<ConwayError>,
+    path: Option<PathBuf>,
+    // The file as it was read, so writing it back keeps its comments
+    document: Document,
+}
+
+impl Config {
+    /// Reads the command line and the config file. Invalid arguments exit
+    /// with a usage error, while config values that do not parse are
+    /// replaced by their defaults and kept as warnings.
+    pub fn load() -> Config {
+        let args = ConwayArgs::parse();
+        let exit_with = |e: String| -> ! {
+            ConwayArgs::command()
+                .error(clap::error::ErrorKind::ValueValidation, e)
+                .exit()
+        };
+
+        let path = args.config.clone().or_else(|| {
+            dirs::config_dir().map(|dir| CONFIG_PATH.iter().fold(dir, |path, part| path.join(part)))
+        });
+        let document = match &path {
+            Some(path) if path.exists() => Config::read(path).unwrap_or_else(|e| exit_with(e)),
+            // A missing file is created when the preferences are saved
+            _ => Document::new(),
+        };
+
+        let mut warnings = Vec::new();
+        let preferences = Preferences {
+            width: args
+                .width
+                .or_else(|| Config::get(&document, "width", &mut warnings))
+                .unwrap_or(DEFAULT_BOARD_SIZE),
+            height: args
+                .height
+                .or_else(|| Config::get(&document, "height", &mut warnings))
+                .unwrap_or(DEFAULT_BOARD_SIZE),
+            topology: args
+                .topology
+                .or_else(|| Config::get(&document, "topology", &mut warnings))
+                .unwrap_or(Topology::Plane),
+            rule: args
+                .rule
+                .or_else(|| Config::get(&document, "rule", &mut warnings))
+                .unwrap_or_default(),
+            theme: args
+                .theme
+                .or_else(|| Config::get(&document, "theme", &mut warnings))
+                .unwrap_or_default(),
+            speed: args
+                .speed
+                .or_else(|| Config::get(&document, "speed", &mut warnings))
+                .unwrap_or(Speed::PerSecond(DEFAULT_SPEED)),
+            window_size: args
+                .window_size
+                .or_else(|| Config::get(&document, "window-size", &mut warnings))
+                .unwrap_or_default(),
+            last_directory: Config::get(&document, "last-directory", &mut warnings),
+            recent_files: Config::get_paths(&document, "recent-files", &mut warnings),
+        };
+        if let Err(e) = ConwayGameState::validate_size(
+            preferences.width,
+            preferences.height,
+            preferences.topology,
+        ) {
+            exit_with(e);
+        }
+
+        let export_options = ExportOptions {
+            frames: args
+                .frames
+                .or_else(|| Config::get(&document, "frames", &mut warnings))
+                .unwrap_or(DEFAULT_FRAMES),
+            cell_size: args
+                .cell_size
+                .or_else(|| Config::get(&document, "cell-size", &mut warnings))
+                .unwrap_or(DEFAULT_CELL_SIZE),
+            frame_delay: args
+                .frame_delay
+                .or_else(|| Config::get(&document, "frame-delay", &mut warnings))
+                .unwrap_or(DEFAULT_FRAME_DELAY),
+            grid: args.grid,
+            theme: preferences.theme,
+        };
+        if let Err(e) = export_options.validate() {
+            exit_with(e);
+        }
+        if let Some(screenshot) = &args.screenshot {
+            if !screenshot
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+            {
+                exit_with(format!(
+                    "Screenshots are saved as .png files, not {}",
+                    screenshot.display()
+                ));
+            }
+        }
+        if let Some(census) = &args.census {
+            if !census.extension().is_some_and(|extension| {
+                extension.eq_ignore_ascii_case("json") || extension.eq_ignore_ascii_case("csv")
+            }) {
+                exit_with(format!(
+                    "Censuses are written to .json or .csv files, not {}",
+                    census.display()
+                ));
+            }
+        }
+
+        let mut keymap = Keymap::default();
+        match document.get("keys").map(|keys| keys.as_table()) {
+            Some(Some(keys)) => {
+                if let Err(e) = keymap.apply(keys) {
+                    warnings.push(ConwayError::Validation(format!(
+                        "Invalid keys in the config: {}",
+                        e
+                    )));
+                }
+            }
+            Some(None) => warnings.push(ConwayError::Validation(
+                "The keys in the config must be a table".to_string(),
+            )),
+            None => {}
+        }
+        if let Some(keymap_path) = &args.keymap {
+            if let Err(e) = keymap.load(keymap_path) {
+                exit_with(e);
+            }
+        }
+
+        Config {
+            preferences,
+            keymap,
+            file: args.file,
+            paused: args.paused,
+            generation: args.generation,
+            seed: args.seed,
+            export_path: args.export,
+            image_path: args.image,
+            screenshot_path: args.screenshot,
+            census_path: args.census,
+            export_options,
+            warnings,
+            path,
+            document,
+        }
+    }
+
+    fn read(path: &Path) -> Result<Document, String> {
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read config {}: {}", path.display(), e))?
+            .parse::<Document>()
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// Parses a value of the config file, whether it is written as a string
+    /// or a number.
+    fn get<T: FromStr>(document: &Document, key: &str, warnings: &mut Vec<ConwayError>) -> Option<T>
+    where
+        T::Err: fmt::Display,
+    {
+        let text = match document.get(key)?.as_value()? {
+            toml_edit::Value::String(text) => text.value().clone(),
+            toml_edit::Value::Integer(number) => number.value().to_string(),
+            toml_edit::Value::Float(number) => number.value().to_string(),
+            _ => {
+                warnings.push(ConwayError::Validation(format!(
+                    "Invalid {} in the config",
+                    key
+                )));
+                return None;
+            }
+        };
+        text.parse()
+            .map_err(|e| {
+                warnings.push(ConwayError::Validation(format!(
+                    "Invalid {} in the config: {}",
+                    key, e
+                )))
+            })
+            .ok()
+    }
+
+    /// Reads an array of paths from the config file.
+    fn get_paths(document: &Document, key: &str, warnings: &mut Vec<ConwayError>) -> Vec<PathBuf> {
+        let Some(item) = document.get(key) else {
+            return Vec::new();
+        };
+        match item.as_array() {
+            Some(paths) => paths
+                .iter()
+                .filter_map(|path| path.as_str())
+                .map(PathBuf::from)
+                .collect(),
+            None => {
+                warnings.push(ConwayError::Validation(format!(
+                    "Invalid {} in the config",
+                    key
+                )));
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn get_preferences(&self) -> &Preferences {
+        &self.preferences
+    }
+
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// Replaces the preferences without saving them, so they are only
+    /// saved if they change again.
+    pub fn set_preferences(&mut self, preferences: Preferences) {
+        self.preferences = preferences;
+    }
+
+    /// The file to load on start.
+    pub fn get_file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// The generation to run the board to on start.
+    pub fn get_generation(&self) -> Option<u64> {
+        self.generation
+    }
+
+    /// The seed to fill the board randomly from on start.
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Where to export a run to instead of opening the window.
+    pub fn get_export_path(&self) -> Option<&Path> {
+        self.export_path.as_deref()
+    }
+
+    /// Where to export the board as an image to instead of opening the
+    /// window.
+    pub fn get_image_path(&self) -> Option<&Path> {
+        self.image_path.as_deref()
+    }
+
+    /// Where to save a screenshot of the window once it is drawn, exiting
+    /// after.
+    pub fn get_screenshot_path(&self) -> Option<&Path> {
+        self.screenshot_path.as_deref()
+    }
+
+    /// Where to write a census of the objects the board settles into
+    /// instead of opening the window.
+    pub fn get_census_path(&self) -> Option<&Path> {
+        self.census_path.as_deref()
+    }
+
+    pub fn get_export_options(&self) -> &ExportOptions {
+        &self.export_options
+    }
+
+    /// Takes the warnings about the config file, to report them once.
+    pub fn take_warnings(&mut self) -> Vec<ConwayError> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    pub fn get_window_size(&self) -> (u32, u32) {
+        let size = self.preferences.window_size;
+        (size.width, size.height)
+    }
+
+    /// Writes the preferences changed during the session back to the config
+    /// file. The ones given on the command line and left alone are not
+    /// written, so they only last for the session.
+    pub fn save(&mut self, preferences: &Preferences) -> Result<(), String> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let old = &self.preferences;
+        let mut changes = Vec::new();
+        if preferences.width != old.width {
+            changes.push(("width", value(preferences.width as i64)));
+        }
+        if preferences.height != old.height {
+            changes.push(("height", value(preferences.height as i64)));
+        }
+        if preferences.topology != old.topology {
+            changes.push(("topology", value(preferences.topology.to_string())));
+        }
+        if preferences.rule != old.rule {
+            changes.push(("rule", value(preferences.rule.to_string())));
+        }
+        if preferences.theme != old.theme {
+            changes.push(("theme", value(preferences.theme.to_string())));
+        }
+        if preferences.speed != old.speed {
+            changes.push((
+                "speed",
+                match preferences.speed {
+                    Speed::PerSecond(speed) => value(speed),
+                    Speed::Max => value("max"),
+                },
+            ));
+        }
+        if preferences.window_size != old.window_size {
+            changes.push(("window-size", value(preferences.window_size.to_string())));
+        }
+        if preferences.last_directory != old.last_directory {
+            if let Some(directory) = &preferences.last_directory {
+                changes.push((
+                    "last-directory",
+                    value(directory.to_string_lossy().as_ref()),
+                ));
+            }
+        }
+        if preferences.recent_files != old.recent_files {
+            let paths = preferences
+                .recent_files
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect::<toml_edit::Array>();
+            changes.push(("recent-files", value(paths)));
+        }
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        for (key, item) in changes {
+            self.document[key] = item;
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+        }
+        std::fs::write(&path, self.document.to_string())
+            .map_err(|e| format!("Cannot write config {}: {}", path.display(), e))?;
+        self.preferences = preferences.clone();
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    error, fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Why loading, saving or drawing the board failed.
+#[derive(Debug)]
+pub enum ConwayError {
+    /// A file could not be read or written.
+    Io { path: PathBuf, error: io::Error },
+    /// A file is malformed. Lines and columns count from 1.
+    Parse {
+        path: Option<PathBuf>,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A value is well-formed but not allowed, e.g. a board too large.
+    Validation(String),
+    /// SDL failed to draw.
+    Sdl(String),
+}
+
+impl ConwayError {
+    /// Wraps the I/O errors of accessing `path`, for `map_err`.
+    pub fn io(path: &Path) -> impl FnOnce(io::Error) -> ConwayError + '_ {
+        move |error| ConwayError::Io {
+            path: path.to_path_buf(),
+            error,
+        }
+    }
+
+    pub fn parse(line: usize, column: usize, message: impl Into<String>) -> ConwayError {
+        ConwayError::Parse {
+            path: None,
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    /// A parse error where `content` ends, for what is missing from it.
+    pub fn parse_at_end(content: &str, message: impl Into<String>) -> ConwayError {
+        let line = content.lines().count().max(1);
+        let column = content
+            .lines()
+            .last()
+            .map_or(0, |last| last.chars().count())
+            + 1;
+        ConwayError::parse(line, column, message)
+    }
+
+    /// Names the file a parse error is in.
+    pub fn in_file(self, file: &Path) -> ConwayError {
+        match self {
+            ConwayError::Parse {
+                line,
+                column,
+                message,
+                ..
+            } => ConwayError::Parse {
+                path: Some(file.to_path_buf()),
+                line,
+                column,
+                message,
+            },
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for ConwayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConwayError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ConwayError::Parse {
+                path: Some(path),
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "{}, line {}, column {}: {}",
+                path.display(),
+                line,
+                column,
+                message
+            ),
+            ConwayError::Parse {
+                path: None,
+                line,
+                column,
+                message,
+            } => write!(f, "Line {}, column {}: {}", line, column, message),
+            ConwayError::Validation(message) => write!(f, "{}", message),
+            ConwayError::Sdl(message) => write!(f, "SDL error: {}", message),
+        }
+    }
+}
+
+impl error::Error for ConwayError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ConwayError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The column of `part`, a slice of `line`, counted from 1.
+pub fn column_of(line: &str, part: &str) -> usize {
+    let offset = (part.as_ptr() as usize).saturating_sub(line.as_ptr() as usize);
+    line[..offset.min(line.len())].chars().count() + 1
+}
@@ -0,0 +1,232 @@
+//! Exports runs of the board as an animated GIF or a sequence of PNG images,
+//! one frame per generation, drawn by [`super::raster`].
+
+use std::path::{Path, PathBuf};
+
+use sdl2::rect::Rect;
+
+use super::board::BoardView;
+use super::config::Config;
+use super::error::ConwayError;
+use super::game_state::ConwayGameState;
+use super::raster::{check_image_size, get_image_size, get_palette, rasterize};
+use super::theme::Theme;
+
+pub const DEFAULT_FRAMES: usize = 100;
+pub const DEFAULT_CELL_SIZE: usize = 4;
+pub const DEFAULT_FRAME_DELAY: u32 = 100;
+pub const MAX_CELL_SIZE: usize = 64;
+
+/// How runs and images are exported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportOptions {
+    /// The number of generations, the first being the board as it is.
+    pub frames: usize,
+    /// The side of a cell in pixels.
+    pub cell_size: usize,
+    /// The delay between the frames of a GIF in milliseconds.
+    pub frame_delay: u32,
+    /// Whether SVG images draw the grid between the cells.
+    pub grid: bool,
+    pub theme: Theme,
+}
+
+impl ExportOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.frames == 0 {
+            return Err("At least one frame must be exported".to_string());
+        }
+        if !(1..=MAX_CELL_SIZE).contains(&self.cell_size) {
+            return Err(format!(
+                "The cell size must be from 1 to {}, not {}",
+                MAX_CELL_SIZE, self.cell_size
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Exports a run from the board, which is left as it is. A path ending in
+/// `.gif` gets an animated GIF, and one ending in `.png` a numbered image
+/// per frame, e.g. `run-0000.png`, `run-0001.png` and so on for `run.png`.
+pub fn export(
+    state: &ConwayGameState,
+    path: &Path,
+    options: &ExportOptions,
+) -> Result<(), ConwayError> {
+    options.validate().map_err(ConwayError::Validation)?;
+    check_image_size(get_image_size(state, options.cell_size))?;
+    let mut state = copy_state(state);
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("gif") => export_gif(&mut state, path, options),
+        Some("png") => export_png_sequence(&mut state, path, options),
+        _ => Err(ConwayError::Validation(format!(
+            "Runs are exported to .gif or .png files, not {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Loads the board as the window would and exports it, without opening a
+/// window. Returns the number of frames written.
+pub fn export_headless(config: &Config, path: &Path) -> Result<usize, ConwayError> {
+    let board = load_headless(config, config.get_seed())?;
+    let options = config.get_export_options();
+    export(board.get_game_state(), path, options)?;
+    Ok(options.frames)
+}
+
+/// A board loaded from the file, or else filled randomly from `seed`, and
+/// run to the generation of the command line, without a window.
+pub(super) fn load_headless(config: &Config, seed: Option<u64>) -> Result<BoardView, ConwayError> {
+    let preferences = config.get_preferences();
+    let mut board = BoardView::new(
+        preferences.width,
+        preferences.height,
+        preferences.topology,
+        preferences.rule.clone(),
+        Rect::new(0, 0, 1, 1),
+    );
+    if let Some(file) = config.get_file() {
+        board.load_file(file)?;
+    } else if let Some(seed) = seed {
+        board.set_seed(seed);
+        board.randomize();
+    }
+    if let Some(generation) = config.get_generation() {
+        while board.get_generation() < generation {
+            board.step();
+        }
+    }
+    Ok(board)
+}
+
+/// A copy of the cells, rule and topology of a board to run on its own.
+pub(super) fn copy_state(state: &ConwayGameState) -> ConwayGameState {
+    let mut copy = ConwayGameState::new(
+        state.get_width(),
+        state.get_height(),
+        state.get_topology(),
+        state.get_rule().clone(),
+    );
+    copy.set_cells(state.get_cells().clone());
+    copy.set_generation(state.get_generation());
+    copy
+}
+
+fn export_gif(
+    state: &mut ConwayGameState,
+    path: &Path,
+    options: &ExportOptions,
+) -> Result<(), ConwayError> {
+    let (width, height) = get_image_size(state, options.cell_size);
+    let too_large = || {
+        ConwayError::Validation(format!(
+            "A {}x{} image is too large for a GIF",
+            width, height
+        ))
+    };
+    let width = u16::try_from(width).map_err(|_| too_large())?;
+    let height = u16::try_from(height).map_err(|_| too_large())?;
+    let palette = get_palette(state.get_rule(), options.theme).concat();
+    let encoding = |e: gif::EncodingError| ConwayError::Validation(e.to_string());
+
+    let mut content = Vec::new();
+    {
+        let mut encoder =
+            gif::Encoder::new(&mut content, width, height, &palette).map_err(encoding)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(encoding)?;
+        for i in 0..options.frames {
+            if i > 0 {
+                state.step();
+            }
+            let image = rasterize(state, options.cell_size);
+            let mut frame = gif::Frame::from_indexed_pixels(width, height, image.pixels, None);
+            // GIF delays are in hundredths of a second
+            frame.delay = (options.frame_delay / 10).min(u16::MAX as u32) as u16;
+            encoder.write_frame(&frame).map_err(encoding)?;
+        }
+    }
+    std::fs::write(path, content).map_err(ConwayError::io(path))
+}
+
+fn export_png_sequence(
+    state: &mut ConwayGameState,
+    path: &Path,
+    options: &ExportOptions,
+) -> Result<(), ConwayError> {
+    let palette = get_palette(state.get_rule(), options.theme).concat();
+    let digits = (options.frames - 1).to_string().len().max(4);
+    for i in 0..options.frames {
+        if i > 0 {
+            state.step();
+        }
+        let image = rasterize(state, options.cell_size);
+        let mut content = Vec::new();
+        write_png(
+            &mut content,
+            image.width,
+            image.height,
+            &palette,
+            &image.pixels,
+        )?;
+        let frame_path = get_frame_path(path, i, digits);
+        std::fs::write(&frame_path, content).map_err(ConwayError::io(&frame_path))?;
+    }
+    Ok(())
+}
+
+/// Encodes an image with a palette index per pixel as a PNG.
+pub fn write_png(
+    content: &mut Vec<u8>,
+    width: usize,
+    height: usize,
+    palette: &[u8],
+    pixels: &[u8],
+) -> Result<(), ConwayError> {
+    let encoding = |e: png::EncodingError| ConwayError::Validation(e.to_string());
+    let mut encoder = png::Encoder::new(content, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette.to_vec());
+    let mut writer = encoder.write_header().map_err(encoding)?;
+    writer.write_image_data(pixels).map_err(encoding)
+}
+
+/// The path of a frame of a PNG sequence, numbered before the extension.
+fn get_frame_path(path: &Path, frame: usize, digits: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}-{:0digits$}.png", stem, frame, digits = digits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conway::topology::Topology;
+
+    #[test]
+    fn rejects_images_too_large_to_draw() {
+        let state = ConwayGameState::new(4096, 4096, Topology::Plane, Default::default());
+        let options = ExportOptions {
+            frames: 1,
+            cell_size: MAX_CELL_SIZE,
+            frame_delay: DEFAULT_FRAME_DELAY,
+            grid: false,
+            theme: Theme::default(),
+        };
+        let path = std::env::temp_dir().join("conway-rs-too-large.png");
+        assert!(matches!(
+            export(&state, &path, &options),
+            Err(ConwayError::Validation(_))
+        ));
+        assert!(!get_frame_path(&path, 0, 4).exists());
+    }
+}
@@ -1,21 +1,121 @@
+use super::rule::{hensel, Rule};
+use super::topology::Topology;
+
+/// Cells whose rendered appearance may have changed since the last call to
+/// [`ConwayGameState::take_dirty_cells`].
+pub enum DirtyCells {
+    All,
+    Cells(Vec<usize>),
+}
+
+/// Births and deaths produced by the last step, as cell indices.
+#[derive(Debug, Clone, Default)]
+pub struct CellChanges {
+    pub births: Vec<usize>,
+    pub deaths: Vec<usize>,
+}
+
+/// An inclusive bounding box of cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub min_x: usize,
+    pub min_y: usize,
+    pub max_x: usize,
+    pub max_y: usize,
+}
+
+impl Region {
+    pub fn new(x: usize, y: usize) -> Region {
+        Region {
+            min_x: x,
+            min_y: y,
+            max_x: x,
+            max_y: y,
+        }
+    }
+
+    pub fn union(self, other: Region) -> Region {
+        Region {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+}
+
+/// Which part of the cells stays in place when the board is resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizeAnchor {
+    #[default]
+    TopLeft,
+    Center,
+}
+
+/// Cell state 0 is dead and 1 is alive. Generations rules use the remaining
+/// states for dying cells, rule tables give them their own meaning.
+pub const DEAD: u8 = 0;
+pub const ALIVE: u8 = 1;
+
+/// The smallest and largest width and height of a board.
+pub const MIN_BOARD_SIZE: usize = 8;
+pub const MAX_BOARD_SIZE: usize = 4096;
+
 pub struct ConwayGameState {
     width: usize,
     height: usize,
-    cells: Vec<bool>,
-    prev_cells: Vec<bool>,
-    periodic: bool,
+    cells: Vec<u8>,
+    prev_cells: Vec<u8>,
+    topology: Topology,
+    rule: Rule,
+    // Steps since the cells were last set or cleared
+    generation: u64,
+    // Neighbor offsets of cells in even and odd rows
+    neighbor_offsets: [Vec<(isize, isize)>; 2],
+    // Indices of cells that changed during the last step
+    changed_cells: Vec<usize>,
+    // Cells that may change in the next step, None if the board is static
+    active_region: Option<Region>,
+    // Cells whose previous state may differ from their current one, None
+    // if the two agree everywhere
+    stale_region: Option<Region>,
+    dirty_cells: Vec<usize>,
+    dirty_flags: Vec<bool>,
+    all_dirty: bool,
 }
 
 #[allow(dead_code)]
 impl ConwayGameState {
-    pub fn new(width: usize, height: usize, periodic: bool) -> ConwayGameState {
+    pub fn new(width: usize, height: usize, topology: Topology, rule: Rule) -> ConwayGameState {
         ConwayGameState {
             width,
             height,
-            periodic,
-            cells: vec![false; width * height],
-            prev_cells: vec![false; width * height],
+            topology,
+            neighbor_offsets: ConwayGameState::neighbor_offsets(&rule),
+            rule,
+            generation: 0,
+            cells: vec![DEAD; width * height],
+            prev_cells: vec![DEAD; width * height],
+            changed_cells: Vec::new(),
+            active_region: ConwayGameState::full_region(width, height),
+            stale_region: None,
+            dirty_cells: Vec::new(),
+            dirty_flags: vec![false; width * height],
+            all_dirty: true,
+        }
+    }
+
+    /// Checks that a board of the given size and topology can be created.
+    pub fn validate_size(width: usize, height: usize, topology: Topology) -> Result<(), String> {
+        for (name, size) in [("width", width), ("height", height)] {
+            if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&size) {
+                return Err(format!(
+                    "The board {} must be between {} and {}, not {}",
+                    name, MIN_BOARD_SIZE, MAX_BOARD_SIZE, size
+                ));
+            }
         }
+        topology.validate(width, height)
     }
 
     pub fn get_width(&self) -> usize {
@@ -26,138 +126,393 @@ impl ConwayGameState {
         self.height
     }
 
-    pub fn get_cells(&self) -> &Vec<bool> {
+    pub fn get_cells(&self) -> &Vec<u8> {
         &self.cells
     }
 
-    pub fn set_cells(&mut self, cells: Vec<bool>) {
+    pub fn get_prev_cells(&self) -> &Vec<u8> {
+        &self.prev_cells
+    }
+
+    pub fn set_cells(&mut self, cells: Vec<u8>) {
         assert!(cells.len() == self.width * self.height);
         self.cells = cells;
-        self.prev_cells = vec![false; self.width * self.height];
+        self.prev_cells = vec![DEAD; self.width * self.height];
+        self.generation = 0;
+        self.invalidate_all();
+        self.stale_region = ConwayGameState::full_region(self.width, self.height);
     }
 
-    pub fn get_cell(&self, x: usize, y: usize) -> bool {
+    pub fn get_generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    pub fn get_cell(&self, x: usize, y: usize) -> u8 {
         self.cells[y * self.width + x]
     }
 
-    pub fn set_cell(&mut self, x: usize, y: usize, value: bool) {
-        self.cells[y * self.width + x] = value;
+    pub fn is_alive(&self, x: usize, y: usize) -> bool {
+        self.get_cell(x, y) == ALIVE
+    }
+
+    pub fn set_cell(&mut self, x: usize, y: usize, value: u8) {
+        assert!((value as usize) < self.rule.get_states());
+        let idx = y * self.width + x;
+        if self.cells[idx] != value {
+            self.cells[idx] = value;
+            self.mark_dirty(idx);
+
+            let region = self.expand_region(Region::new(x, y));
+            self.active_region = Some(match self.active_region {
+                Some(active_region) => active_region.union(region),
+                None => region,
+            });
+            self.stale_region = Some(match self.stale_region {
+                Some(stale_region) => stale_region.union(Region::new(x, y)),
+                None => Region::new(x, y),
+            });
+        }
+    }
+
+    pub fn get_topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.active_region = ConwayGameState::full_region(self.width, self.height);
     }
 
-    pub fn get_is_periodic(&self) -> bool {
-        self.periodic
+    pub fn get_rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    pub fn set_rule(&mut self, rule: Rule) {
+        // Drop the states the new rule does not have
+        let states = rule.get_states();
+        for cell in self.cells.iter_mut().chain(self.prev_cells.iter_mut()) {
+            if *cell as usize >= states {
+                *cell = DEAD;
+            }
+        }
+        self.all_dirty = true;
+
+        self.neighbor_offsets = ConwayGameState::neighbor_offsets(&rule);
+        self.rule = rule;
+        self.active_region = ConwayGameState::full_region(self.width, self.height);
     }
 
-    pub fn set_is_periodic(&mut self, periodic: bool) {
-        self.periodic = periodic;
+    /// The bounding box of cells that may change in the next step. Cells
+    /// outside of it are skipped when stepping.
+    pub fn get_active_region(&self) -> Option<Region> {
+        self.active_region
     }
 
     pub fn step(&mut self) {
         assert!(self.cells.len() == self.width * self.height);
         assert!(self.prev_cells.len() == self.width * self.height);
+        self.generation += 1;
 
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let count = self.count_neighors(x, y);
-                let idx = y * self.width + x;
+        // Cells outside the active region keep their state, so only those
+        // that differ from their previous state are copied over it
+        if let Some(region) = self.stale_region {
+            for y in region.min_y..=region.max_y {
+                let row = y * self.width;
+                let (start, end) = (row + region.min_x, row + region.max_x + 1);
+                self.prev_cells[start..end].copy_from_slice(&self.cells[start..end]);
+            }
+        }
+
+        let mut changed_cells = Vec::new();
+        let mut changed_region: Option<Region> = None;
+        if let Some(region) = self.active_region {
+            for y in region.min_y..=region.max_y {
+                for x in region.min_x..=region.max_x {
+                    let idx = y * self.width + x;
+
+                    self.prev_cells[idx] = if let Some(table) = self.rule.get_table() {
+                        table.next_state(self.cells[idx], &self.get_neighbor_states(x, y))
+                    } else if self.rule.is_isotropic() {
+                        self.rule
+                            .next_state(self.cells[idx], self.get_neighbor_mask(x, y))
+                    } else {
+                        self.rule
+                            .next_state(self.cells[idx], self.count_neighors(x, y))
+                    };
 
-                if self.cells[idx] {
-                    self.prev_cells[idx] = count == 2 || count == 3;
-                } else {
-                    self.prev_cells[idx] = count == 3;
+                    if self.prev_cells[idx] != self.cells[idx] {
+                        changed_cells.push(idx);
+                        changed_region = Some(match changed_region {
+                            Some(changed_region) => changed_region.union(Region::new(x, y)),
+                            None => Region::new(x, y),
+                        });
+                    }
                 }
             }
         }
         std::mem::swap(&mut self.cells, &mut self.prev_cells);
+        self.active_region = changed_region.map(|region| self.expand_region(region));
+        self.stale_region = changed_region;
+
+        // A cell is drawn from both its current and previous state, so the
+        // cells changed in the step before this one need to be redrawn too.
+        let previously_changed = std::mem::take(&mut self.changed_cells);
+        for &idx in previously_changed.iter().chain(&changed_cells) {
+            self.mark_dirty(idx);
+        }
+        self.changed_cells = changed_cells;
+    }
+
+    /// Steps the board and returns the births and deaths it produced.
+    pub fn step_with_changes(&mut self) -> CellChanges {
+        self.step();
+        self.get_last_changes()
+    }
+
+    /// The births and deaths of the last step, without scanning the board.
+    /// Dying cells advancing to their next state are neither.
+    pub fn get_last_changes(&self) -> CellChanges {
+        let mut changes = CellChanges::default();
+        for &idx in &self.changed_cells {
+            if self.cells[idx] == ALIVE {
+                changes.births.push(idx);
+            } else if self.prev_cells[idx] == ALIVE {
+                changes.deaths.push(idx);
+            }
+        }
+        changes
+    }
+
+    /// Returns the cells to redraw and resets the dirty set.
+    pub fn take_dirty_cells(&mut self) -> DirtyCells {
+        for &idx in &self.dirty_cells {
+            self.dirty_flags[idx] = false;
+        }
+        let dirty_cells = std::mem::take(&mut self.dirty_cells);
+
+        if self.all_dirty {
+            self.all_dirty = false;
+            DirtyCells::All
+        } else {
+            DirtyCells::Cells(dirty_cells)
+        }
     }
 
-    pub fn resize(&mut self, width: usize, height: usize) {
+    fn mark_dirty(&mut self, idx: usize) {
+        if !self.all_dirty && !self.dirty_flags[idx] {
+            self.dirty_flags[idx] = true;
+            self.dirty_cells.push(idx);
+        }
+    }
+
+    fn invalidate_all(&mut self) {
+        for &idx in &self.dirty_cells {
+            self.dirty_flags[idx] = false;
+        }
+        self.dirty_cells.clear();
+        self.changed_cells.clear();
+        self.all_dirty = true;
+        self.active_region = ConwayGameState::full_region(self.width, self.height);
+    }
+
+    fn full_region(width: usize, height: usize) -> Option<Region> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        Some(Region {
+            min_x: 0,
+            min_y: 0,
+            max_x: width - 1,
+            max_y: height - 1,
+        })
+    }
+
+    /// Grows a region by the neighborhood range. On a periodic axis a region
+    /// that would cross an edge covers the whole axis instead, and on
+    /// topologies joining the edges to other rows or columns the whole board.
+    fn expand_region(&self, region: Region) -> Region {
+        let radius = self.rule.get_neighborhood().get_range();
+        let crosses_edge = region.min_x < radius
+            || region.min_y < radius
+            || region.max_x + radius >= self.width
+            || region.max_y + radius >= self.height;
+        if !self.topology.is_axis_aligned() && crosses_edge {
+            return ConwayGameState::full_region(self.width, self.height).unwrap();
+        }
+
+        let (wraps_x, wraps_y) = self.topology.get_periodic_axes();
+        let (min_x, max_x) = ConwayGameState::expand_range(
+            (region.min_x, region.max_x),
+            radius,
+            self.width,
+            wraps_x,
+        );
+        let (min_y, max_y) = ConwayGameState::expand_range(
+            (region.min_y, region.max_y),
+            radius,
+            self.height,
+            wraps_y,
+        );
+        Region {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        }
+    }
+
+    fn expand_range(
+        (min, max): (usize, usize),
+        radius: usize,
+        size: usize,
+        wraps: bool,
+    ) -> (usize, usize) {
+        if wraps && (min < radius || max + radius >= size) {
+            (0, size - 1)
+        } else {
+            (min.saturating_sub(radius), (max + radius).min(size - 1))
+        }
+    }
+
+    fn neighbor_offsets(rule: &Rule) -> [Vec<(isize, isize)>; 2] {
+        // Rule tables list the neighbors in their own order
+        if let Some(table) = rule.get_table() {
+            let neighborhood = table.get_neighborhood();
+            return [neighborhood.offsets(false), neighborhood.offsets(true)];
+        }
+        let neighborhood = rule.get_neighborhood();
+        [neighborhood.offsets(false), neighborhood.offsets(true)]
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize, anchor: ResizeAnchor) {
+        // Where the old top-left cell ends up
+        let offset = match anchor {
+            ResizeAnchor::TopLeft => (0, 0),
+            ResizeAnchor::Center => (
+                (width as isize - self.width as isize) / 2,
+                (height as isize - self.height as isize) / 2,
+            ),
+        };
+
         // Resize the cells and prev_cells vectors
         self.cells = ConwayGameState::resize_cells(
-            self.cells.clone(),
+            &self.cells,
             (width, height),
             (self.width, self.height),
+            offset,
         );
         self.prev_cells = ConwayGameState::resize_cells(
-            self.prev_cells.clone(),
+            &self.prev_cells,
             (width, height),
             (self.width, self.height),
+            offset,
         );
         self.width = width;
         self.height = height;
+        self.invalidate_all();
+        self.stale_region = ConwayGameState::full_region(width, height);
+        self.dirty_flags = vec![false; width * height];
     }
 
     pub fn clear(&mut self) {
-        self.cells.fill(false);
-        self.prev_cells.fill(false);
+        self.cells.fill(DEAD);
+        self.prev_cells.fill(DEAD);
+        self.generation = 0;
+        self.invalidate_all();
+        self.stale_region = None;
     }
 
     fn resize_cells(
-        cells: Vec<bool>,
+        cells: &[u8],
         new_size: (usize, usize),
         old_size: (usize, usize),
-    ) -> Vec<bool> {
-        let mut new_cells = vec![false; new_size.0 * new_size.1];
-        for y in 0..old_size.1.min(new_size.1) {
-            for x in 0..old_size.0.min(new_size.0) {
-                new_cells[y * new_size.0 + x] = cells[y * old_size.0 + x];
+        (dx, dy): (isize, isize),
+    ) -> Vec<u8> {
+        let mut new_cells = vec![DEAD; new_size.0 * new_size.1];
+        for y in 0..old_size.1 {
+            for x in 0..old_size.0 {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if (0..new_size.0 as isize).contains(&nx) && (0..new_size.1 as isize).contains(&ny)
+                {
+                    new_cells[ny as usize * new_size.0 + nx as usize] = cells[y * old_size.0 + x];
+                }
             }
         }
         new_cells
     }
 
     fn count_neighors(&self, x: usize, y: usize) -> usize {
-        let mut count = 0;
-        for dy in -1..=1 {
-            for dx in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
+        let count = self.neighbor_offsets[y % 2]
+            .iter()
+            .filter(|&&(dx, dy)| {
+                self.get_neighbor_index(x, y, dx, dy)
+                    .is_some_and(|idx| self.cells[idx] == ALIVE)
+            })
+            .count();
 
-                if self.periodic {
-                    let nx = (x as isize + dx + self.width as isize) as usize % self.width;
-                    let ny = (y as isize + dy + self.height as isize) as usize % self.height;
-                    if self.cells[ny * self.width + nx] {
-                        count += 1;
-                    }
-                } else {
-                    let nx = x as isize + dx;
-                    let ny = y as isize + dy;
-                    if nx >= 0 && nx < self.width as isize && ny >= 0 && ny < self.height as isize {
-                        if self.cells[ny as usize * self.width + nx as usize] {
-                            count += 1;
-                        }
-                    }
-                }
-            }
+        if self.rule.get_include_center() && self.cells[y * self.width + x] == ALIVE {
+            count + 1
+        } else {
+            count
         }
-        count
     }
 
-    pub fn get_newly_born_cells(&self) -> Vec<(usize, usize)> {
-        let mut newly_born_cells = Vec::new();
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let idx = y * self.width + x;
-                if self.cells[idx] && !self.prev_cells[idx] {
-                    newly_born_cells.push((x, y));
-                }
-            }
+    /// The live cells of the Moore neighborhood as a mask, with the bits
+    /// ordered as in [`hensel::NEIGHBOR_OFFSETS`].
+    fn get_neighbor_mask(&self, x: usize, y: usize) -> usize {
+        hensel::NEIGHBOR_OFFSETS
+            .iter()
+            .enumerate()
+            .filter(|&(_, &(dx, dy))| {
+                self.get_neighbor_index(x, y, dx, dy)
+                    .is_some_and(|idx| self.cells[idx] == ALIVE)
+            })
+            .fold(0, |mask, (bit, _)| mask | 1 << bit)
+    }
+
+    /// The states of the neighbors, in the order of the neighbor offsets.
+    /// Neighbors beyond the edges of a plane are dead.
+    fn get_neighbor_states(&self, x: usize, y: usize) -> [u8; 8] {
+        let mut states = [DEAD; 8];
+        for (state, &(dx, dy)) in states.iter_mut().zip(&self.neighbor_offsets[y % 2]) {
+            *state = self
+                .get_neighbor_index(x, y, dx, dy)
+                .map_or(DEAD, |idx| self.cells[idx]);
         }
-        newly_born_cells
+        states
     }
 
-    pub fn get_newly_dead_cells(&self) -> Vec<(usize, usize)> {
-        let mut newly_dead_cells = Vec::new();
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let idx = y * self.width + x;
-                if !self.cells[idx] && self.prev_cells[idx] {
-                    newly_dead_cells.push((x, y));
-                }
-            }
+    /// The index of the cell at the given offset, joined across the edges
+    /// by the topology, or None if it lies beyond the edges of a plane.
+    pub fn get_neighbor_index(&self, x: usize, y: usize, dx: isize, dy: isize) -> Option<usize> {
+        let nx = x as isize + dx;
+        let ny = y as isize + dy;
+        if nx >= 0 && nx < self.width as isize && ny >= 0 && ny < self.height as isize {
+            return Some(ny as usize * self.width + nx as usize);
         }
-        newly_dead_cells
+        self.topology
+            .wrap(nx, ny, self.width, self.height)
+            .map(|(nx, ny)| ny * self.width + nx)
+    }
+
+    pub fn get_newly_born_cells(&self) -> Vec<(usize, usize)> {
+        self.get_last_changes()
+            .births
+            .iter()
+            .map(|idx| (idx % self.width, idx / self.width))
+            .collect()
+    }
+
+    pub fn get_newly_dead_cells(&self) -> Vec<(usize, usize)> {
+        self.get_last_changes()
+            .deaths
+            .iter()
+            .map(|idx| (idx % self.width, idx / self.width))
+            .collect()
     }
 
     pub fn get_retained_cells(&self) -> Vec<(usize, usize)> {
@@ -165,7 +520,7 @@ impl ConwayGameState {
         for y in 0..self.height {
             for x in 0..self.width {
                 let idx = y * self.width + x;
-                if self.cells[idx] && self.prev_cells[idx] {
+                if self.cells[idx] == ALIVE && self.prev_cells[idx] == ALIVE {
                     retained_cells.push((x, y));
                 }
             }
@@ -173,3 +528,64 @@ impl ConwayGameState {
         retained_cells
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn life(width: usize, height: usize, topology: Topology) -> ConwayGameState {
+        ConwayGameState::new(width, height, topology, "B3/S23".parse().unwrap())
+    }
+
+    /// Steps the board and checks the previous cells are the whole board
+    /// as it was before the step.
+    fn step_and_check(state: &mut ConwayGameState) {
+        let before = state.get_cells().clone();
+        state.step();
+        assert_eq!(*state.get_prev_cells(), before);
+    }
+
+    #[test]
+    fn previous_cells_follow_a_glider() {
+        let mut state = life(16, 16, Topology::TORUS);
+        for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            state.set_cell(x, y, ALIVE);
+        }
+        for _ in 0..80 {
+            step_and_check(&mut state);
+        }
+        // A glider comes back to where it started every 64 generations on
+        // a 16 by 16 torus
+        assert_eq!(
+            state
+                .get_cells()
+                .iter()
+                .filter(|&&cell| cell == ALIVE)
+                .count(),
+            5
+        );
+    }
+
+    #[test]
+    fn previous_cells_follow_edits_between_steps() {
+        let mut state = life(16, 16, Topology::Plane);
+        for x in 4..7 {
+            state.set_cell(x, 4, ALIVE);
+        }
+        step_and_check(&mut state);
+        step_and_check(&mut state);
+        // A block far from the blinker, drawn between two steps
+        for (x, y) in [(12, 12), (13, 12), (12, 13), (13, 13)] {
+            state.set_cell(x, y, ALIVE);
+        }
+        step_and_check(&mut state);
+        step_and_check(&mut state);
+
+        let mut cells = vec![DEAD; 16 * 16];
+        cells[8 * 16 + 8] = ALIVE;
+        state.set_cells(cells);
+        step_and_check(&mut state);
+        state.resize(20, 20, ResizeAnchor::Center);
+        step_and_check(&mut state);
+    }
+}
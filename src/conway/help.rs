@@ -0,0 +1,105 @@
+use std::{cell::Cell, rc::Rc};
+
+use sdl2::rect::Rect;
+
+use crate::engine::{
+    resource_manager::FontManager,
+    view::{TextView, View, ViewChain},
+};
+use crate::fonts;
+
+use super::keymap::{Action, Keymap};
+use super::theme::Theme;
+
+const ROW_HEIGHT: i32 = 26;
+const KEYS_WIDTH: u32 = 220;
+
+/// An overlay listing the keys of every action, shown while `visible` is set.
+pub struct HelpView {
+    bound: Rect,
+    visible: Rc<Cell<bool>>,
+    theme: Theme,
+    components: ViewChain,
+}
+
+impl HelpView {
+    pub fn new(bound: Rect, keymap: &Keymap, visible: Rc<Cell<bool>>, theme: Theme) -> HelpView {
+        let (x, y) = (bound.x() + 20, bound.y() + 20);
+        let text = |bound: Rect, text: String, size: u16| {
+            TextView::new(
+                bound,
+                text,
+                fonts::ARK_PIXEL_FONT,
+                size,
+                theme.get_text_color(),
+            )
+            .wrap()
+        };
+        let mut components = ViewChain::new();
+        components.add_view(text(
+            Rect::new(x, y, bound.width() - 40, 40),
+            "Keyboard shortcuts".to_string(),
+            26,
+        ));
+
+        for (i, action) in Action::ALL.into_iter().enumerate() {
+            let row_y = y + 50 + i as i32 * ROW_HEIGHT;
+            let keys = keymap
+                .get_bindings(action)
+                .iter()
+                .map(|binding| binding.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let keys = if keys.is_empty() {
+                "-".to_string()
+            } else {
+                keys
+            };
+            components.add_view(text(
+                Rect::new(x, row_y, KEYS_WIDTH, ROW_HEIGHT as u32),
+                keys,
+                18,
+            ));
+            components.add_view(text(
+                Rect::new(
+                    x + KEYS_WIDTH as i32,
+                    row_y,
+                    bound.width() - 40 - KEYS_WIDTH,
+                    ROW_HEIGHT as u32,
+                ),
+                action.get_description().to_string(),
+                18,
+            ));
+        }
+
+        HelpView {
+            bound,
+            visible,
+            theme,
+            components,
+        }
+    }
+}
+
+impl View for HelpView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.visible.get() {
+            return Ok(());
+        }
+        canvas.set_draw_color(self.theme.get_background_color());
+        canvas.fill_rect(self.bound)?;
+        canvas.set_draw_color(self.theme.get_text_color());
+        canvas.draw_rect(self.bound)?;
+        self.components
+            .render(canvas, texture_creator, font_manager)
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.bound
+    }
+}
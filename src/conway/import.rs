@@ -0,0 +1,296 @@
+use std::{
+    cell::{Cell, RefCell},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use sdl2::{keyboard::Keycode, mouse::MouseButton, rect::Rect};
+
+use crate::engine::{
+    resource_manager::FontManager,
+    view::{TextView, View, ViewChain},
+};
+use crate::fonts;
+
+use super::board::BoardView;
+use super::error::ConwayError;
+use super::pattern::image::{self, ImageScan};
+use super::pattern::Pattern;
+use super::theme::Theme;
+use super::toast::ToastView;
+
+/// How far a key or button moves the threshold.
+const THRESHOLD_STEP: u8 = 8;
+const BUTTON_HEIGHT: u32 = 36;
+
+#[derive(Clone, Copy)]
+enum Button {
+    Lower,
+    Raise,
+    Invert,
+    Import,
+    Cancel,
+}
+
+impl Button {
+    const ALL: [Button; 5] = [
+        Button::Lower,
+        Button::Raise,
+        Button::Invert,
+        Button::Import,
+        Button::Cancel,
+    ];
+
+    fn get_label(self) -> &'static str {
+        match self {
+            Button::Lower => "-",
+            Button::Raise => "+",
+            Button::Invert => "Invert",
+            Button::Import => "Import",
+            Button::Cancel => "Cancel",
+        }
+    }
+}
+
+/// An overlay previewing the cells scanned from an image, shown while
+/// `visible` is set. The threshold between live and dead cells is adjusted
+/// before the cells replace the board.
+pub struct ImportView {
+    bound: Rect,
+    board: Rc<RefCell<BoardView>>,
+    visible: Rc<Cell<bool>>,
+    theme: Theme,
+    toasts: Rc<RefCell<ToastView>>,
+    components: ViewChain,
+    path: PathBuf,
+    scan: Option<ImageScan>,
+    threshold: u8,
+    // Whether the cells darker than the threshold are live
+    dark: bool,
+    pattern: Option<Pattern>,
+    // The live cells of the pattern, scaled into the preview
+    preview: Vec<Rect>,
+}
+
+impl ImportView {
+    pub fn new(
+        bound: Rect,
+        board: Rc<RefCell<BoardView>>,
+        visible: Rc<Cell<bool>>,
+        theme: Theme,
+        toasts: Rc<RefCell<ToastView>>,
+    ) -> ImportView {
+        ImportView {
+            bound,
+            board,
+            visible,
+            theme,
+            toasts,
+            components: ViewChain::new(),
+            path: PathBuf::new(),
+            scan: None,
+            threshold: 128,
+            dark: false,
+            pattern: None,
+            preview: Vec::new(),
+        }
+    }
+
+    /// Scans an image and shows its cells, with the threshold set from the
+    /// image.
+    pub fn open(&mut self, path: &Path) -> Result<(), ConwayError> {
+        let scan = image::read(path)?;
+        (self.threshold, self.dark) = scan.get_auto_threshold();
+        self.path = path.to_path_buf();
+        self.scan = Some(scan);
+        self.update();
+        self.visible.set(true);
+        Ok(())
+    }
+
+    fn get_preview_bound(&self) -> Rect {
+        Rect::new(
+            self.bound.x() + 20,
+            self.bound.y() + 96,
+            self.bound.width() - 40,
+            self.bound.height() - 216,
+        )
+    }
+
+    fn get_button_bound(&self, i: usize) -> Rect {
+        let count = Button::ALL.len() as u32;
+        let width = (self.bound.width() - 40 - (count - 1) * 10) / count;
+        Rect::new(
+            self.bound.x() + 20 + i as i32 * (width + 10) as i32,
+            self.bound.bottom() - 20 - BUTTON_HEIGHT as i32,
+            width,
+            BUTTON_HEIGHT,
+        )
+    }
+
+    /// Thresholds the scan again and rebuilds the preview and texts.
+    fn update(&mut self) {
+        let Some(scan) = &self.scan else {
+            return;
+        };
+        let pattern = scan.to_pattern(self.threshold, self.dark);
+        let live = pattern.cells.iter().filter(|&&cell| cell != 0).count();
+
+        let area = self.get_preview_bound();
+        let scale = (area.width() as f32 / pattern.width as f32)
+            .min(area.height() as f32 / pattern.height as f32);
+        let size = scale.ceil().max(1.0) as u32;
+        let left = area.x() + (area.width() as f32 - pattern.width as f32 * scale) as i32 / 2;
+        let top = area.y() + (area.height() as f32 - pattern.height as f32 * scale) as i32 / 2;
+        self.preview = pattern
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(_, &cell)| cell != 0)
+            .map(|(i, _)| {
+                let x = (i % pattern.width) as f32 * scale;
+                let y = (i / pattern.width) as f32 * scale;
+                Rect::new(left + x as i32, top + y as i32, size, size)
+            })
+            .collect();
+
+        let (x, width) = (self.bound.x() + 20, self.bound.width() - 40);
+        let text = |bound: Rect, text: String, size: u16| {
+            TextView::new(
+                bound,
+                text,
+                fonts::ARK_PIXEL_FONT,
+                size,
+                self.theme.get_text_color(),
+            )
+            .wrap()
+        };
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.components = ViewChain::new();
+        self.components.add_view(text(
+            Rect::new(x, self.bound.y() + 20, width, 40),
+            format!("Import {}", name),
+            26,
+        ));
+        self.components.add_view(text(
+            Rect::new(x, self.bound.y() + 64, width, 20),
+            format!(
+                "{}x{} cells of {}x{} pixels, {} live",
+                scan.width, scan.height, scan.cell_size.0, scan.cell_size.1, live
+            ),
+            14,
+        ));
+        self.components.add_view(text(
+            Rect::new(x, self.bound.bottom() - 108, width, 24),
+            format!(
+                "Threshold: {}, {} cells live",
+                self.threshold,
+                if self.dark { "dark" } else { "light" }
+            ),
+            18,
+        ));
+        self.components.add_view(text(
+            Rect::new(x, self.bound.bottom() - 80, width, 18),
+            "Up/Down: threshold  I: invert  Enter: import  Esc: cancel".to_string(),
+            12,
+        ));
+        for (i, button) in Button::ALL.into_iter().enumerate() {
+            let bound = self.get_button_bound(i);
+            self.components.add_view(text(
+                Rect::new(bound.x() + 10, bound.y() + 8, bound.width() - 20, 20),
+                button.get_label().to_string(),
+                18,
+            ));
+        }
+        self.pattern = Some(pattern);
+    }
+
+    fn press(&mut self, button: Button) {
+        match button {
+            Button::Lower => self.threshold = self.threshold.saturating_sub(THRESHOLD_STEP),
+            Button::Raise => self.threshold = self.threshold.saturating_add(THRESHOLD_STEP),
+            Button::Invert => self.dark = !self.dark,
+            Button::Import => {
+                self.import();
+                return;
+            }
+            Button::Cancel => {
+                self.scan = None;
+                self.pattern = None;
+                self.visible.set(false);
+                return;
+            }
+        }
+        self.update();
+    }
+
+    /// Replaces the board with the cells as previewed.
+    fn import(&mut self) {
+        self.visible.set(false);
+        self.scan = None;
+        let Some(pattern) = self.pattern.take() else {
+            return;
+        };
+        if let Err(e) = self.board.borrow_mut().import_image(&self.path, pattern) {
+            self.toasts.borrow_mut().error(e);
+        }
+    }
+}
+
+impl View for ImportView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.visible.get() {
+            return Ok(());
+        }
+        canvas.set_draw_color(self.theme.get_background_color());
+        canvas.fill_rect(self.bound)?;
+        canvas.set_draw_color(self.theme.get_text_color());
+        canvas.draw_rect(self.bound)?;
+        canvas.draw_rect(self.get_preview_bound())?;
+        canvas.fill_rects(&self.preview)?;
+        for i in 0..Button::ALL.len() {
+            canvas.draw_rect(self.get_button_bound(i))?;
+        }
+        self.components
+            .render(canvas, texture_creator, font_manager)
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.bound
+    }
+
+    fn on_key_down(&mut self, key: Keycode) {
+        match key {
+            Keycode::Up | Keycode::Right | Keycode::Plus | Keycode::KpPlus | Keycode::Equals => {
+                self.press(Button::Raise)
+            }
+            Keycode::Down | Keycode::Left | Keycode::Minus | Keycode::KpMinus => {
+                self.press(Button::Lower)
+            }
+            Keycode::I => self.press(Button::Invert),
+            Keycode::Return | Keycode::KpEnter => self.press(Button::Import),
+            Keycode::Escape => self.press(Button::Cancel),
+            _ => {}
+        }
+    }
+
+    fn on_mouse_button_down(&mut self, _button: MouseButton, x: i32, y: i32) {
+        if !self.visible.get() {
+            return;
+        }
+        if let Some(i) =
+            (0..Button::ALL.len()).find(|&i| self.get_button_bound(i).contains_point((x, y)))
+        {
+            self.press(Button::ALL[i]);
+        }
+    }
+}
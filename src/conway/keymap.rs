@@ -0,0 +1,288 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use sdl2::keyboard::{Keycode, Mod};
+
+/// Something a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Pause,
+    Step,
+    SpeedUp,
+    SpeedDown,
+    Clear,
+    Load,
+    Save,
+    Recent,
+    Export,
+    ExportImage,
+    Randomize,
+    Census,
+    Undo,
+    ZoomIn,
+    ZoomOut,
+    Help,
+    Exit,
+}
+
+impl Action {
+    pub const ALL: [Action; 17] = [
+        Action::Pause,
+        Action::Step,
+        Action::SpeedUp,
+        Action::SpeedDown,
+        Action::Clear,
+        Action::Load,
+        Action::Save,
+        Action::Recent,
+        Action::Export,
+        Action::ExportImage,
+        Action::Randomize,
+        Action::Census,
+        Action::Undo,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::Help,
+        Action::Exit,
+    ];
+
+    /// The name of the action in keymap files.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Action::Pause => "pause",
+            Action::Step => "step",
+            Action::SpeedUp => "speed-up",
+            Action::SpeedDown => "speed-down",
+            Action::Clear => "clear",
+            Action::Load => "load",
+            Action::Save => "save",
+            Action::Recent => "recent",
+            Action::Export => "export",
+            Action::ExportImage => "export-image",
+            Action::Randomize => "randomize",
+            Action::Census => "census",
+            Action::Undo => "undo",
+            Action::ZoomIn => "zoom-in",
+            Action::ZoomOut => "zoom-out",
+            Action::Help => "help",
+            Action::Exit => "exit",
+        }
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            Action::Pause => "Pause or resume",
+            Action::Step => "Step one generation",
+            Action::SpeedUp => "Speed up",
+            Action::SpeedDown => "Speed down",
+            Action::Clear => "Clear the board",
+            Action::Load => "Load a file",
+            Action::Save => "Save to a file",
+            Action::Recent => "Open a recent file",
+            Action::Export => "Export a run as a GIF or PNGs",
+            Action::ExportImage => "Export the board as an SVG or PNG",
+            Action::Randomize => "Fill randomly",
+            Action::Census => "Take a census, or stop one",
+            Action::Undo => "Undo the last edit",
+            Action::ZoomIn => "Zoom in",
+            Action::ZoomOut => "Zoom out",
+            Action::Help => "Show or hide this help",
+            Action::Exit => "Exit",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.get_name() == s)
+            .ok_or_else(|| format!("Unknown action: {}", s))
+    }
+}
+
+/// A key together with the modifiers held with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    key: Keycode,
+    ctrl: bool,
+    shift: bool,
+    alt: bool,
+}
+
+impl KeyBinding {
+    fn new(key: Keycode) -> KeyBinding {
+        KeyBinding {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    fn with_ctrl(self) -> KeyBinding {
+        KeyBinding { ctrl: true, ..self }
+    }
+
+    fn with_shift(self) -> KeyBinding {
+        KeyBinding {
+            shift: true,
+            ..self
+        }
+    }
+
+    /// The binding of a pressed key. Left and right modifiers are the same.
+    pub fn from_event(key: Keycode, keymod: Mod) -> KeyBinding {
+        KeyBinding {
+            key,
+            ctrl: keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD),
+            shift: keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD),
+            alt: keymod.intersects(Mod::LALTMOD | Mod::RALTMOD),
+        }
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    /// Parses a key name as SDL names it, e.g. `Space`, `Z`, `/` or
+    /// `Keypad +`, after any number of `Ctrl+`, `Shift+` and `Alt+`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rest = s.trim();
+        let (mut ctrl, mut shift, mut alt) = (false, false, false);
+        while let Some(modifier) = ["Ctrl+", "Shift+", "Alt+"].into_iter().find(|modifier| {
+            rest.len() > modifier.len()
+                && rest
+                    .get(..modifier.len())
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(modifier))
+        }) {
+            match modifier {
+                "Ctrl+" => ctrl = true,
+                "Shift+" => shift = true,
+                _ => alt = true,
+            }
+            rest = &rest[modifier.len()..];
+        }
+        let key = Keycode::from_name(rest).ok_or_else(|| format!("Unknown key: {}", s))?;
+        Ok(KeyBinding {
+            key,
+            ctrl,
+            shift,
+            alt,
+        })
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl+"),
+            (self.shift, "Shift+"),
+            (self.alt, "Alt+"),
+        ] {
+            if held {
+                write!(f, "{}", name)?;
+            }
+        }
+        write!(f, "{}", self.key.name())
+    }
+}
+
+/// Maps keys to actions.
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = vec![
+            (KeyBinding::new(Keycode::P), Action::Pause),
+            (KeyBinding::new(Keycode::Space), Action::Step),
+            (KeyBinding::new(Keycode::Equals), Action::SpeedUp),
+            (KeyBinding::new(Keycode::KpPlus), Action::SpeedUp),
+            (KeyBinding::new(Keycode::Minus), Action::SpeedDown),
+            (KeyBinding::new(Keycode::KpMinus), Action::SpeedDown),
+            (KeyBinding::new(Keycode::Delete), Action::Clear),
+            (KeyBinding::new(Keycode::O).with_ctrl(), Action::Load),
+            (KeyBinding::new(Keycode::S).with_ctrl(), Action::Save),
+            (KeyBinding::new(Keycode::R).with_ctrl(), Action::Recent),
+            (KeyBinding::new(Keycode::E).with_ctrl(), Action::Export),
+            (
+                KeyBinding::new(Keycode::E).with_ctrl().with_shift(),
+                Action::ExportImage,
+            ),
+            (KeyBinding::new(Keycode::R), Action::Randomize),
+            (KeyBinding::new(Keycode::C), Action::Census),
+            (KeyBinding::new(Keycode::Z).with_ctrl(), Action::Undo),
+            (KeyBinding::new(Keycode::RightBracket), Action::ZoomIn),
+            (KeyBinding::new(Keycode::LeftBracket), Action::ZoomOut),
+            (KeyBinding::new(Keycode::Slash).with_shift(), Action::Help),
+            (KeyBinding::new(Keycode::F1), Action::Help),
+            (KeyBinding::new(Keycode::Escape), Action::Exit),
+        ];
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    /// Applies the bindings of a keymap file, a TOML file with the keys of
+    /// actions as in `apply`.
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let document = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read keymap {}: {}", path.display(), e))?
+            .parse::<toml_edit::Document>()
+            .map_err(|e| format!("Invalid keymap {}: {}", path.display(), e))?;
+        self.apply(&document)
+            .map_err(|e| format!("Invalid keymap {}: {}", path.display(), e))
+    }
+
+    /// Binds the keys of the actions in a TOML table, as a string or an
+    /// array, e.g. `pause = "Return"` or `undo = ["Ctrl+Z", "Backspace"]`.
+    /// The listed actions lose their other keys, and an empty array unbinds
+    /// one.
+    pub fn apply(&mut self, table: &toml_edit::Table) -> Result<(), String> {
+        for (name, item) in table.iter() {
+            let action: Action = name.parse()?;
+            let keys: Vec<&str> = match item.as_value() {
+                Some(toml_edit::Value::String(key)) => vec![key.value().as_str()],
+                Some(toml_edit::Value::Array(keys)) => keys
+                    .iter()
+                    .map(|key| key.as_str().ok_or("Keys must be strings"))
+                    .collect::<Result<_, _>>()?,
+                _ => return Err(format!("The keys of {} must be a string or an array", name)),
+            };
+            let bindings = keys
+                .into_iter()
+                .map(KeyBinding::from_str)
+                .collect::<Result<Vec<_>, _>>()?;
+            self.bind(action, bindings);
+        }
+        Ok(())
+    }
+
+    /// Replaces the keys of an action, taking them from other actions.
+    pub fn bind(&mut self, action: Action, bindings: Vec<KeyBinding>) {
+        self.bindings
+            .retain(|(binding, other)| *other != action && !bindings.contains(binding));
+        self.bindings
+            .extend(bindings.into_iter().map(|binding| (binding, action)));
+    }
+
+    pub fn get_action(&self, key: Keycode, keymod: Mod) -> Option<Action> {
+        let pressed = KeyBinding::from_event(key, keymod);
+        self.bindings
+            .iter()
+            .find(|(binding, _)| *binding == pressed)
+            .map(|&(_, action)| action)
+    }
+
+    /// The keys bound to an action.
+    pub fn get_bindings(&self, action: Action) -> Vec<KeyBinding> {
+        self.bindings
+            .iter()
+            .filter(|(_, other)| *other == action)
+            .map(|&(binding, _)| binding)
+            .collect()
+    }
+}
@@ -0,0 +1,50 @@
+use std::str::Chars;
+
+use super::rule::Rule;
+use super::topology::Topology;
+
+pub mod apgcode;
+pub mod image;
+pub mod macrocell;
+pub mod rle;
+pub mod save;
+
+/// A rectangle of cells read from or written to a pattern file.
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<u8>,
+    pub rule: Option<Rule>,
+    pub topology: Option<Topology>,
+}
+
+/// Formats a cell state in the multi-state RLE alphabet: `.` is dead, `A` to
+/// `X` are the states 1 to 24 and `pA` to `yO` the states above.
+pub fn format_state(state: u8) -> String {
+    match state {
+        0 => ".".to_string(),
+        1..=24 => ((b'A' + state - 1) as char).to_string(),
+        _ => {
+            let prefix = (b'p' + (state - 25) / 24) as char;
+            let suffix = (b'A' + (state - 25) % 24) as char;
+            format!("{}{}", prefix, suffix)
+        }
+    }
+}
+
+/// Reads a state in the multi-state RLE alphabet whose first character is
+/// `first`, consuming the suffix of two-character states from `chars`.
+pub fn parse_state(first: char, chars: &mut Chars) -> Option<u8> {
+    match first {
+        '.' => Some(0),
+        'A'..='X' => Some(first as u8 - b'A' + 1),
+        'p'..='y' => {
+            let suffix = chars.clone().next().filter(|c| matches!(c, 'A'..='X'))?;
+            chars.next();
+            let state =
+                25 + (first as usize - 'p' as usize) * 24 + (suffix as usize - 'A' as usize);
+            u8::try_from(state).ok()
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,402 @@
+//! Catagolue's apgcodes, which name periodic objects like `xs4_33` for the
+//! block, `xp2_7` for the blinker and `xq4_153` for the glider.
+//!
+//! The prefix tells a still life and its population, or an oscillator or a
+//! spaceship and its period. The rest is the extended Wechsler format of the
+//! object: strips of five rows separated by `z`, each a column per
+//! character from `0` to `v` with the top row as the lowest bit. `w` and `x`
+//! stand for two and three blank columns, and `y` followed by a character
+//! from `0` to `z` for four to 39. Of all phases and orientations of the
+//! object, the shortest code comes first, then the earliest in ASCII.
+
+use super::Pattern;
+use crate::conway::error::ConwayError;
+use crate::conway::game_state::{ConwayGameState, ALIVE, DEAD, MAX_BOARD_SIZE};
+use crate::conway::rule::Rule;
+use crate::conway::topology::Topology;
+
+/// The longest period objects are run for to identify them.
+pub const MAX_PERIOD: u64 = 64;
+
+const DIGITS: &[u8; 36] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+/// The rows of a strip of the Wechsler format.
+const STRIP_HEIGHT: usize = 5;
+/// The most blank columns a `y` stands for.
+const MAX_GAP: usize = 39;
+
+/// How an object repeats itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Kind {
+    StillLife,
+    Oscillator,
+    Spaceship,
+}
+
+impl Kind {
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Kind::StillLife => "Still life",
+            Kind::Oscillator => "Oscillator",
+            Kind::Spaceship => "Spaceship",
+        }
+    }
+}
+
+/// A periodic object identified by its apgcode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    pub kind: Kind,
+    /// Generations until the object comes back, 1 for still lifes.
+    pub period: u64,
+    pub code: String,
+}
+
+impl Object {
+    /// What the object is, e.g. "Period 2 oscillator".
+    pub fn describe(&self) -> String {
+        match self.kind {
+            Kind::StillLife => Kind::StillLife.get_name().to_string(),
+            kind => format!(
+                "Period {} {}",
+                self.period,
+                kind.get_name().to_ascii_lowercase()
+            ),
+        }
+    }
+}
+
+/// The live cells of a phase, moved to the top left corner, and where they
+/// were.
+struct Phase {
+    offset: (usize, usize),
+    cells: Vec<(usize, usize)>,
+}
+
+/// Runs the live cells of a pattern on their own until they come back, and
+/// names them. Fails with why they cannot be named: apgcodes are only
+/// defined for two states on square grids, and for cells that repeat
+/// within [`MAX_PERIOD`] generations.
+pub fn identify(pattern: &Pattern, rule: &Rule) -> Result<Object, String> {
+    if rule.get_states() != 2 {
+        return Err("Needs two states".to_string());
+    }
+    if rule.get_neighborhood().is_hexagonal() {
+        return Err("Needs a square grid".to_string());
+    }
+
+    // The margin fits a spaceship at the speed of light for a whole period
+    let margin = (MAX_PERIOD as usize + 1) * rule.get_neighborhood().get_range().max(1);
+    let (width, height) = (pattern.width + 2 * margin, pattern.height + 2 * margin);
+    if width.max(height) > MAX_BOARD_SIZE {
+        return Err("Too large".to_string());
+    }
+    let mut state = ConwayGameState::new(width, height, Topology::Plane, rule.clone());
+    for (i, _) in pattern
+        .cells
+        .iter()
+        .enumerate()
+        .filter(|(_, &cell)| cell != DEAD)
+    {
+        let (x, y) = (i % pattern.width, i / pattern.width);
+        state.set_cell(margin + x, margin + y, ALIVE);
+    }
+
+    let first = get_phase(&state).ok_or("No live cells")?;
+    let mut phases = vec![first.cells.clone()];
+    for period in 1..=MAX_PERIOD {
+        state.step();
+        let phase = get_phase(&state).ok_or("Dies out")?;
+        if phase.cells == first.cells {
+            let kind = if phase.offset != first.offset {
+                Kind::Spaceship
+            } else if period == 1 {
+                Kind::StillLife
+            } else {
+                Kind::Oscillator
+            };
+            let prefix = match kind {
+                Kind::StillLife => format!("xs{}", first.cells.len()),
+                Kind::Oscillator => format!("xp{}", period),
+                Kind::Spaceship => format!("xq{}", period),
+            };
+            let code = phases
+                .iter()
+                .flat_map(|cells| (0..8).map(move |orientation| encode(cells, orientation)))
+                .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+                .unwrap();
+            return Ok(Object {
+                kind,
+                period,
+                code: format!("{}_{}", prefix, code),
+            });
+        }
+        // Cells reaching the edge may have been cut off by it
+        let (right, bottom) = phase.cells.iter().fold((0, 0), |(right, bottom), &(x, y)| {
+            (
+                right.max(phase.offset.0 + x),
+                bottom.max(phase.offset.1 + y),
+            )
+        });
+        if phase.offset.0 == 0 || phase.offset.1 == 0 || right + 1 == width || bottom + 1 == height
+        {
+            break;
+        }
+        phases.push(phase.cells);
+    }
+    Err("Not periodic".to_string())
+}
+
+fn get_phase(state: &ConwayGameState) -> Option<Phase> {
+    let width = state.get_width();
+    let cells: Vec<(usize, usize)> = state
+        .get_cells()
+        .iter()
+        .enumerate()
+        .filter(|(_, &cell)| cell != DEAD)
+        .map(|(i, _)| (i % width, i / width))
+        .collect();
+    let left = cells.iter().map(|&(x, _)| x).min()?;
+    let top = cells.iter().map(|&(_, y)| y).min()?;
+    Some(Phase {
+        offset: (left, top),
+        cells: cells.iter().map(|&(x, y)| (x - left, y - top)).collect(),
+    })
+}
+
+/// Encodes cells in the Wechsler format after flipping them horizontally
+/// with bit 0 of `orientation` set, vertically with bit 1, and along the
+/// diagonal with bit 2.
+fn encode(cells: &[(usize, usize)], orientation: u8) -> String {
+    let cells: Vec<(i64, i64)> = cells
+        .iter()
+        .map(|&(x, y)| {
+            let (x, y) = (x as i64, y as i64);
+            let (x, y) = if orientation & 4 != 0 { (y, x) } else { (x, y) };
+            (
+                if orientation & 1 != 0 { -x } else { x },
+                if orientation & 2 != 0 { -y } else { y },
+            )
+        })
+        .collect();
+    let left = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let top = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    let width = cells.iter().map(|&(x, _)| x - left + 1).max().unwrap_or(0) as usize;
+    let height = cells.iter().map(|&(_, y)| y - top + 1).max().unwrap_or(0) as usize;
+
+    let strips = height.div_ceil(STRIP_HEIGHT);
+    let mut columns = vec![0u8; strips * width];
+    for &(x, y) in &cells {
+        let (x, y) = ((x - left) as usize, (y - top) as usize);
+        columns[y / STRIP_HEIGHT * width + x] |= 1 << (y % STRIP_HEIGHT);
+    }
+
+    let mut code = String::new();
+    for (i, strip) in columns.chunks(width.max(1)).enumerate() {
+        if i > 0 {
+            code.push('z');
+        }
+        let end = strip
+            .iter()
+            .rposition(|&column| column != 0)
+            .map_or(0, |i| i + 1);
+        let mut gap = 0;
+        for &column in &strip[..end] {
+            if column == 0 {
+                gap += 1;
+                continue;
+            }
+            push_gap(&mut code, gap);
+            gap = 0;
+            code.push(DIGITS[column as usize] as char);
+        }
+    }
+    code
+}
+
+/// Writes a run of blank columns, as short as it gets.
+fn push_gap(code: &mut String, mut gap: usize) {
+    while gap > 0 {
+        let run = gap.min(MAX_GAP);
+        match run {
+            1 => code.push('0'),
+            2 => code.push('w'),
+            3 => code.push('x'),
+            _ => {
+                code.push('y');
+                code.push(DIGITS[run - 4] as char);
+            }
+        }
+        gap -= run;
+    }
+}
+
+/// Reads the live cells of an apgcode into a pattern, without a rule.
+pub fn decode(code: &str) -> Result<Pattern, ConwayError> {
+    let code = code.trim();
+    let invalid =
+        |message: String| ConwayError::Validation(format!("Invalid apgcode {}: {}", code, message));
+    let (prefix, body) = code
+        .split_once('_')
+        .ok_or_else(|| invalid("Expected a prefix like xs4_".to_string()))?;
+    let number = prefix
+        .strip_prefix("xs")
+        .or_else(|| prefix.strip_prefix("xp"))
+        .or_else(|| prefix.strip_prefix("xq"));
+    if !number
+        .is_some_and(|number| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return Err(invalid(format!("Unknown prefix {}", prefix)));
+    }
+
+    let mut cells = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '0'..='9' | 'a'..='v' => {
+                let column = DIGITS.iter().position(|&digit| digit as char == c).unwrap();
+                for row in 0..STRIP_HEIGHT {
+                    if column & (1 << row) != 0 {
+                        cells.push((x, y + row));
+                    }
+                }
+                x += 1;
+            }
+            'w' => x += 2,
+            'x' => x += 3,
+            'y' => {
+                let run = chars
+                    .next()
+                    .and_then(|c| DIGITS.iter().position(|&digit| digit as char == c))
+                    .ok_or_else(|| invalid("y must be followed by 0 to z".to_string()))?;
+                x += 4 + run;
+            }
+            'z' => {
+                x = 0;
+                y += STRIP_HEIGHT;
+            }
+            _ => return Err(invalid(format!("Unexpected character {}", c))),
+        }
+        if x > MAX_BOARD_SIZE || y > MAX_BOARD_SIZE {
+            return Err(invalid("The object is too large".to_string()));
+        }
+    }
+
+    let left = cells.iter().map(|&(x, _)| x).min();
+    let top = cells.iter().map(|&(_, y)| y).min();
+    let (Some(left), Some(top)) = (left, top) else {
+        return Err(invalid("There are no live cells".to_string()));
+    };
+    let width = cells.iter().map(|&(x, _)| x - left + 1).max().unwrap();
+    let height = cells.iter().map(|&(_, y)| y - top + 1).max().unwrap();
+    let mut pattern = Pattern {
+        width,
+        height,
+        cells: vec![DEAD; width * height],
+        rule: None,
+        topology: None,
+    };
+    for (x, y) in cells {
+        pattern.cells[(y - top) * width + x - left] = ALIVE;
+    }
+    Ok(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(rows: &[&str]) -> Pattern {
+        Pattern {
+            width: rows[0].len(),
+            height: rows.len(),
+            cells: rows
+                .iter()
+                .flat_map(|row| row.bytes().map(|b| (b == b'o') as u8))
+                .collect(),
+            rule: None,
+            topology: None,
+        }
+    }
+
+    fn identify_life(pattern: &Pattern) -> Result<Object, String> {
+        identify(pattern, &Rule::default())
+    }
+
+    #[test]
+    fn identifies_common_objects() {
+        let block = identify_life(&pattern(&["oo", "oo"])).unwrap();
+        assert_eq!((block.kind, block.period), (Kind::StillLife, 1));
+        assert_eq!(block.code, "xs4_33");
+
+        let blinker = identify_life(&pattern(&["ooo"])).unwrap();
+        assert_eq!((blinker.kind, blinker.period), (Kind::Oscillator, 2));
+        assert_eq!(blinker.code, "xp2_7");
+
+        let glider = identify_life(&pattern(&[".o.", "..o", "ooo"])).unwrap();
+        assert_eq!((glider.kind, glider.period), (Kind::Spaceship, 4));
+        assert_eq!(glider.code, "xq4_153");
+        assert_eq!(glider.describe(), "Period 4 spaceship");
+    }
+
+    #[test]
+    fn names_every_phase_and_orientation_alike() {
+        for rows in [
+            &["o..", "o.o", "oo."][..],
+            &["ooo", "o..", ".o."],
+            &["...o", ".o.o", "..oo"],
+        ] {
+            assert_eq!(identify_life(&pattern(rows)).unwrap().code, "xq4_153");
+        }
+    }
+
+    #[test]
+    fn decodes_what_it_identifies() {
+        for code in [
+            "xs4_33",
+            "xp2_7",
+            "xq4_153",
+            "xs6_696",
+            "xs14_39e0e93",
+            "xq4_6frc",
+            "xp15_4r4z4r4",
+        ] {
+            let pattern = decode(code).unwrap();
+            assert_eq!(identify_life(&pattern).unwrap().code, code);
+        }
+    }
+
+    #[test]
+    fn decodes_gaps() {
+        // Two blocks 1, 3 and 5 columns apart
+        for (code, width) in [("xs8_33033", 5), ("xs8_33x33", 7), ("xs8_33y133", 9)] {
+            let pattern = decode(code).unwrap();
+            assert_eq!((pattern.width, pattern.height), (width, 2), "{}", code);
+            assert_eq!(
+                pattern.cells.iter().filter(|&&cell| cell == ALIVE).count(),
+                8
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_codes() {
+        for code in ["33", "xz4_33", "xs_33", "xs4_3!", "xs4_y", "xs0_0"] {
+            assert!(decode(code).is_err(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn fails_to_identify_what_is_not_periodic() {
+        assert_eq!(identify_life(&pattern(&["o"])).unwrap_err(), "Dies out");
+        assert_eq!(
+            identify_life(&pattern(&["."])).unwrap_err(),
+            "No live cells"
+        );
+        let generations: Rule = "B2/S/C3".parse().unwrap();
+        assert_eq!(
+            identify(&pattern(&["oo"]), &generations).unwrap_err(),
+            "Needs two states"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Patterns scanned from PNG images, like screenshots or pixel art.
+//!
+//! The grid is found from the edges between light and dark pixels, which
+//! fall on the borders of cells, and each cell is live or dead by how light
+//! its middle is.
+
+use std::path::Path;
+
+use super::Pattern;
+use crate::conway::error::ConwayError;
+
+/// How much two neighboring pixels differ in lightness to be an edge.
+const EDGE_CONTRAST: u8 = 48;
+/// The share of edges that must fall on the borders of cells of a size.
+const GRID_FIT: f64 = 0.9;
+/// The largest cell size looked for, in pixels.
+const MAX_CELL_SIZE: usize = 256;
+
+/// An image divided into cells, with the lightness of each.
+pub struct ImageScan {
+    pub width: usize,
+    pub height: usize,
+    /// The size of a cell in pixels.
+    pub cell_size: (usize, usize),
+    // The average lightness of the middle of each cell
+    lightness: Vec<u8>,
+}
+
+/// Whether a file is read as an image, by its extension.
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+}
+
+/// Reads a PNG and finds its grid.
+pub fn read(path: &Path) -> Result<ImageScan, ConwayError> {
+    let content = std::fs::read(path).map_err(ConwayError::io(path))?;
+    scan(&content).map_err(|e| {
+        ConwayError::Validation(format!("{} is not a valid PNG: {}", path.display(), e))
+    })
+}
+
+fn scan(content: &[u8]) -> Result<ImageScan, png::DecodingError> {
+    let mut decoder = png::Decoder::new(content);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    let channels = info.color_type.samples();
+
+    // Transparent pixels are taken as white
+    let pixels = buffer[..info.buffer_size()]
+        .chunks_exact(channels)
+        .map(|pixel| {
+            let (lightness, alpha) = match pixel {
+                [gray] => (*gray as u32, 255),
+                [gray, alpha] => (*gray as u32, *alpha as u32),
+                [r, g, b] => (get_lightness(*r, *g, *b), 255),
+                [r, g, b, alpha] => (get_lightness(*r, *g, *b), *alpha as u32),
+                _ => unreachable!(),
+            };
+            ((lightness * alpha + 255 * (255 - alpha)) / 255) as u8
+        })
+        .collect::<Vec<_>>();
+
+    let row_edges = (0..height).flat_map(|y| {
+        let pixels = &pixels;
+        (1..width).filter(move |&x| is_edge(pixels[y * width + x - 1], pixels[y * width + x]))
+    });
+    let (cell_width, left) = find_grid(row_edges, width);
+    let column_edges = (0..width).flat_map(|x| {
+        let pixels = &pixels;
+        (1..height).filter(move |&y| is_edge(pixels[(y - 1) * width + x], pixels[y * width + x]))
+    });
+    let (cell_height, top) = find_grid(column_edges, height);
+
+    let columns = (width - left) / cell_width;
+    let rows = (height - top) / cell_height;
+    let mut lightness = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            // Only the middle of a cell is sampled, away from any grid lines
+            let middle = |start: usize, size: usize| start + size / 4..start + (size - size / 4);
+            let ys = middle(top + row * cell_height, cell_height);
+            let xs = middle(left + column * cell_width, cell_width);
+            let count = ys.len() * xs.len();
+            let sum: usize = ys
+                .flat_map(|y| xs.clone().map(move |x| y * width + x))
+                .map(|i| pixels[i] as usize)
+                .sum();
+            lightness.push((sum / count) as u8);
+        }
+    }
+
+    Ok(ImageScan {
+        width: columns,
+        height: rows,
+        cell_size: (cell_width, cell_height),
+        lightness,
+    })
+}
+
+impl ImageScan {
+    /// A threshold halfway between the lightest and darkest cells, and
+    /// whether live cells are the dark ones, as they are fewer.
+    pub fn get_auto_threshold(&self) -> (u8, bool) {
+        let min = self.lightness.iter().copied().min().unwrap_or(0);
+        let max = self.lightness.iter().copied().max().unwrap_or(255);
+        let threshold = if min == max {
+            128
+        } else {
+            (min as u16 + max as u16).div_ceil(2) as u8
+        };
+        let dark = self
+            .lightness
+            .iter()
+            .filter(|&&lightness| lightness < threshold)
+            .count();
+        (threshold, dark * 2 <= self.lightness.len())
+    }
+
+    /// The cells lighter than the threshold are live, or the darker ones
+    /// with `dark` set.
+    pub fn to_pattern(&self, threshold: u8, dark: bool) -> Pattern {
+        let cells = self
+            .lightness
+            .iter()
+            .map(|&lightness| ((lightness < threshold) == dark) as u8)
+            .collect();
+        Pattern {
+            width: self.width,
+            height: self.height,
+            cells,
+            rule: None,
+            topology: None,
+        }
+    }
+}
+
+fn get_lightness(r: u8, g: u8, b: u8) -> u32 {
+    (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000
+}
+
+fn is_edge(a: u8, b: u8) -> bool {
+    a.abs_diff(b) >= EDGE_CONTRAST
+}
+
+/// Finds the size and offset of the cells along an axis of `length` pixels
+/// from where its edges are. The edges of pixel art all fall on the borders
+/// of cells, and those of screenshots with grid lines on either side of the
+/// lines. The smallest size fitting the edges is preferred, and grows to a
+/// multiple of itself only if that fits every edge it does, as the edges of
+/// cells of a size also fall on the borders of its divisors.
+fn find_grid(edges: impl Iterator<Item = usize>, length: usize) -> (usize, usize) {
+    let mut counts = vec![0usize; length];
+    for edge in edges {
+        counts[edge] += 1;
+    }
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        // A blank image is a single cell
+        return (length, 0);
+    }
+
+    // The offset fitting the most edges to the borders of cells of a size,
+    // and how many it fits. Grid lines take a pixel off cells of at least
+    // four pixels.
+    let fit = |size: usize, lines: bool| {
+        let mut folded = vec![0usize; size];
+        for (position, count) in counts.iter().enumerate() {
+            folded[position % size] += count;
+        }
+        (0..size)
+            .map(|offset| {
+                let fitted = folded[offset]
+                    + if lines {
+                        folded[(offset + 1) % size]
+                    } else {
+                        0
+                    };
+                (fitted, offset)
+            })
+            .max()
+            .unwrap()
+    };
+    let fits = |fitted: usize| fitted as f64 >= total as f64 * GRID_FIT;
+    let largest = length.min(MAX_CELL_SIZE);
+    for lines in [false, true] {
+        let smallest = if lines { 4 } else { 2 };
+        let Some((size, fitted, offset)) = (smallest..=largest).find_map(|size| {
+            let (fitted, offset) = fit(size, lines);
+            fits(fitted).then_some((size, fitted, offset))
+        }) else {
+            continue;
+        };
+        return (2..=largest / size)
+            .rev()
+            .map(|factor| size * factor)
+            .find_map(|multiple| {
+                let (multiple_fitted, offset) = fit(multiple, lines);
+                (multiple_fitted == fitted).then_some((multiple, offset))
+            })
+            .unwrap_or((size, offset));
+    }
+    (1, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_large_pixel_art_cells() {
+        let edges = [3, 11, 27, 35, 43, 75];
+        assert_eq!(find_grid(edges.into_iter(), 100), (8, 3));
+    }
+
+    #[test]
+    fn prefers_small_cells_fitting_more_edges() {
+        // Two pixel cells with most, but not all, edges four pixels apart
+        let edges = (1..20).map(|i| i * 4).chain([42]);
+        assert_eq!(find_grid(edges, 100), (2, 0));
+    }
+
+    #[test]
+    fn finds_cells_between_grid_lines() {
+        // Ten pixel cells with a grid line on their first pixel
+        let edges = (1..9).flat_map(|i| [i * 10, i * 10 + 1]);
+        assert_eq!(find_grid(edges, 100), (10, 0));
+    }
+
+    #[test]
+    fn takes_blank_axes_as_one_cell() {
+        assert_eq!(find_grid(std::iter::empty(), 40), (40, 0));
+    }
+}
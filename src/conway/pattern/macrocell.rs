@@ -0,0 +1,562 @@
+//! Golly's macrocell format, a quadtree of the pattern where equal subtrees
+//! are written once, e.g.
+//!
+//! ```text
+//! [M2] (conway-rs)
+//! #R B3/S23
+//! .*$..*$***$
+//! 4 1 0 0 0
+//! ```
+//!
+//! Every line after the header is a node, numbered from 1, with 0 standing
+//! for an empty node and the last line for the whole pattern. A node of
+//! level `k` is `2^k` cells wide, written as `k nw ne sw se` with its
+//! quadrants as nodes of level `k - 1`. Two-state patterns write the nodes of
+//! level 3 as 8x8 cells instead, in rows of `.` and `*` ended by `$`, and
+//! patterns with more states write the nodes of level 1 as `1 nw ne sw se`
+//! with the states of the cells.
+
+use std::collections::HashMap;
+
+use super::Pattern;
+use crate::conway::error::{column_of, ConwayError};
+use crate::conway::game_state::MAX_BOARD_SIZE;
+use crate::conway::rule::Rule;
+use crate::conway::topology::Topology;
+
+/// The level of the 8x8 leaves of two-state patterns.
+const LEAF_LEVEL: u32 = 3;
+/// The deepest level, for coordinates to fit in 64 bits.
+const MAX_LEVEL: u32 = 62;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Node {
+    /// The states of a 2x2 block of cells, in the order nw, ne, sw, se.
+    Cells([u8; 4]),
+    /// The level of a larger node and its quadrants.
+    Quadrants(u32, [usize; 4]),
+}
+
+/// The cells of a node that are not dead, from its top left corner.
+#[derive(Clone, Copy)]
+struct Bounds {
+    left: u64,
+    top: u64,
+    right: u64,
+    bottom: u64,
+}
+
+/// A pattern of any size as a quadtree, sharing the nodes that are equal.
+pub struct QuadTree {
+    // Nodes by index, 0 being the empty node of every level
+    nodes: Vec<Node>,
+    bounds: Vec<Option<Bounds>>,
+    indices: HashMap<Node, usize>,
+    root: usize,
+    level: u32,
+}
+
+impl QuadTree {
+    fn new() -> QuadTree {
+        QuadTree {
+            nodes: vec![Node::Cells([0; 4])],
+            bounds: vec![None],
+            indices: HashMap::new(),
+            root: 0,
+            level: 1,
+        }
+    }
+
+    /// Builds the tree of a rectangle of cells centered on the center of the
+    /// tree, where Golly puts the origin.
+    pub fn from_cells(width: usize, height: usize, cells: &[u8]) -> QuadTree {
+        let mut tree = QuadTree::new();
+        let size = width.max(height).max(1).next_power_of_two();
+        tree.level = (size.trailing_zeros() + 1).max(LEAF_LEVEL);
+        let half = 1 << (tree.level - 1);
+        let (x, y) = ((width / 2) as i64 - half, (height / 2) as i64 - half);
+        tree.root = tree.build(tree.level, x, y, width, height, cells);
+        tree
+    }
+
+    /// The index of a node, added unless an equal one is in the tree.
+    fn add(&mut self, node: Node) -> usize {
+        match node {
+            Node::Cells([0, 0, 0, 0]) | Node::Quadrants(_, [0, 0, 0, 0]) => return 0,
+            _ => {}
+        }
+        if let Some(&index) = self.indices.get(&node) {
+            return index;
+        }
+        let bounds = match node {
+            Node::Cells(states) => (0..4)
+                .filter(|&i| states[i] != 0)
+                .map(|i| {
+                    let (x, y) = ((i % 2) as u64, (i / 2) as u64);
+                    Bounds {
+                        left: x,
+                        top: y,
+                        right: x + 1,
+                        bottom: y + 1,
+                    }
+                })
+                .reduce(union),
+            Node::Quadrants(level, children) => {
+                let half = 1u64 << (level - 1);
+                (0..4)
+                    .filter_map(|i| {
+                        let (x, y) = ((i % 2) as u64 * half, (i / 2) as u64 * half);
+                        self.bounds[children[i]].map(|bounds| Bounds {
+                            left: bounds.left + x,
+                            top: bounds.top + y,
+                            right: bounds.right + x,
+                            bottom: bounds.bottom + y,
+                        })
+                    })
+                    .reduce(union)
+            }
+        };
+        self.nodes.push(node);
+        self.bounds.push(bounds);
+        self.indices.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// Builds the node of a level whose top left corner is at `(x, y)` from
+    /// the top left corner of a rectangle of cells, the cells past the
+    /// rectangle being dead.
+    fn build(
+        &mut self,
+        level: u32,
+        x: i64,
+        y: i64,
+        width: usize,
+        height: usize,
+        cells: &[u8],
+    ) -> usize {
+        let size = 1 << level;
+        if x >= width as i64 || y >= height as i64 || x + size <= 0 || y + size <= 0 {
+            return 0;
+        }
+        if level == 1 {
+            let state = |dx: i64, dy: i64| {
+                let (x, y) = (x + dx, y + dy);
+                if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                    cells[y as usize * width + x as usize]
+                } else {
+                    0
+                }
+            };
+            return self.add(Node::Cells([
+                state(0, 0),
+                state(1, 0),
+                state(0, 1),
+                state(1, 1),
+            ]));
+        }
+        let half = 1 << (level - 1);
+        let children = [(0, 0), (half, 0), (0, half), (half, half)]
+            .map(|(dx, dy)| self.build(level - 1, x + dx, y + dy, width, height, cells));
+        self.add(Node::Quadrants(level, children))
+    }
+
+    /// Copies the cells of a node whose top left corner is at `(x, y)` that
+    /// are in an area of the tree, given by its corners, into the cells of
+    /// the area.
+    fn fill(
+        &self,
+        node: usize,
+        (x, y): (i64, i64),
+        ((left, top), (right, bottom)): ((i64, i64), (i64, i64)),
+        cells: &mut [u8],
+    ) {
+        let Some(bounds) = self.bounds[node] else {
+            return;
+        };
+        if x + bounds.right as i64 <= left
+            || y + bounds.bottom as i64 <= top
+            || x + bounds.left as i64 >= right
+            || y + bounds.top as i64 >= bottom
+        {
+            return;
+        }
+        let width = (right - left) as usize;
+        match self.nodes[node] {
+            Node::Cells(states) => {
+                for (i, &state) in states.iter().enumerate() {
+                    let (x, y) = (x + (i % 2) as i64, y + (i / 2) as i64);
+                    if state != 0 && (left..right).contains(&x) && (top..bottom).contains(&y) {
+                        cells[(y - top) as usize * width + (x - left) as usize] = state;
+                    }
+                }
+            }
+            Node::Quadrants(level, children) => {
+                let half = 1 << (level - 1);
+                for (i, &child) in children.iter().enumerate() {
+                    let corner = (x + (i % 2) as i64 * half, y + (i / 2) as i64 * half);
+                    self.fill(child, corner, ((left, top), (right, bottom)), cells);
+                }
+            }
+        }
+    }
+}
+
+fn union(a: Bounds, b: Bounds) -> Bounds {
+    Bounds {
+        left: a.left.min(b.left),
+        top: a.top.min(b.top),
+        right: a.right.max(b.right),
+        bottom: a.bottom.max(b.bottom),
+    }
+}
+
+/// A pattern read from a macrocell file.
+pub struct Macrocell {
+    pub tree: QuadTree,
+    pub rule: Option<Rule>,
+    pub topology: Option<Topology>,
+    /// The size of the bounded grid of the rule, 0 for unbounded axes.
+    pub grid_size: (u64, u64),
+}
+
+impl Macrocell {
+    /// The cells of the bounded grid, centered on the origin, or the
+    /// rectangle around the cells that are not dead along unbounded axes, if
+    /// it is no larger than a board can be.
+    pub fn flatten(&self) -> Result<Pattern, ConwayError> {
+        let tree = &self.tree;
+        let half = 1 << (tree.level - 1);
+        let bounds = tree.bounds[tree.root];
+        let axis = |size: u64, start: Option<u64>, end: Option<u64>| match (size, start, end) {
+            (0, Some(start), Some(end)) => (start as i64, end as i64),
+            (0, _, _) => (0, 0),
+            _ => (
+                half - (size / 2) as i64,
+                half - (size / 2) as i64 + size as i64,
+            ),
+        };
+        let (left, right) = axis(
+            self.grid_size.0,
+            bounds.map(|bounds| bounds.left),
+            bounds.map(|bounds| bounds.right),
+        );
+        let (top, bottom) = axis(
+            self.grid_size.1,
+            bounds.map(|bounds| bounds.top),
+            bounds.map(|bounds| bounds.bottom),
+        );
+        let (width, height) = ((right - left) as u64, (bottom - top) as u64);
+        if width > MAX_BOARD_SIZE as u64 || height > MAX_BOARD_SIZE as u64 {
+            return Err(ConwayError::Validation(format!(
+                "The pattern is {}x{} cells, larger than a board of {}x{}",
+                width, height, MAX_BOARD_SIZE, MAX_BOARD_SIZE
+            )));
+        }
+        let (width, height) = (width as usize, height as usize);
+        let mut cells = vec![0; width * height];
+        tree.fill(
+            tree.root,
+            (0, 0),
+            ((left, top), (right, bottom)),
+            &mut cells,
+        );
+        Ok(Pattern {
+            width,
+            height,
+            cells,
+            rule: self.rule.clone(),
+            topology: self.topology,
+        })
+    }
+}
+
+pub fn parse(content: &str) -> Result<Macrocell, ConwayError> {
+    let mut lines = content.lines().enumerate().map(|(i, line)| (i + 1, line));
+    match lines.next() {
+        Some((_, header)) if header.starts_with("[M2]") => {}
+        _ => return Err(ConwayError::parse(1, 1, "Missing macrocell header [M2]")),
+    }
+
+    let mut tree = QuadTree::new();
+    // The index and level in the tree of each node of the file
+    let mut nodes = vec![(0, 0)];
+    let mut rule = None;
+    let mut topology = None;
+    let mut grid_size = (0, 0);
+    for (number, line) in lines {
+        let error = |part: &str, message: String| {
+            ConwayError::parse(number, column_of(line, part), message)
+        };
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        if let Some(comment) = text.strip_prefix('#') {
+            // A bounded grid may follow the rule, as in RLE
+            if let Some(text) = comment.strip_prefix('R') {
+                let text = text.trim();
+                let (name, grid) = text.split_once(':').unwrap_or((text, ""));
+                rule = Some(name.parse().map_err(|e| error(name, e))?);
+                topology = (!grid.is_empty())
+                    .then(|| grid.parse().map_err(|e| error(grid, e)))
+                    .transpose()?;
+                grid_size = get_grid_size(grid);
+            }
+            continue;
+        }
+
+        if text.starts_with(['.', '*', '$']) {
+            let mut cells = [0; 64];
+            let (mut x, mut y) = (0, 0);
+            for (offset, c) in text.char_indices() {
+                let error = |message: &str| error(&text[offset..], message.to_string());
+                match c {
+                    '.' | '*' if x >= 8 || y >= 8 => return Err(error("A leaf is 8x8 cells")),
+                    '.' => x += 1,
+                    '*' => {
+                        cells[y * 8 + x] = 1;
+                        x += 1;
+                    }
+                    '$' => {
+                        x = 0;
+                        y += 1;
+                    }
+                    _ => return Err(error(&format!("Invalid character: {}", c))),
+                }
+            }
+            nodes.push((tree.build(LEAF_LEVEL, 0, 0, 8, 8, &cells), LEAF_LEVEL));
+            continue;
+        }
+
+        let parts: Vec<&str> = text.split_whitespace().collect();
+        let [level, nw, ne, sw, se] = parts[..] else {
+            return Err(error(
+                text,
+                "A node is a level and four quadrants".to_string(),
+            ));
+        };
+        let quadrants = [nw, ne, sw, se];
+        let level = level
+            .parse::<u32>()
+            .ok()
+            .filter(|level| (1..=MAX_LEVEL).contains(level))
+            .ok_or_else(|| {
+                error(
+                    level,
+                    format!("The level must be from 1 to {}, not {}", MAX_LEVEL, level),
+                )
+            })?;
+        let node = if level == 1 {
+            let mut states = [0; 4];
+            for (state, part) in states.iter_mut().zip(quadrants) {
+                *state = part
+                    .parse()
+                    .map_err(|_| error(part, format!("Invalid state: {}", part)))?;
+            }
+            Node::Cells(states)
+        } else {
+            let mut children = [0; 4];
+            for (child, part) in children.iter_mut().zip(quadrants) {
+                let (index, child_level) = part
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| nodes.get(i).copied())
+                    .ok_or_else(|| error(part, format!("Node {} is not defined before", part)))?;
+                if index != 0 && child_level != level - 1 {
+                    return Err(error(
+                        part,
+                        format!(
+                            "Node {} is of level {}, not {}",
+                            part,
+                            child_level,
+                            level - 1
+                        ),
+                    ));
+                }
+                *child = index;
+            }
+            Node::Quadrants(level, children)
+        };
+        nodes.push((tree.add(node), level));
+    }
+
+    let &(root, level) = nodes
+        .last()
+        .filter(|_| nodes.len() > 1)
+        .ok_or_else(|| ConwayError::parse_at_end(content, "Missing macrocell nodes"))?;
+    tree.root = root;
+    tree.level = level;
+    Ok(Macrocell {
+        tree,
+        rule,
+        topology,
+        grid_size,
+    })
+}
+
+/// The size of a bounded grid, like 20 and 11 for `T20,11` or `K20*,11`,
+/// with 0 for unbounded axes.
+fn get_grid_size(grid: &str) -> (u64, u64) {
+    let sizes = grid.get(1..).unwrap_or_default();
+    let (width, height) = sizes.split_once(',').unwrap_or((sizes, sizes));
+    let size = |axis: &str| {
+        let end = axis
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(axis.len());
+        axis[..end].parse().unwrap_or(0)
+    };
+    (size(width), size(height))
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let multi_state = pattern.cells.iter().any(|&cell| cell > 1)
+        || pattern
+            .rule
+            .as_ref()
+            .is_some_and(|rule| rule.get_states() > 2);
+    let tree = QuadTree::from_cells(pattern.width, pattern.height, &pattern.cells);
+
+    let mut content = "[M2] (conway-rs)\n".to_string();
+    if let Some(rule) = &pattern.rule {
+        content += &format!("#R {}", rule);
+        if let Some(grid) = pattern
+            .topology
+            .filter(|&topology| topology != Topology::Plane)
+            .and_then(|topology| topology.format_bounded_grid(pattern.width, pattern.height))
+        {
+            content += &format!(":{}", grid);
+        }
+        content.push('\n');
+    }
+    if tree.root == 0 {
+        // An empty pattern still needs a node
+        content += if multi_state { "1 0 0 0 0\n" } else { "$\n" };
+        return content;
+    }
+    let mut numbers = HashMap::from([(0, 0)]);
+    write_node(&tree, tree.root, multi_state, &mut numbers, &mut content);
+    content
+}
+
+/// Writes a node after its quadrants, returning its number in the file.
+fn write_node(
+    tree: &QuadTree,
+    node: usize,
+    multi_state: bool,
+    numbers: &mut HashMap<usize, usize>,
+    content: &mut String,
+) -> usize {
+    if let Some(&number) = numbers.get(&node) {
+        return number;
+    }
+    let line = match tree.nodes[node] {
+        Node::Cells(states) => format!("1 {} {} {} {}", states[0], states[1], states[2], states[3]),
+        Node::Quadrants(LEAF_LEVEL, _) if !multi_state => {
+            let mut cells = [0; 64];
+            tree.fill(node, (0, 0), ((0, 0), (8, 8)), &mut cells);
+            // Trailing dead cells and empty rows are left out
+            let mut rows: Vec<String> = cells
+                .chunks(8)
+                .map(|row| {
+                    let end = row.iter().rposition(|&cell| cell != 0).map_or(0, |i| i + 1);
+                    row[..end]
+                        .iter()
+                        .map(|&cell| if cell != 0 { '*' } else { '.' })
+                        .collect()
+                })
+                .collect();
+            while rows.last().is_some_and(String::is_empty) {
+                rows.pop();
+            }
+            rows.iter().map(|row| format!("{}$", row)).collect()
+        }
+        Node::Quadrants(level, children) => {
+            let children =
+                children.map(|child| write_node(tree, child, multi_state, numbers, content));
+            format!(
+                "{} {} {} {} {}",
+                level, children[0], children[1], children[2], children[3]
+            )
+        }
+    };
+    content.push_str(&line);
+    content.push('\n');
+    let number = numbers.len();
+    numbers.insert(node, number);
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "[M2] (conway-rs)\n#R B3/S23\n.*$..*$***$\n4 1 0 0 0\n";
+
+    fn glider() -> Pattern {
+        Pattern {
+            width: 3,
+            height: 3,
+            cells: vec![0, 1, 0, 0, 0, 1, 1, 1, 1],
+            rule: Some(Rule::default()),
+            topology: None,
+        }
+    }
+
+    #[test]
+    fn reads_a_glider() {
+        let pattern = parse(GLIDER).unwrap().flatten().unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, glider().cells);
+        assert_eq!(pattern.rule, Some(Rule::default()));
+    }
+
+    #[test]
+    fn round_trips_a_glider() {
+        let pattern = parse(&write(&glider())).unwrap().flatten().unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, glider().cells);
+    }
+
+    #[test]
+    fn round_trips_a_bounded_grid() {
+        let mut torus = Pattern {
+            width: 20,
+            height: 12,
+            cells: vec![0; 20 * 12],
+            rule: Some(Rule::default()),
+            topology: Some(Topology::TORUS),
+        };
+        // Cells in the corners, so the whole grid has to be kept
+        for i in [0, 19, 11 * 20, 12 * 20 - 1] {
+            torus.cells[i] = 1;
+        }
+        let content = write(&torus);
+        assert!(content.contains("#R B3/S23:T20,12\n"));
+        let pattern = parse(&content).unwrap().flatten().unwrap();
+        assert_eq!((pattern.width, pattern.height), (20, 12));
+        assert_eq!(pattern.cells, torus.cells);
+        assert_eq!(pattern.topology, Some(Topology::TORUS));
+    }
+
+    #[test]
+    fn round_trips_more_states() {
+        let pattern = Pattern {
+            width: 4,
+            height: 2,
+            cells: vec![0, 1, 2, 3, 3, 2, 1, 0],
+            rule: Some("B2/S/C4".parse().unwrap()),
+            topology: None,
+        };
+        let read = parse(&write(&pattern)).unwrap().flatten().unwrap();
+        assert_eq!((read.width, read.height), (4, 2));
+        assert_eq!(read.cells, pattern.cells);
+    }
+
+    #[test]
+    fn rejects_a_missing_header() {
+        assert!(matches!(
+            parse("#R B3/S23\n.*$\n"),
+            Err(ConwayError::Parse { line: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,274 @@
+//! Golly's run length encoded pattern format, e.g.
+//!
+//! ```text
+//! x = 3, y = 3, rule = B3/S23
+//! bo$2bo$3o!
+//! ```
+//!
+//! A bounded grid may follow the rule, as in `rule = B3/S23:T32,32`.
+//!
+//! Two-state patterns use `b` and `o` for dead and live cells, patterns with
+//! more states use the alphabet of [`super::format_state`].
+
+use super::{format_state, parse_state, Pattern};
+use crate::conway::error::{column_of, ConwayError};
+use crate::conway::game_state::MAX_BOARD_SIZE;
+use crate::conway::topology::Topology;
+
+const MAX_LINE_LENGTH: usize = 70;
+
+pub fn parse(content: &str) -> Result<Pattern, ConwayError> {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('#')
+        });
+
+    let (number, header) = lines
+        .next()
+        .ok_or_else(|| ConwayError::parse_at_end(content, "Missing RLE header"))?;
+    let error =
+        |part: &str, message: String| ConwayError::parse(number, column_of(header, part), message);
+    // The rule may contain commas itself, so it is split off first
+    let (dimensions, rule) = match header.find("rule") {
+        Some(pos) => {
+            let (_, rule) = header[pos..]
+                .split_once('=')
+                .ok_or_else(|| error(&header[pos..], "Invalid RLE header".to_string()))?;
+            (&header[..pos], Some(rule.trim()))
+        }
+        None => (header, None),
+    };
+
+    let mut width = None;
+    let mut height = None;
+    for item in dimensions.split(',').filter(|item| !item.trim().is_empty()) {
+        let (key, value) = item
+            .split_once('=')
+            .ok_or_else(|| error(item, "Invalid RLE header".to_string()))?;
+        let value = value.trim();
+        let size = || {
+            value
+                .parse::<usize>()
+                .map_err(|_| error(value, format!("Invalid size: {}", value)))
+        };
+        match key.trim() {
+            "x" => width = Some(size()?),
+            "y" => height = Some(size()?),
+            _ => {}
+        }
+    }
+    let width = width.ok_or_else(|| error(header, "Missing pattern width".to_string()))?;
+    let height = height.ok_or_else(|| error(header, "Missing pattern height".to_string()))?;
+    // The size is checked before the cells are allocated
+    if width > MAX_BOARD_SIZE || height > MAX_BOARD_SIZE {
+        return Err(error(
+            header,
+            format!(
+                "The pattern is {}x{} cells, larger than a board of {}x{}",
+                width, height, MAX_BOARD_SIZE, MAX_BOARD_SIZE
+            ),
+        ));
+    }
+    let (rule, topology) = match rule.map(|rule| rule.split_once(':').unwrap_or((rule, ""))) {
+        Some((rule, topology)) => (
+            Some(rule.parse().map_err(|e| error(rule, e))?),
+            (!topology.is_empty())
+                .then(|| topology.parse().map_err(|e| error(topology, e)))
+                .transpose()?,
+        ),
+        None => (None, None),
+    };
+
+    let mut cells = vec![0; width * height];
+    let (mut x, mut y): (usize, usize) = (0, 0);
+    let mut count: usize = 0;
+    'lines: for (number, line) in lines {
+        let mut chars = line.chars();
+        while let Some(c) = chars.next() {
+            let offset = line.len() - chars.as_str().len() - c.len_utf8();
+            let error = |message: String| {
+                ConwayError::parse(number, column_of(line, &line[offset..]), message)
+            };
+            match c {
+                '0'..='9' => {
+                    count = count
+                        .saturating_mul(10)
+                        .saturating_add(c.to_digit(10).unwrap() as usize)
+                }
+                '!' => break 'lines,
+                '$' => {
+                    y = y.saturating_add(count.max(1));
+                    x = 0;
+                    count = 0;
+                }
+                _ if c.is_whitespace() => {}
+                _ => {
+                    let state = match c {
+                        'b' => 0,
+                        'o' => 1,
+                        // Other letters are live cells too, unless they start
+                        // a two-character state
+                        'a'..='z' if !matches!(chars.clone().next(), Some('A'..='X')) => 1,
+                        _ => parse_state(c, &mut chars)
+                            .ok_or_else(|| error(format!("Invalid character: {}", c)))?,
+                    };
+                    for _ in 0..count.max(1) {
+                        if x >= width || y >= height {
+                            return Err(error("Pattern exceeds its declared size".to_string()));
+                        }
+                        cells[y * width + x] = state;
+                        x += 1;
+                    }
+                    count = 0;
+                }
+            }
+        }
+    }
+
+    Ok(Pattern {
+        width,
+        height,
+        cells,
+        rule,
+        topology,
+    })
+}
+
+pub fn write(pattern: &Pattern) -> String {
+    let multi_state = pattern.cells.iter().any(|&cell| cell > 1)
+        || pattern
+            .rule
+            .as_ref()
+            .is_some_and(|rule| rule.get_states() > 2);
+    let format_run = |count: usize, state: u8| {
+        let token = match (multi_state, state) {
+            (false, 0) => "b".to_string(),
+            (false, _) => "o".to_string(),
+            (true, _) => format_state(state),
+        };
+        if count > 1 {
+            format!("{}{}", count, token)
+        } else {
+            token
+        }
+    };
+
+    let mut runs = Vec::new();
+    let mut last_row = 0;
+    for y in 0..pattern.height {
+        let row = &pattern.cells[y * pattern.width..(y + 1) * pattern.width];
+        // Trailing dead cells are left out
+        let Some(end) = row.iter().rposition(|&cell| cell != 0) else {
+            continue;
+        };
+
+        if y > last_row {
+            runs.push(if y - last_row > 1 {
+                format!("{}$", y - last_row)
+            } else {
+                "$".to_string()
+            });
+            last_row = y;
+        }
+
+        let mut x = 0;
+        while x <= end {
+            let state = row[x];
+            let count = row[x..=end]
+                .iter()
+                .take_while(|&&cell| cell == state)
+                .count();
+            runs.push(format_run(count, state));
+            x += count;
+        }
+    }
+    runs.push("!".to_string());
+
+    let mut content = format!("x = {}, y = {}", pattern.width, pattern.height);
+    if let Some(rule) = &pattern.rule {
+        content += &format!(", rule = {}", rule);
+        // A plane is left out so the pattern stays unbounded in Golly, and
+        // a mirror as Golly has no such grid
+        if let Some(grid) = pattern
+            .topology
+            .filter(|&topology| topology != Topology::Plane)
+            .and_then(|topology| topology.format_bounded_grid(pattern.width, pattern.height))
+        {
+            content += &format!(":{}", grid);
+        }
+    }
+    content.push('\n');
+
+    let mut line_length = 0;
+    for run in runs {
+        if line_length + run.len() > MAX_LINE_LENGTH {
+            content.push('\n');
+            line_length = 0;
+        }
+        line_length += run.len();
+        content += &run;
+    }
+    content.push('\n');
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n";
+
+    #[test]
+    fn parses_a_glider() {
+        let pattern = parse(GLIDER).unwrap();
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.cells, [0, 1, 0, 0, 0, 1, 1, 1, 1]);
+        assert_eq!(pattern.rule, Some("B3/S23".parse().unwrap()));
+        assert_eq!(pattern.topology, None);
+    }
+
+    #[test]
+    fn writes_a_glider_back() {
+        assert_eq!(write(&parse(GLIDER).unwrap()), GLIDER);
+    }
+
+    #[test]
+    fn round_trips_a_torus() {
+        let content = "x = 3, y = 3, rule = B3/S23:T3,3\nbo$2bo$3o!\n";
+        let pattern = parse(content).unwrap();
+        assert_eq!(pattern.topology, Some(Topology::TORUS));
+        assert_eq!(write(&pattern), content);
+    }
+
+    #[test]
+    fn leaves_out_the_grid_of_a_mirror() {
+        let mut pattern = parse(GLIDER).unwrap();
+        pattern.topology = Some(Topology::Mirror);
+        assert_eq!(write(&pattern), GLIDER);
+    }
+
+    #[test]
+    fn rejects_a_huge_header_before_allocating() {
+        let content = "x = 4000000000, y = 4000000000\no!\n";
+        assert!(matches!(
+            parse(content),
+            Err(ConwayError::Parse { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_cells_past_the_declared_size() {
+        assert!(matches!(
+            parse("x = 2, y = 1\n3o!\n"),
+            Err(ConwayError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            parse("x = 2, y = 1\n99999999999999999999999$o!\n"),
+            Err(ConwayError::Parse { line: 2, .. })
+        ));
+    }
+}
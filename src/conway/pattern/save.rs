@@ -0,0 +1,117 @@
+//! The format of `.conway` saves: a line with the width, height, topology
+//! and rule of the board, and a line with every cell, e.g.
+//!
+//! ```text
+//! 8 8 T B3/S23
+//! 0100000000100000111000000000000000000000000000000000000000000000
+//! ```
+//!
+//! Cells are digits, or in the alphabet of [`super::format_state`] from the
+//! state 10 on.
+
+use super::{format_state, parse_state, Pattern};
+use crate::conway::error::{column_of, ConwayError};
+use crate::conway::rule::Rule;
+use crate::conway::topology::Topology;
+
+/// Parses a save. A rule table loaded from outside the rules directory is
+/// only known by name, so a save naming `current_rule` keeps it.
+pub fn parse(content: &str, current_rule: &Rule) -> Result<Pattern, ConwayError> {
+    let mut lines = content.lines();
+
+    let line = lines
+        .next()
+        .ok_or_else(|| ConwayError::parse_at_end(content, "Missing board size"))?;
+    let error = |part: &str, message: String| ConwayError::parse(1, column_of(line, part), message);
+    let mut parts = line.split_whitespace();
+    let mut next = |name: &str| {
+        parts
+            .next()
+            .ok_or_else(|| error(&line[line.trim_end().len()..], format!("Missing {}", name)))
+    };
+    let size = |part: &str| {
+        part.parse::<usize>()
+            .map_err(|_| error(part, format!("Invalid size: {}", part)))
+    };
+    let width = size(next("width")?)?;
+    let height = size(next("height")?)?;
+    // Older saves store whether the board is periodic instead
+    let topology = match next("topology")? {
+        "true" => Topology::TORUS,
+        "false" => Topology::Plane,
+        topology => topology.parse().map_err(|e| error(topology, e))?,
+    };
+    // Older saves have no rule and are always B3/S23
+    let rule = match parts.next() {
+        Some(rule) if current_rule.get_table().is_some() && *rule == current_rule.to_string() => {
+            current_rule.clone()
+        }
+        Some(rule) => rule.parse().map_err(|e| error(rule, e))?,
+        None => Rule::default(),
+    };
+
+    let line = lines
+        .next()
+        .ok_or_else(|| ConwayError::parse_at_end(content, "Missing cells"))?;
+    let mut cells = Vec::with_capacity(width.saturating_mul(height).min(line.len()));
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        let state = match c.to_digit(10) {
+            Some(digit) => digit as u8,
+            None => parse_state(c, &mut chars).ok_or_else(|| {
+                let offset = line.len() - chars.as_str().len() - c.len_utf8();
+                ConwayError::parse(
+                    2,
+                    column_of(line, &line[offset..]),
+                    format!("Invalid cell state: {}", c),
+                )
+            })?,
+        };
+        cells.push(state);
+    }
+
+    // Check if the number of cells match the width and height
+    if Some(cells.len()) != width.checked_mul(height) {
+        return Err(ConwayError::parse(
+            2,
+            1,
+            format!(
+                "Expected {}x{} cells but found {}",
+                width,
+                height,
+                cells.len()
+            ),
+        ));
+    }
+
+    Ok(Pattern {
+        width,
+        height,
+        cells,
+        rule: Some(rule),
+        topology: Some(topology),
+    })
+}
+
+/// Writes a save. Patterns without a rule or topology are saved as B3/S23
+/// on a plane.
+pub fn write(pattern: &Pattern) -> String {
+    let topology = pattern.topology.unwrap_or(Topology::Plane);
+    let rule = pattern.rule.clone().unwrap_or_default();
+    let cells = pattern
+        .cells
+        .iter()
+        .map(|&cell| {
+            // Digits keep two-state saves readable by older versions
+            if cell < 10 {
+                cell.to_string()
+            } else {
+                format_state(cell)
+            }
+        })
+        .collect::<String>();
+    format!(
+        "{} {} {} {}\n{}",
+        pattern.width, pattern.height, topology, rule, cells
+    )
+}
@@ -0,0 +1,144 @@
+//! Draws boards in software, to export them as images without a window.
+//!
+//! Pixels are the states of the cells they show, so the palette of a rule
+//! colors the image.
+
+use std::ops::Range;
+
+use super::error::ConwayError;
+use super::game_state::{ConwayGameState, ALIVE, DEAD};
+use super::rule::Rule;
+use super::theme::Theme;
+
+/// The most pixels an image is drawn with, about 8192 by 8192, so large
+/// boards at large cell sizes fail instead of running out of memory.
+pub const MAX_IMAGE_PIXELS: usize = 1 << 26;
+
+/// An image with a palette index per pixel, row by row.
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+/// The color of every state of a rule as RGB. Dead cells take the
+/// background color of the theme and live cells its text color, and other
+/// states fade from the live color to the background, unless the rule table
+/// brings its own colors.
+pub fn get_palette(rule: &Rule, theme: Theme) -> Vec<[u8; 3]> {
+    let (r, g, b) = theme.get_background_color().rgb();
+    let background = [r, g, b];
+    let (r, g, b) = theme.get_text_color().rgb();
+    let foreground = [r, g, b];
+    let states = rule.get_states();
+    (0..states)
+        .map(|state| {
+            if let Some((r, g, b)) = rule
+                .get_table()
+                .and_then(|table| table.get_color(state as u8))
+            {
+                return [r, g, b];
+            }
+            match state as u8 {
+                DEAD => background,
+                ALIVE => foreground,
+                _ => {
+                    let t = (state - 1) as f32 / (states - 1) as f32;
+                    let lerp = |i: usize| {
+                        (foreground[i] as f32 + (background[i] as f32 - foreground[i] as f32) * t)
+                            as u8
+                    };
+                    [lerp(0), lerp(1), lerp(2)]
+                }
+            }
+        })
+        .collect()
+}
+
+/// The size of the image of a board, odd rows of hexagonal boards sticking
+/// out by half a cell.
+pub fn get_image_size(state: &ConwayGameState, cell_size: usize) -> (usize, usize) {
+    get_region_size(state, cell_size, state.get_width(), state.get_height())
+}
+
+/// The size of the image of a number of columns and rows of a board.
+pub fn get_region_size(
+    state: &ConwayGameState,
+    cell_size: usize,
+    columns: usize,
+    rows: usize,
+) -> (usize, usize) {
+    let shift = if is_hexagonal(state) {
+        cell_size / 2
+    } else {
+        0
+    };
+    (columns * cell_size + shift, rows * cell_size)
+}
+
+/// Fails for images with more than [`MAX_IMAGE_PIXELS`] pixels.
+pub fn check_image_size((width, height): (usize, usize)) -> Result<(), ConwayError> {
+    if width.saturating_mul(height) > MAX_IMAGE_PIXELS {
+        return Err(ConwayError::Validation(format!(
+            "A {}x{} image is too large, images can have at most {} pixels",
+            width, height, MAX_IMAGE_PIXELS
+        )));
+    }
+    Ok(())
+}
+
+/// Draws every cell as a square `cell_size` pixels wide.
+pub fn rasterize(state: &ConwayGameState, cell_size: usize) -> Image {
+    rasterize_region(
+        state,
+        cell_size,
+        0..state.get_width(),
+        0..state.get_height(),
+    )
+}
+
+/// Draws the cells in a range of columns and rows, as `rasterize` would
+/// draw a board of just those cells.
+pub fn rasterize_region(
+    state: &ConwayGameState,
+    cell_size: usize,
+    columns: Range<usize>,
+    rows: Range<usize>,
+) -> Image {
+    let (width, height) = get_region_size(state, cell_size, columns.len(), rows.len());
+    let mut pixels = vec![DEAD; width * height];
+    let cells = state.get_cells();
+    for (i, y) in rows.enumerate() {
+        let shift = get_row_shift(state, y, cell_size);
+        let start = y * state.get_width();
+        let row = &cells[start + columns.start..start + columns.end];
+        let top = i * cell_size * width;
+        // Draw the first pixel row of the cells, then copy it down
+        for (x, &cell) in row.iter().enumerate() {
+            let left = top + shift + x * cell_size;
+            pixels[left..left + cell_size].fill(cell);
+        }
+        for i in 1..cell_size {
+            pixels.copy_within(top..top + width, top + i * width);
+        }
+    }
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+/// How far a row is drawn to the right, half a cell for the odd rows of
+/// hexagonal boards.
+pub fn get_row_shift(state: &ConwayGameState, y: usize, cell_size: usize) -> usize {
+    if is_hexagonal(state) && y % 2 == 1 {
+        cell_size / 2
+    } else {
+        0
+    }
+}
+
+pub fn is_hexagonal(state: &ConwayGameState) -> bool {
+    state.get_rule().get_neighborhood().is_hexagonal()
+}
@@ -0,0 +1,149 @@
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+    rc::Rc,
+};
+
+use sdl2::{keyboard::Keycode, mouse::MouseButton, rect::Rect};
+
+use crate::engine::{
+    resource_manager::FontManager,
+    view::{TextView, View, ViewChain},
+};
+use crate::fonts;
+
+use super::board::BoardView;
+use super::theme::Theme;
+use super::toast::ToastView;
+
+const ROW_HEIGHT: i32 = 40;
+
+/// An overlay listing the recent files, shown while `visible` is set.
+/// Clicking a file loads it, and any other click or key closes the list.
+pub struct RecentView {
+    bound: Rect,
+    board: Rc<RefCell<BoardView>>,
+    visible: Rc<Cell<bool>>,
+    theme: Theme,
+    toasts: Rc<RefCell<ToastView>>,
+    components: ViewChain,
+    // The files the rows show
+    files: Vec<PathBuf>,
+}
+
+impl RecentView {
+    pub fn new(
+        bound: Rect,
+        board: Rc<RefCell<BoardView>>,
+        visible: Rc<Cell<bool>>,
+        theme: Theme,
+        toasts: Rc<RefCell<ToastView>>,
+    ) -> RecentView {
+        let mut view = RecentView {
+            bound,
+            board,
+            visible,
+            theme,
+            toasts,
+            components: ViewChain::new(),
+            files: Vec::new(),
+        };
+        view.update_rows();
+        view
+    }
+
+    fn get_rows_top(&self) -> i32 {
+        self.bound.y() + 70
+    }
+
+    /// Rebuilds the rows from the recent files of the board.
+    fn update_rows(&mut self) {
+        self.files = self.board.borrow().get_recent_files().to_vec();
+        let (x, width) = (self.bound.x() + 20, self.bound.width() - 40);
+        let text = |bound: Rect, text: String, size: u16| {
+            TextView::new(
+                bound,
+                text,
+                fonts::ARK_PIXEL_FONT,
+                size,
+                self.theme.get_text_color(),
+            )
+            .wrap()
+        };
+
+        self.components = ViewChain::new();
+        self.components.add_view(text(
+            Rect::new(x, self.bound.y() + 20, width, 40),
+            "Recent files".to_string(),
+            26,
+        ));
+        if self.files.is_empty() {
+            self.components.add_view(text(
+                Rect::new(x, self.get_rows_top(), width, 24),
+                "No files yet".to_string(),
+                18,
+            ));
+        }
+        for (i, file) in self.files.iter().enumerate() {
+            let y = self.get_rows_top() + i as i32 * ROW_HEIGHT;
+            let name = file
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let directory = file
+                .parent()
+                .map(|directory| directory.display().to_string())
+                .unwrap_or_default();
+            self.components
+                .add_view(text(Rect::new(x, y, width, 22), name, 18));
+            self.components
+                .add_view(text(Rect::new(x, y + 20, width, 18), directory, 12));
+        }
+    }
+}
+
+impl View for RecentView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.visible.get() {
+            return Ok(());
+        }
+        if self.files != self.board.borrow().get_recent_files() {
+            self.update_rows();
+        }
+        canvas.set_draw_color(self.theme.get_background_color());
+        canvas.fill_rect(self.bound)?;
+        canvas.set_draw_color(self.theme.get_text_color());
+        canvas.draw_rect(self.bound)?;
+        self.components
+            .render(canvas, texture_creator, font_manager)
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.bound
+    }
+
+    fn on_key_down(&mut self, _key: Keycode) {
+        self.visible.set(false);
+    }
+
+    fn on_mouse_button_down(&mut self, _button: MouseButton, x: i32, y: i32) {
+        if !self.visible.get() {
+            return;
+        }
+        self.visible.set(false);
+        if !self.bound.contains_point((x, y)) || y < self.get_rows_top() {
+            return;
+        }
+        let row = ((y - self.get_rows_top()) / ROW_HEIGHT) as usize;
+        if let Some(file) = self.files.get(row) {
+            if let Err(e) = self.board.borrow_mut().load_file(file) {
+                self.toasts.borrow_mut().error(e);
+            }
+        }
+    }
+}
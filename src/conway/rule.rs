@@ -0,0 +1,566 @@
+use std::{fmt, path::Path, str::FromStr};
+
+pub use self::neighborhood::{Neighborhood, NeighborhoodShape};
+pub use self::table::RuleTable;
+use self::table::TableNeighborhood;
+use super::error::ConwayError;
+
+pub mod hensel;
+mod neighborhood;
+pub mod table;
+
+/// The largest number of states a Generations rule can have.
+pub const MAX_STATES: usize = 256;
+/// The largest range of a Larger than Life neighborhood, as in Golly.
+pub const MAX_RANGE: usize = 500;
+
+/// Where rule tables are looked up by name, as `rules/<name>.rule`.
+pub const RULES_DIRECTORY: &str = "rules";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Transitions {
+    // Indexed by the number of live cells in the neighborhood
+    Totalistic {
+        birth: Vec<bool>,
+        survival: Vec<bool>,
+    },
+    // Indexed by the mask of live neighbors, see `hensel`
+    Isotropic {
+        birth: Box<[bool; 256]>,
+        survival: Box<[bool; 256]>,
+    },
+    // Looks at the state of each neighbor
+    Table(Box<RuleTable>),
+}
+
+/// A rule where whether a cell is alive in the next generation depends on
+/// the live cells in its neighborhood. Totalistic rules only look at their
+/// number, isotropic non-totalistic rules at their configuration up to
+/// rotations and reflections. Rules with more than two states are Generations
+/// rules, where a cell that does not survive passes through the dying states
+/// `2..states` before it is dead. Rule tables define arbitrary transitions
+/// between any number of states.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    neighborhood: Neighborhood,
+    states: usize,
+    // Whether the cell itself is counted, as in Larger than Life's `M1`
+    include_center: bool,
+    transitions: Transitions,
+}
+
+impl Rule {
+    pub fn new(neighborhood: Neighborhood, include_center: bool, states: usize) -> Rule {
+        assert!((2..=MAX_STATES).contains(&states));
+        let max_count = neighborhood.size() + include_center as usize;
+        Rule {
+            neighborhood,
+            states,
+            include_center,
+            transitions: Transitions::Totalistic {
+                birth: vec![false; max_count + 1],
+                survival: vec![false; max_count + 1],
+            },
+        }
+    }
+
+    /// An isotropic non-totalistic rule on the Moore neighborhood, from
+    /// tables indexed by the mask of live neighbors. Tables that only depend
+    /// on the number of live neighbors make a plain totalistic rule.
+    pub fn new_isotropic(birth: [bool; 256], survival: [bool; 256], states: usize) -> Rule {
+        assert!((2..=MAX_STATES).contains(&states));
+        let totalistic = |table: &[bool; 256]| {
+            (0..256).all(|mask: usize| table[mask] == table[(1 << mask.count_ones()) - 1])
+        };
+        if totalistic(&birth) && totalistic(&survival) {
+            let counts = |table: &[bool; 256]| {
+                (0..=8)
+                    .filter(|&count| table[(1usize << count) - 1])
+                    .collect::<Vec<_>>()
+            };
+            let mut rule = Rule::new(Neighborhood::default(), false, states);
+            // Counts up to 8 always fit the Moore neighborhood
+            rule.set_counts(true, &counts(&birth)).unwrap();
+            rule.set_counts(false, &counts(&survival)).unwrap();
+            return rule;
+        }
+
+        Rule {
+            neighborhood: Neighborhood::default(),
+            states,
+            include_center: false,
+            transitions: Transitions::Isotropic {
+                birth: Box::new(birth),
+                survival: Box::new(survival),
+            },
+        }
+    }
+
+    /// A rule running a Golly rule table.
+    pub fn from_table(table: RuleTable) -> Rule {
+        let shape = match table.get_neighborhood() {
+            TableNeighborhood::VonNeumann => NeighborhoodShape::VonNeumann,
+            TableNeighborhood::Hexagonal => NeighborhoodShape::Hexagonal,
+            TableNeighborhood::Moore | TableNeighborhood::OneDimensional => {
+                NeighborhoodShape::Moore
+            }
+        };
+        Rule {
+            neighborhood: Neighborhood::new(shape, 1),
+            states: table.get_states(),
+            include_center: false,
+            transitions: Transitions::Table(Box::new(table)),
+        }
+    }
+
+    /// Loads a rule table from a Golly `.rule` file.
+    pub fn load_table(path: &Path) -> Result<Rule, ConwayError> {
+        let contents = std::fs::read_to_string(path).map_err(ConwayError::io(path))?;
+        let table = table::parse(&contents).map_err(|e| e.in_file(path))?;
+        Ok(Rule::from_table(table))
+    }
+
+    pub fn get_neighborhood(&self) -> &Neighborhood {
+        &self.neighborhood
+    }
+
+    pub fn get_states(&self) -> usize {
+        self.states
+    }
+
+    pub fn get_include_center(&self) -> bool {
+        self.include_center
+    }
+
+    pub fn get_max_count(&self) -> usize {
+        self.neighborhood.size() + self.include_center as usize
+    }
+
+    pub fn is_isotropic(&self) -> bool {
+        matches!(self.transitions, Transitions::Isotropic { .. })
+    }
+
+    pub fn get_table(&self) -> Option<&RuleTable> {
+        match &self.transitions {
+            Transitions::Table(table) => Some(table),
+            _ => None,
+        }
+    }
+
+    /// The next state of a cell. `neighborhood` is the number of live cells
+    /// in the neighborhood for totalistic rules, and the mask of live
+    /// neighbors (see [`hensel::NEIGHBOR_OFFSETS`]) for isotropic ones.
+    pub fn next_state(&self, state: u8, neighborhood: usize) -> u8 {
+        let (birth, survival) = match &self.transitions {
+            Transitions::Totalistic { birth, survival } => {
+                (birth[neighborhood], survival[neighborhood])
+            }
+            Transitions::Isotropic { birth, survival } => {
+                (birth[neighborhood], survival[neighborhood])
+            }
+            Transitions::Table(_) => unreachable!("rule tables look at each neighbor"),
+        };
+        match state {
+            0 => birth as u8,
+            1 if survival => 1,
+            _ if (state as usize + 1) < self.states => state + 1,
+            _ => 0,
+        }
+    }
+
+    fn set_counts(&mut self, birth: bool, counts: &[usize]) -> Result<(), String> {
+        let max_count = self.get_max_count();
+        let Transitions::Totalistic {
+            birth: birth_table,
+            survival: survival_table,
+        } = &mut self.transitions
+        else {
+            unreachable!("counts are only set on totalistic rules");
+        };
+        let table = if birth { birth_table } else { survival_table };
+        for &count in counts {
+            if count > max_count {
+                return Err(format!(
+                    "Neighbor count {} exceeds the neighborhood size {}",
+                    count, max_count
+                ));
+            }
+            table[count] = true;
+        }
+        Ok(())
+    }
+
+    fn is_larger_than_life(&self) -> bool {
+        if !matches!(self.transitions, Transitions::Totalistic { .. }) {
+            return false;
+        }
+        self.neighborhood.get_range() > 1
+            || self.include_center
+            || self.neighborhood.get_shape() == NeighborhoodShape::Circular
+    }
+
+    /// Separates the halves of a rule written without a slash, as Golly
+    /// accepts, e.g. `B36S23` or `B2n3S23-qV`. None for anything else.
+    fn add_slash(rule: &str) -> Option<String> {
+        let rest = rule.strip_prefix(['B', 'b'])?;
+        let (birth, survival) = rest.split_at(rest.find(['S', 's'])?);
+        // Conditions start with a neighbor count, so names like `Bosco`
+        // are left to the rule tables
+        let is_conditions = |conditions: &str| {
+            conditions.chars().next().is_none_or(|c| c.is_ascii_digit())
+                && conditions
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c.is_ascii_lowercase() || c == '-')
+        };
+        let conditions = survival[1..].strip_suffix(['V', 'v', 'H', 'h']);
+        let conditions = conditions.unwrap_or(&survival[1..]);
+        (is_conditions(birth) && is_conditions(conditions))
+            .then(|| format!("B{}/{}", birth, survival))
+    }
+
+    /// Parses `B3/S23`, `S23/B3` and the `23/3` S/B notation, with an optional
+    /// `V` (von Neumann) or `H` (hexagonal) suffix. Generations rules add the
+    /// number of states, as in `B2/S/C3` or `/2/3`. Counts followed by Hensel
+    /// letters make an isotropic non-totalistic rule, as in `B2n3/S23-q`.
+    fn parse_life_like(rule: &str) -> Result<Rule, String> {
+        let (rule, shape) = match rule.chars().last() {
+            Some('V' | 'v') => (&rule[..rule.len() - 1], NeighborhoodShape::VonNeumann),
+            Some('H' | 'h') => (&rule[..rule.len() - 1], NeighborhoodShape::Hexagonal),
+            _ => (rule, NeighborhoodShape::Moore),
+        };
+
+        let parts: Vec<&str> = rule.split('/').collect();
+        if parts.len() != 2 && parts.len() != 3 {
+            return Err(format!("Invalid rule: {}", rule));
+        }
+
+        let mut birth = None;
+        let mut survival = None;
+        let mut states = None;
+        for (i, part) in parts.iter().enumerate() {
+            match part.chars().next() {
+                Some('B' | 'b') => birth = Some(&part[1..]),
+                Some('S' | 's') => survival = Some(&part[1..]),
+                Some('C' | 'c') => states = Some(&part[1..]),
+                // S/B/C notation
+                _ if i == 0 => survival = Some(part),
+                _ if i == 1 => birth = Some(part),
+                _ => states = Some(part),
+            }
+        }
+        let (birth, survival) = birth
+            .zip(survival)
+            .ok_or(format!("Invalid rule: {}", rule))?;
+        let states = match states {
+            Some(states) => Rule::parse_states(states)?,
+            None => 2,
+        };
+
+        if birth.contains(char::is_alphabetic) || survival.contains(char::is_alphabetic) {
+            if shape != NeighborhoodShape::Moore {
+                return Err(format!(
+                    "Non-totalistic rules need the Moore neighborhood: {}",
+                    rule
+                ));
+            }
+            return Ok(Rule::new_isotropic(
+                hensel::parse_conditions(birth)?,
+                hensel::parse_conditions(survival)?,
+                states,
+            ));
+        }
+
+        let parse_digits = |digits: &str| {
+            digits
+                .chars()
+                .map(|c| {
+                    c.to_digit(10)
+                        .map(|d| d as usize)
+                        .ok_or(format!("Invalid neighbor count: {}", c))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let mut result = Rule::new(Neighborhood::new(shape, 1), false, states);
+        result.set_counts(true, &parse_digits(birth)?)?;
+        result.set_counts(false, &parse_digits(survival)?)?;
+        Ok(result)
+    }
+
+    /// Parses Larger than Life rules such as `R5,C0,M1,S34..58,B34..45,NM`.
+    /// `S` and `B` accept several comma separated counts or ranges.
+    fn parse_larger_than_life(rule: &str) -> Result<Rule, String> {
+        let mut range = 1;
+        let mut include_center = false;
+        let mut shape = NeighborhoodShape::Moore;
+        let mut states = 2;
+        let mut birth = Vec::new();
+        let mut survival = Vec::new();
+        // Whether bare ranges following `S` or `B` are birth counts
+        let mut current_is_birth: Option<bool> = None;
+
+        for token in rule.split(',').map(str::trim) {
+            let invalid = || format!("Invalid rule component: {}", token);
+            let mut chars = token.chars();
+            match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('R') => {
+                    range = chars.as_str().parse().map_err(|_| invalid())?;
+                    if range == 0 {
+                        return Err(invalid());
+                    }
+                    if range > MAX_RANGE {
+                        return Err(format!(
+                            "The range must be at most {}, not {}",
+                            MAX_RANGE, range
+                        ));
+                    }
+                    current_is_birth = None;
+                }
+                Some('C') => {
+                    states = Rule::parse_states(chars.as_str())?;
+                    current_is_birth = None;
+                }
+                Some('M') => {
+                    include_center = match chars.as_str() {
+                        "0" => false,
+                        "1" => true,
+                        _ => return Err(invalid()),
+                    };
+                    current_is_birth = None;
+                }
+                Some('N') => {
+                    shape = match chars.as_str().to_ascii_uppercase().as_str() {
+                        "M" => NeighborhoodShape::Moore,
+                        "N" => NeighborhoodShape::VonNeumann,
+                        "C" => NeighborhoodShape::Circular,
+                        "H" => NeighborhoodShape::Hexagonal,
+                        _ => return Err(invalid()),
+                    };
+                    current_is_birth = None;
+                }
+                Some(c @ ('S' | 'B')) => {
+                    let counts = if c == 'B' { &mut birth } else { &mut survival };
+                    if !chars.as_str().is_empty() {
+                        counts.extend(Rule::parse_count_range(chars.as_str()).ok_or_else(invalid)?);
+                    }
+                    current_is_birth = Some(c == 'B');
+                }
+                Some(_) => {
+                    let counts = match current_is_birth.ok_or_else(invalid)? {
+                        true => &mut birth,
+                        false => &mut survival,
+                    };
+                    counts.extend(Rule::parse_count_range(token).ok_or_else(invalid)?);
+                }
+                None => return Err(invalid()),
+            }
+        }
+
+        let mut result = Rule::new(Neighborhood::new(shape, range), include_center, states);
+        result.set_counts(true, &birth)?;
+        result.set_counts(false, &survival)?;
+        Ok(result)
+    }
+
+    /// Parses the number of states, where 0 and 1 mean a two-state rule.
+    fn parse_states(states: &str) -> Result<usize, String> {
+        match states.parse::<usize>() {
+            Ok(0..=2) => Ok(2),
+            Ok(states) if states <= MAX_STATES => Ok(states),
+            _ => Err(format!("Invalid number of states: {}", states)),
+        }
+    }
+
+    /// Parses `a..b` or `a` into the counts it covers.
+    fn parse_count_range(range: &str) -> Option<std::ops::RangeInclusive<usize>> {
+        match range.split_once("..") {
+            Some((min, max)) => Some(min.parse().ok()?..=max.parse().ok()?),
+            None => {
+                let count = range.parse().ok()?;
+                Some(count..=count)
+            }
+        }
+    }
+
+    /// Formats the counts of a table as `a..b` ranges.
+    fn format_count_ranges(table: &[bool]) -> Vec<String> {
+        let mut ranges = Vec::new();
+        let mut count = 0;
+        while count < table.len() {
+            if table[count] {
+                let start = count;
+                while count + 1 < table.len() && table[count + 1] {
+                    count += 1;
+                }
+                ranges.push(format!("{}..{}", start, count));
+            }
+            count += 1;
+        }
+        ranges
+    }
+}
+
+impl Default for Rule {
+    /// Conway's Game of Life, `B3/S23`.
+    fn default() -> Self {
+        let mut rule = Rule::new(Neighborhood::default(), false, 2);
+        rule.set_counts(true, &[3]).unwrap();
+        rule.set_counts(false, &[2, 3]).unwrap();
+        rule
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.ends_with(".rule") {
+            Rule::load_table(Path::new(s)).map_err(|e| e.to_string())
+        } else if s.starts_with(['R', 'r']) && s.contains(',') {
+            Rule::parse_larger_than_life(s)
+        } else if let Some(rule) = Rule::add_slash(s) {
+            Rule::parse_life_like(&rule)
+        } else if !s.is_empty() && !s.contains('/') {
+            // Other rules are named after their rule table, as in Golly
+            let path = Path::new(RULES_DIRECTORY).join(format!("{}.rule", s));
+            if !path.exists() {
+                return Err(format!(
+                    "Unknown rule {}, there is no rule table {}",
+                    s,
+                    path.display()
+                ));
+            }
+            Rule::load_table(&path).map_err(|e| e.to_string())
+        } else {
+            Rule::parse_life_like(s)
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (birth, survival) = match &self.transitions {
+            Transitions::Totalistic { birth, survival } if self.is_larger_than_life() => {
+                return write!(
+                    f,
+                    "R{},C{},M{},S{},B{},N{}",
+                    self.neighborhood.get_range(),
+                    if self.states > 2 { self.states } else { 0 },
+                    self.include_center as u8,
+                    Rule::format_count_ranges(survival).join(","),
+                    Rule::format_count_ranges(birth).join(","),
+                    self.neighborhood.get_shape(),
+                );
+            }
+            Transitions::Totalistic { birth, survival } => {
+                let digits = |table: &[bool]| {
+                    (0..table.len())
+                        .filter(|&count| table[count])
+                        .map(|count| count.to_string())
+                        .collect::<String>()
+                };
+                (digits(birth), digits(survival))
+            }
+            Transitions::Isotropic { birth, survival } => (
+                hensel::format_conditions(birth),
+                hensel::format_conditions(survival),
+            ),
+            Transitions::Table(table) => return write!(f, "{}", table.get_name()),
+        };
+
+        let suffix = match self.neighborhood.get_shape() {
+            NeighborhoodShape::VonNeumann => "V",
+            NeighborhoodShape::Hexagonal => "H",
+            _ => "",
+        };
+        write!(f, "B{}/S{}", birth, survival)?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        write!(f, "{}", suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(rule: &str) -> String {
+        rule.parse::<Rule>().unwrap().to_string()
+    }
+
+    #[test]
+    fn round_trips_rules() {
+        for rule in [
+            "B3/S23",
+            "B36/S23",
+            "B2/S/C3",
+            "B2/S34H",
+            "B1/S1V",
+            "B2n3/S23-q",
+            "R2,C0,M1,S2..3,B3..4,NM",
+        ] {
+            assert_eq!(format(rule), rule);
+        }
+    }
+
+    #[test]
+    fn reads_other_notations() {
+        assert_eq!(format("S23/B3"), "B3/S23");
+        assert_eq!(format("23/3"), "B3/S23");
+        assert_eq!(format("b3/s23"), "B3/S23");
+    }
+
+    #[test]
+    fn reads_rules_without_a_slash() {
+        assert_eq!(format("B3S23"), "B3/S23");
+        assert_eq!(format("B36S23"), "B36/S23");
+        assert_eq!(format("b2n3s23-q"), "B2n3/S23-q");
+        assert_eq!(format("B2S34H"), "B2/S34H");
+        assert_eq!(format("B3S"), "B3/S");
+    }
+
+    #[test]
+    fn limits_the_range() {
+        assert!("R500,C0,M0,S1,B1,NM".parse::<Rule>().is_ok());
+        assert_eq!(
+            "R100000,C0,M0,S1,B1,NM".parse::<Rule>(),
+            Err("The range must be at most 500, not 100000".to_string())
+        );
+    }
+
+    #[test]
+    fn names_rule_tables() {
+        let rule: Rule = "WireWorld".parse().unwrap();
+        assert_eq!(rule.get_states(), 4);
+        assert_eq!(rule.to_string(), "WireWorld");
+        assert!("NoSuchRule".parse::<Rule>().is_err());
+        // Names looking like rules without a slash are still table names
+        for name in ["Bosco", "Bas2"] {
+            assert_eq!(
+                name.parse::<Rule>(),
+                Err(format!(
+                    "Unknown rule {}, there is no rule table {}",
+                    name,
+                    Path::new(RULES_DIRECTORY)
+                        .join(format!("{}.rule", name))
+                        .display()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for rule in [
+            "B9/S23",
+            "B3/S23/C999",
+            "B3/X",
+            "B3S2x",
+            "R0,C0,M0,S1,B1,NM",
+        ] {
+            assert!(rule.parse::<Rule>().is_err(), "{}", rule);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Hensel notation for isotropic non-totalistic rules, e.g. `B2n3/S23-q`.
+//!
+//! Each neighbor count is followed by letters naming configurations of that
+//! many live neighbors, up to rotations and reflections. `3ai` means the
+//! configurations `a` and `i` of three neighbors, `3-ai` all of them except
+//! those, and a bare `3` all of them.
+//!
+//! A configuration is a mask of the live neighbors, with the bits ordered as
+//! in [`NEIGHBOR_OFFSETS`].
+
+/// Offsets of the neighbors for the bits of a neighborhood mask, clockwise
+/// from the north-west corner. Even bits are corners and odd bits are edges,
+/// so rotating the mask by two bits rotates the neighborhood by 90 degrees.
+pub const NEIGHBOR_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+];
+
+/// A representative mask for each letter, for up to four live neighbors.
+/// Configurations of more neighbors are named after their complement.
+const LETTERS: [&[(char, u8)]; 5] = [
+    &[],
+    &[('c', 0x01), ('e', 0x02)],
+    &[
+        ('c', 0x05),
+        ('e', 0x0a),
+        ('k', 0x21),
+        ('a', 0x03),
+        ('i', 0x22),
+        ('n', 0x11),
+    ],
+    &[
+        ('c', 0x15),
+        ('e', 0x2a),
+        ('k', 0x29),
+        ('a', 0x0e),
+        ('i', 0x07),
+        ('n', 0x0b),
+        ('y', 0x25),
+        ('q', 0x13),
+        ('j', 0x0d),
+        ('r', 0x23),
+    ],
+    &[
+        ('c', 0x55),
+        ('e', 0xaa),
+        ('k', 0x2d),
+        ('a', 0x0f),
+        ('i', 0x17),
+        ('n', 0x2e),
+        ('y', 0x35),
+        ('q', 0x39),
+        ('j', 0x36),
+        ('r', 0x2b),
+        ('t', 0x27),
+        ('w', 0x1b),
+        ('z', 0x33),
+    ],
+];
+
+/// The letters for a neighbor count, in canonical order, with their
+/// representative masks.
+fn letters(count: usize) -> Vec<(char, u8)> {
+    if count <= 4 {
+        LETTERS[count].to_vec()
+    } else {
+        LETTERS[8 - count]
+            .iter()
+            .map(|&(letter, mask)| (letter, !mask))
+            .collect()
+    }
+}
+
+/// Whether two masks are the same configuration up to rotations and
+/// reflections.
+fn is_equivalent(a: u8, b: u8) -> bool {
+    // Reflects across the diagonal through the north-west corner
+    let reflect = |mask: u8| (0..8).fold(0u8, |r, i| r | ((mask >> i) & 1) << ((8 - i) % 8));
+    (0..4).any(|i| {
+        let rotated = b.rotate_left(2 * i);
+        a == rotated || a == reflect(rotated)
+    })
+}
+
+/// The letter naming a configuration, None for 0 and 8 neighbors.
+pub fn letter_of(mask: u8) -> Option<char> {
+    letters(mask.count_ones() as usize)
+        .into_iter()
+        .find(|&(_, representative)| is_equivalent(mask, representative))
+        .map(|(letter, _)| letter)
+}
+
+/// Parses the conditions of one half of a rule, e.g. the `23-q` of
+/// `B3/S23-q`, into a table indexed by neighborhood mask.
+pub fn parse_conditions(conditions: &str) -> Result<[bool; 256], String> {
+    let mut table = [false; 256];
+    let mut chars = conditions.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = c
+            .to_digit(10)
+            .filter(|&count| count <= 8)
+            .ok_or(format!("Invalid neighbor count: {}", c))? as usize;
+
+        let negate = chars.next_if_eq(&'-').is_some();
+        let mut selected = Vec::new();
+        while let Some(letter) = chars.next_if(char::is_ascii_lowercase) {
+            if !letters(count).iter().any(|&(l, _)| l == letter) {
+                return Err(format!("Invalid letter {} for {} neighbors", letter, count));
+            }
+            selected.push(letter);
+        }
+        if negate && selected.is_empty() {
+            return Err(format!("Missing letters after {}-", count));
+        }
+
+        for mask in 0..=255u8 {
+            if mask.count_ones() as usize != count {
+                continue;
+            }
+            let listed = letter_of(mask).is_some_and(|letter| selected.contains(&letter));
+            if selected.is_empty() || listed != negate {
+                table[mask as usize] = true;
+            }
+        }
+    }
+    Ok(table)
+}
+
+/// Formats a table as the conditions of one half of a rule, choosing the
+/// shorter of the listed and the negated letters for each count.
+pub fn format_conditions(table: &[bool; 256]) -> String {
+    let mut conditions = String::new();
+    for count in 0..=8 {
+        let (included, excluded): (Vec<char>, Vec<char>) = letters(count)
+            .into_iter()
+            .map(|(letter, _)| letter)
+            .partition(|&letter| {
+                (0..=255u8).any(|mask| {
+                    mask.count_ones() as usize == count
+                        && letter_of(mask) == Some(letter)
+                        && table[mask as usize]
+                })
+            });
+
+        // 0 and 8 neighbors have no letters
+        let any = if count == 0 || count == 8 {
+            table[if count == 0 { 0 } else { 255 }]
+        } else {
+            !included.is_empty()
+        };
+        if !any {
+            continue;
+        }
+
+        conditions += &count.to_string();
+        if excluded.is_empty() {
+            continue;
+        }
+        if included.len() <= excluded.len() {
+            conditions.extend(included);
+        } else {
+            conditions.push('-');
+            conditions.extend(excluded);
+        }
+    }
+    conditions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conway::rule::Rule;
+
+    fn binomial(n: usize, k: usize) -> usize {
+        (0..k).fold(1, |result, i| result * (n - i) / (i + 1))
+    }
+
+    #[test]
+    fn letters_name_each_configuration_once() {
+        for count in 0..=8 {
+            let masks: Vec<u8> = (0..=255u8)
+                .filter(|mask| mask.count_ones() as usize == count)
+                .collect();
+            assert_eq!(masks.len(), binomial(8, count));
+            for &mask in &masks {
+                let named = letters(count)
+                    .into_iter()
+                    .filter(|&(_, representative)| is_equivalent(mask, representative))
+                    .count();
+                let expected = if count == 0 || count == 8 { 0 } else { 1 };
+                assert_eq!(named, expected, "{} neighbors, mask {:#04x}", count, mask);
+            }
+            // Every letter names some configuration of its count
+            for (letter, representative) in letters(count) {
+                assert_eq!(
+                    representative.count_ones() as usize,
+                    count,
+                    "{}{}",
+                    count,
+                    letter
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_conditions() {
+        for conditions in ["23-q", "2n3", "2-i34q", "3", "1e2a3-ea4w", "012345678"] {
+            let table = parse_conditions(conditions).unwrap();
+            assert_eq!(format_conditions(&table), conditions);
+        }
+    }
+
+    #[test]
+    fn round_trips_rules() {
+        for rule in ["B2n3/S23-q", "B3/S2-i34q", "B2e3-ka/S12i"] {
+            assert_eq!(rule.parse::<Rule>().unwrap().to_string(), rule);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_conditions() {
+        assert!(parse_conditions("3x").is_err());
+        assert!(parse_conditions("1k").is_err());
+        assert!(parse_conditions("3-").is_err());
+        assert!(parse_conditions("9").is_err());
+    }
+
+    #[test]
+    fn every_letter_is_plain_life() {
+        for (lettered, plain) in [("3cekainyqjr", "3"), ("2cekain3cekainyqjr", "23")] {
+            let lettered = parse_conditions(lettered).unwrap();
+            for mask in 0..=255u8 {
+                let count = mask.count_ones();
+                assert_eq!(lettered[mask as usize], plain.contains(&count.to_string()));
+            }
+            assert_eq!(lettered, parse_conditions(plain).unwrap());
+        }
+        // Rules with every letter are totalistic
+        let lettered: Rule = "B3cekainyqjr/S2cekain3cekainyqjr".parse().unwrap();
+        assert_eq!(lettered, "B3/S23".parse().unwrap());
+        assert!(!lettered.is_isotropic());
+        assert_eq!(lettered.to_string(), "B3/S23");
+    }
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeighborhoodShape {
+    Moore,
+    VonNeumann,
+    Circular,
+    // Odd rows are shifted right by half a cell
+    Hexagonal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Neighborhood {
+    shape: NeighborhoodShape,
+    range: usize,
+}
+
+impl Neighborhood {
+    pub fn new(shape: NeighborhoodShape, range: usize) -> Neighborhood {
+        assert!(range > 0);
+        Neighborhood { shape, range }
+    }
+
+    pub fn get_shape(&self) -> NeighborhoodShape {
+        self.shape
+    }
+
+    pub fn get_range(&self) -> usize {
+        self.range
+    }
+
+    pub fn is_hexagonal(&self) -> bool {
+        self.shape == NeighborhoodShape::Hexagonal
+    }
+
+    /// Relative positions of the neighbors of a cell, excluding the cell
+    /// itself. Only the hexagonal shape depends on the row parity.
+    pub fn offsets(&self, odd_row: bool) -> Vec<(isize, isize)> {
+        let r = self.range as isize;
+        let mut offsets = Vec::new();
+        for dy in -r..=r {
+            for dx in -r - 1..=r + 1 {
+                if (dx, dy) != (0, 0) && self.contains(dx, dy, odd_row) {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        offsets
+    }
+
+    /// Size of the neighborhood, excluding the cell itself.
+    pub fn size(&self) -> usize {
+        self.offsets(false).len()
+    }
+
+    fn contains(&self, dx: isize, dy: isize, odd_row: bool) -> bool {
+        let r = self.range as isize;
+        match self.shape {
+            NeighborhoodShape::Moore => dx.abs() <= r && dy.abs() <= r,
+            NeighborhoodShape::VonNeumann => dx.abs() + dy.abs() <= r,
+            // Same as Golly: the distance is rounded to the nearest integer
+            NeighborhoodShape::Circular => dx * dx + dy * dy <= r * r + r,
+            NeighborhoodShape::Hexagonal => {
+                // Convert the odd-r offset coordinates to axial coordinates
+                let row = if odd_row { 1 } else { 0 };
+                let q = dx - (row + dy - ((row + dy) & 1)) / 2;
+                let s = -q - dy;
+                q.abs().max(dy.abs()).max(s.abs()) <= r
+            }
+        }
+    }
+}
+
+impl Default for Neighborhood {
+    fn default() -> Self {
+        Neighborhood::new(NeighborhoodShape::Moore, 1)
+    }
+}
+
+impl fmt::Display for NeighborhoodShape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NeighborhoodShape::Moore => write!(f, "M"),
+            NeighborhoodShape::VonNeumann => write!(f, "N"),
+            NeighborhoodShape::Circular => write!(f, "C"),
+            NeighborhoodShape::Hexagonal => write!(f, "H"),
+        }
+    }
+}
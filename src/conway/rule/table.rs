@@ -0,0 +1,605 @@
+//! Golly rule tables, the `@TABLE` and `@COLORS` sections of a `.rule` file.
+//!
+//! A table lists transitions as the state of a cell, the states of its
+//! neighbors and its next state, e.g. `0,1,0,0,0,0,0,0,0,2` for the Moore
+//! neighborhood. Variables like `var a={0,1,2}` stand for any of their
+//! states, and a variable used more than once takes the same state at each
+//! use. Symmetries add the rotated and reflected copies of each transition.
+//! The first matching transition wins, and cells matching none keep their
+//! state.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::conway::error::{column_of, ConwayError};
+
+/// The states accepted at each input of a transition, and its next state.
+type Transition = (Vec<Vec<u8>>, u8);
+
+/// A line of a section, with its line and column.
+type Line<'a> = (usize, usize, &'a str);
+
+/// A color as red, green and blue.
+pub type Rgb = (u8, u8, u8);
+
+/// The neighborhoods of Golly rule tables, with the neighbors in the order
+/// they are listed in transitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableNeighborhood {
+    // N, E, S, W
+    VonNeumann,
+    // N, NE, E, SE, S, SW, W, NW
+    Moore,
+    // N, E, SE, S, W, NW
+    Hexagonal,
+    // W, E
+    OneDimensional,
+}
+
+impl TableNeighborhood {
+    fn parse(name: &str) -> Result<TableNeighborhood, String> {
+        match name {
+            "vonNeumann" => Ok(TableNeighborhood::VonNeumann),
+            "Moore" => Ok(TableNeighborhood::Moore),
+            "hexagonal" => Ok(TableNeighborhood::Hexagonal),
+            "oneDimensional" => Ok(TableNeighborhood::OneDimensional),
+            _ => Err(format!("Unsupported neighborhood: {}", name)),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            TableNeighborhood::VonNeumann => 4,
+            TableNeighborhood::Moore => 8,
+            TableNeighborhood::Hexagonal => 6,
+            TableNeighborhood::OneDimensional => 2,
+        }
+    }
+
+    /// Offsets of the neighbors in transition order. Golly's hexagonal
+    /// directions are mapped onto rows where odd rows are shifted right by
+    /// half a cell, so N is the upper right neighbor and S the lower left.
+    pub fn offsets(&self, odd_row: bool) -> Vec<(isize, isize)> {
+        let shift = odd_row as isize;
+        match self {
+            TableNeighborhood::VonNeumann => vec![(0, -1), (1, 0), (0, 1), (-1, 0)],
+            TableNeighborhood::Moore => vec![
+                (0, -1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+                (-1, 0),
+                (-1, -1),
+            ],
+            TableNeighborhood::Hexagonal => vec![
+                (shift, -1),
+                (1, 0),
+                (shift, 1),
+                (shift - 1, 1),
+                (-1, 0),
+                (shift - 1, -1),
+            ],
+            TableNeighborhood::OneDimensional => vec![(-1, 0), (1, 0)],
+        }
+    }
+
+    /// The permutations of the neighbors for a symmetry, or None for
+    /// `permute`, which allows any of them.
+    fn symmetries(&self, name: &str) -> Result<Option<Vec<Vec<usize>>>, String> {
+        let size = self.size();
+        if name == "permute" {
+            return Ok(None);
+        }
+
+        let (rotations, reflect) = match name {
+            "none" => (1, false),
+            "reflect" | "reflect_horizontal" => (1, true),
+            _ => {
+                let rotate = name
+                    .strip_prefix("rotate")
+                    .ok_or(format!("Unsupported symmetries: {}", name))?;
+                let (rotations, reflect) = match rotate.strip_suffix("reflect") {
+                    Some(rotations) => (rotations, true),
+                    None => (rotate, false),
+                };
+                let rotations: usize = rotations
+                    .parse()
+                    .map_err(|_| format!("Unsupported symmetries: {}", name))?;
+                (rotations, reflect)
+            }
+        };
+        if rotations == 0 || !size.is_multiple_of(rotations) {
+            return Err(format!(
+                "Symmetries {} do not fit the {:?} neighborhood",
+                name, self
+            ));
+        }
+
+        // The neighbors form a ring, so rotating shifts it and reflecting
+        // left to right mirrors it around N. The one-dimensional neighborhood
+        // has no N and mirrors W and E.
+        let rotate: Vec<usize> = (0..size).map(|i| (i + size / rotations) % size).collect();
+        let mirror: Vec<usize> = match self {
+            TableNeighborhood::OneDimensional => vec![1, 0],
+            _ => (0..size).map(|i| (size - i) % size).collect(),
+        };
+        let mut generators = vec![rotate];
+        if reflect {
+            generators.push(mirror);
+        }
+
+        // Close the generators under composition
+        let mut group = vec![(0..size).collect::<Vec<_>>()];
+        let mut i = 0;
+        while i < group.len() {
+            for generator in &generators {
+                let composed: Vec<usize> = group[i].iter().map(|&j| generator[j]).collect();
+                if !group.contains(&composed) {
+                    group.push(composed);
+                }
+            }
+            i += 1;
+        }
+        Ok(Some(group))
+    }
+}
+
+/// A compiled rule table. For each input of a transition and each state,
+/// a bitset holds the transitions accepting that state there, so finding
+/// the first matching transition takes an AND over the inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleTable {
+    name: String,
+    states: usize,
+    neighborhood: TableNeighborhood,
+    // Indexed by (input * states + state) * words + word
+    matches: Vec<u64>,
+    words: usize,
+    outputs: Vec<u8>,
+    colors: Vec<Option<Rgb>>,
+}
+
+impl RuleTable {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_states(&self) -> usize {
+        self.states
+    }
+
+    pub fn get_neighborhood(&self) -> TableNeighborhood {
+        self.neighborhood
+    }
+
+    /// The color of a state from the `@COLORS` section, if it has one.
+    pub fn get_color(&self, state: u8) -> Option<Rgb> {
+        self.colors.get(state as usize).copied().flatten()
+    }
+
+    /// The next state of a cell from the states of its neighbors, in the
+    /// order of [`TableNeighborhood::offsets`].
+    pub fn next_state(&self, state: u8, neighbors: &[u8]) -> u8 {
+        let inputs =
+            std::iter::once(state).chain(neighbors[..self.neighborhood.size()].iter().copied());
+        for word in 0..self.words {
+            let mut matches = u64::MAX;
+            for (input, state) in inputs.clone().enumerate() {
+                matches &= self.matches[(input * self.states + state as usize) * self.words + word];
+                if matches == 0 {
+                    break;
+                }
+            }
+            if matches != 0 {
+                return self.outputs[word * 64 + matches.trailing_zeros() as usize];
+            }
+        }
+        state
+    }
+}
+
+/// Parses the contents of a `.rule` file. Sections other than `@RULE`,
+/// `@TABLE` and `@COLORS` are ignored.
+pub fn parse(contents: &str) -> Result<RuleTable, ConwayError> {
+    let mut name = None;
+    let mut section = "";
+    let mut table_lines = Vec::new();
+    let mut color_lines = Vec::new();
+    for (number, original) in contents.lines().enumerate() {
+        if let Some(header) = original.trim().strip_prefix('@') {
+            let (header, argument) = header
+                .split_once(char::is_whitespace)
+                .unwrap_or((header, ""));
+            section = header;
+            if section == "RULE" {
+                name = Some(argument.trim().to_string());
+            }
+            continue;
+        }
+
+        let line = original.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let line = (number + 1, column_of(original, line), line);
+        match section {
+            "TABLE" => table_lines.push(line),
+            "COLORS" => color_lines.push(line),
+            _ => {}
+        }
+    }
+
+    let name = name
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| ConwayError::parse_at_end(contents, "Missing @RULE name"))?;
+    if table_lines.is_empty() {
+        return Err(ConwayError::parse_at_end(
+            contents,
+            format!("Rule {} has no @TABLE section", name),
+        ));
+    }
+
+    let mut table = parse_table(name, &table_lines)?;
+    table.colors = parse_colors(table.states, &color_lines)?;
+    Ok(table)
+}
+
+fn parse_table(name: String, lines: &[Line]) -> Result<RuleTable, ConwayError> {
+    let mut states = None;
+    let mut neighborhood = TableNeighborhood::Moore;
+    let mut symmetries = "none";
+    let mut variables: HashMap<&str, Vec<u8>> = HashMap::new();
+    let mut transitions: Vec<Transition> = Vec::new();
+    // The inputs of the transitions so far, as later duplicates never match
+    let mut listed: HashSet<Vec<Vec<u8>>> = HashSet::new();
+
+    for &(number, column, line) in lines {
+        let error = |message: String| ConwayError::parse(number, column, message);
+
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" => {
+                    states = Some(
+                        value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|states| (2..=256).contains(states))
+                            .ok_or(error(format!("Invalid number of states: {}", value)))?,
+                    )
+                }
+                "neighborhood" => neighborhood = TableNeighborhood::parse(value).map_err(error)?,
+                "symmetries" => symmetries = value,
+                key => return Err(error(format!("Unknown setting: {}", key))),
+            }
+            continue;
+        }
+
+        let states = states.ok_or(error("n_states must come first".to_string()))?;
+
+        if let Some(variable) = line.strip_prefix("var ") {
+            let (variable, values) = variable
+                .split_once('=')
+                .ok_or(error("Invalid variable".to_string()))?;
+            let values = values
+                .trim()
+                .strip_prefix('{')
+                .and_then(|values| values.strip_suffix('}'))
+                .ok_or(error("Variable values must be in braces".to_string()))?;
+            let mut set = Vec::new();
+            for value in values.split(',') {
+                set.extend(parse_entry(value, states, &variables).map_err(error)?.1);
+            }
+            set.sort_unstable();
+            set.dedup();
+            variables.insert(variable.trim(), set);
+            continue;
+        }
+
+        // A transition, separated by commas or spaces, or one digit per entry
+        let entries: Vec<&str> = if line.contains(',') {
+            line.split(',').collect()
+        } else if line.contains(char::is_whitespace) {
+            line.split_whitespace().collect()
+        } else {
+            line.char_indices()
+                .map(|(i, c)| &line[i..i + c.len_utf8()])
+                .collect()
+        };
+        if entries.len() != neighborhood.size() + 2 {
+            return Err(error(format!(
+                "Expected {} entries but found {}",
+                neighborhood.size() + 2,
+                entries.len()
+            )));
+        }
+        let entries = entries
+            .into_iter()
+            .map(|entry| parse_entry(entry, states, &variables).map_err(error))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let variants = bind_variables(&entries).map_err(error)?;
+        let permutations = neighborhood.symmetries(symmetries).map_err(error)?;
+        for (inputs, output) in variants {
+            let symmetric = match &permutations {
+                Some(permutations) => permutations
+                    .iter()
+                    .map(|permutation| {
+                        let mut permuted = vec![inputs[0].clone()];
+                        permuted.extend(permutation.iter().map(|&i| inputs[i + 1].clone()));
+                        permuted
+                    })
+                    .collect(),
+                None => arrangements(&inputs),
+            };
+            for inputs in symmetric {
+                if listed.insert(inputs.clone()) {
+                    transitions.push((inputs, output));
+                }
+            }
+        }
+    }
+
+    let states = states.ok_or_else(|| {
+        let (number, column, _) = lines[0];
+        ConwayError::parse(number, column, "Missing n_states")
+    })?;
+    let words = transitions.len().div_ceil(64);
+    let inputs = neighborhood.size() + 1;
+    let mut matches = vec![0u64; inputs * states * words];
+    for (i, (transition, _)) in transitions.iter().enumerate() {
+        for (input, accepted) in transition.iter().enumerate() {
+            for &state in accepted {
+                matches[(input * states + state as usize) * words + i / 64] |= 1 << (i % 64);
+            }
+        }
+    }
+
+    Ok(RuleTable {
+        name,
+        states,
+        neighborhood,
+        matches,
+        words,
+        outputs: transitions.iter().map(|&(_, output)| output).collect(),
+        colors: Vec::new(),
+    })
+}
+
+/// Parses a state or a variable of a transition.
+fn parse_entry<'a>(
+    entry: &'a str,
+    states: usize,
+    variables: &HashMap<&str, Vec<u8>>,
+) -> Result<(Option<&'a str>, Vec<u8>), String> {
+    let entry = entry.trim();
+    match entry.parse::<usize>() {
+        Ok(state) if state < states => Ok((None, vec![state as u8])),
+        Ok(state) => Err(format!("Invalid state: {}", state)),
+        Err(_) => variables
+            .get(entry)
+            .map(|values| (Some(entry), values.clone()))
+            .ok_or(format!("Unknown variable: {}", entry)),
+    }
+}
+
+/// Expands the variables that are used more than once, or as the output,
+/// into one transition for each of their states. The remaining variables
+/// accept any of their states.
+fn bind_variables(entries: &[(Option<&str>, Vec<u8>)]) -> Result<Vec<Transition>, String> {
+    let (inputs, output) = entries.split_at(entries.len() - 1);
+    let output = &output[0];
+
+    let mut bound: Vec<&str> = Vec::new();
+    for (i, (variable, _)) in inputs.iter().enumerate() {
+        if let Some(variable) = variable {
+            if inputs[i + 1..]
+                .iter()
+                .any(|(other, _)| other == &Some(*variable))
+                && !bound.contains(variable)
+            {
+                bound.push(variable);
+            }
+        }
+    }
+    if let Some(variable) = output.0 {
+        if !inputs.iter().any(|(other, _)| *other == Some(variable)) {
+            return Err(format!("Output variable {} is not an input", variable));
+        }
+        if !bound.contains(&variable) {
+            bound.push(variable);
+        }
+    }
+
+    let values = |variable: &str| {
+        &inputs
+            .iter()
+            .find(|(other, _)| *other == Some(variable))
+            .unwrap()
+            .1
+    };
+    let mut variants = Vec::new();
+    let mut choice = vec![0; bound.len()];
+    loop {
+        let state_of = |variable: &str| {
+            let i = bound.iter().position(|&other| other == variable)?;
+            Some(values(variable)[choice[i]])
+        };
+        let bind = |(variable, states): &(Option<&str>, Vec<u8>)| match variable.and_then(state_of)
+        {
+            Some(state) => vec![state],
+            None => states.clone(),
+        };
+        variants.push((inputs.iter().map(bind).collect(), bind(output)[0]));
+
+        // Advance to the next combination of bound states
+        let mut i = 0;
+        while i < bound.len() {
+            choice[i] += 1;
+            if choice[i] < values(bound[i]).len() {
+                break;
+            }
+            choice[i] = 0;
+            i += 1;
+        }
+        if i == bound.len() {
+            return Ok(variants);
+        }
+    }
+}
+
+/// The distinct orders of the neighbors of a transition, for `permute`.
+fn arrangements(inputs: &[Vec<u8>]) -> Vec<Vec<Vec<u8>>> {
+    let mut neighbors = inputs[1..].to_vec();
+    neighbors.sort();
+    let mut arrangements = Vec::new();
+    loop {
+        let mut arrangement = vec![inputs[0].clone()];
+        arrangement.extend(neighbors.iter().cloned());
+        arrangements.push(arrangement);
+
+        // Step to the next permutation in lexicographic order
+        let Some(i) = (1..neighbors.len())
+            .rev()
+            .find(|&i| neighbors[i - 1] < neighbors[i])
+        else {
+            return arrangements;
+        };
+        let j = (i..neighbors.len())
+            .rev()
+            .find(|&j| neighbors[i - 1] < neighbors[j])
+            .unwrap();
+        neighbors.swap(i - 1, j);
+        neighbors[i..].reverse();
+    }
+}
+
+/// Parses `state r g b` lines, or `r1 g1 b1 r2 g2 b2` for a gradient over
+/// the states after 0.
+fn parse_colors(states: usize, lines: &[Line]) -> Result<Vec<Option<Rgb>>, ConwayError> {
+    let mut colors = vec![None; states];
+    for &(number, column, line) in lines {
+        let error = || ConwayError::parse(number, column, format!("Invalid color: {}", line));
+        let values = line
+            .split_whitespace()
+            .map(|value| value.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| error())?;
+        let component = |value: usize| u8::try_from(value).map_err(|_| error());
+        match values[..] {
+            [state, r, g, b] if state < states => {
+                colors[state] = Some((component(r)?, component(g)?, component(b)?));
+            }
+            [r1, g1, b1, r2, g2, b2] => {
+                let (first, last) = ([r1, g1, b1], [r2, g2, b2]);
+                for (state, color) in colors.iter_mut().enumerate().skip(1) {
+                    let t = (state - 1) as f32 / (states - 2).max(1) as f32;
+                    let lerp = |i: usize| {
+                        component(
+                            (first[i] as f32 + (last[i] as f32 - first[i] as f32) * t) as usize,
+                        )
+                    };
+                    *color = Some((lerp(0)?, lerp(1)?, lerp(2)?));
+                }
+            }
+            _ => return Err(error()),
+        }
+    }
+    Ok(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIREWORLD: &str = "\
+@RULE WireWorld
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,1,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+var n={0,2,3}
+var o={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,i,j,k,l,m,n,o,1
+3,1,1,i,j,k,l,m,n,1
+";
+
+    #[test]
+    fn parses_wireworld() {
+        let table = parse(WIREWORLD).unwrap();
+        assert_eq!(table.get_name(), "WireWorld");
+        assert_eq!(table.get_states(), 4);
+        assert_eq!(table.get_neighborhood(), TableNeighborhood::Moore);
+
+        assert_eq!(table.next_state(1, &[0; 8]), 2);
+        assert_eq!(table.next_state(2, &[3; 8]), 3);
+        // Wire becomes a head next to one or two heads, in any position
+        assert_eq!(table.next_state(3, &[0, 0, 0, 0, 1, 0, 0, 0]), 1);
+        assert_eq!(table.next_state(3, &[0, 1, 0, 0, 0, 0, 0, 1]), 1);
+        assert_eq!(table.next_state(3, &[1, 1, 1, 0, 0, 0, 0, 0]), 3);
+        assert_eq!(table.next_state(0, &[1; 8]), 0);
+        // Variables used once accept any mix of their states
+        assert_eq!(table.next_state(1, &[0, 3, 0, 0, 0, 0, 0, 0]), 2);
+        assert_eq!(table.next_state(2, &[3, 1, 0, 2, 0, 0, 3, 0]), 3);
+        assert_eq!(table.next_state(3, &[1, 3, 3, 2, 0, 0, 0, 0]), 1);
+        assert_eq!(table.next_state(3, &[2, 1, 3, 0, 1, 0, 3, 2]), 1);
+        assert_eq!(table.next_state(3, &[1, 3, 1, 2, 1, 0, 0, 0]), 3);
+    }
+
+    #[test]
+    fn binds_variables_used_more_than_once() {
+        let table = parse(
+            "@RULE Bound\n@TABLE\nn_states:3\nneighborhood:vonNeumann\n\
+             symmetries:none\nvar a={1,2}\n0,a,0,a,0,1\n",
+        )
+        .unwrap();
+        assert_eq!(table.next_state(0, &[2, 0, 2, 0]), 1);
+        assert_eq!(table.next_state(0, &[1, 0, 2, 0]), 0);
+    }
+
+    #[test]
+    fn first_matching_transition_wins() {
+        let table = parse(
+            "@RULE First\n@TABLE\nn_states:3\nneighborhood:vonNeumann\n\
+             symmetries:none\n0,0,0,0,1,1\n0,0,0,0,1,2\n",
+        )
+        .unwrap();
+        assert_eq!(table.next_state(0, &[0, 0, 0, 1]), 1);
+    }
+
+    #[test]
+    fn reads_compact_transitions() {
+        let table = parse(
+            "@RULE Compact\n@TABLE\nn_states:2\nneighborhood:vonNeumann\n\
+             symmetries:none\n010001\n",
+        )
+        .unwrap();
+        assert_eq!(table.next_state(0, &[1, 0, 0, 0]), 1);
+        assert_eq!(table.next_state(0, &[0, 1, 0, 0]), 0);
+    }
+
+    #[test]
+    fn rejects_non_ascii_compact_transitions() {
+        let result = parse(
+            "@RULE Compact\n@TABLE\nn_states:2\nneighborhood:vonNeumann\n\
+             symmetries:none\n01é001\n",
+        );
+        assert!(matches!(result, Err(ConwayError::Parse { line: 6, .. })));
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use sdl2::{keyboard::Keycode, rect::Rect};
+
+use crate::engine::{
+    resource_manager::FontManager,
+    view::{text_input::SubmitCallback, ButtonView, TextInputView, TextView, View, ViewChain},
+};
+use crate::fonts;
+
+use super::board::BoardView;
+use super::theme::Theme;
+use super::toast::ToastView;
+
+/// Generations being run as fast as possible towards a target.
+struct GenerationRun {
+    target: u64,
+    generations: u64,
+    elapsed: Duration,
+}
+
+/// Controls for advancing the board by hand: a Step button that advances
+/// one generation while paused, and inputs that run a
+/// number of generations or up to a generation. Each reports how long the
+/// generations took to compute.
+pub struct RunView {
+    bound: Rect,
+    board: Rc<RefCell<BoardView>>,
+    components: ViewChain,
+    step: Rc<dyn Fn()>,
+    start_run: Rc<dyn Fn(u64)>,
+    run: Rc<RefCell<Option<GenerationRun>>>,
+    generation_text: Rc<RefCell<TextView>>,
+    report_texts: Rc<[Rc<RefCell<TextView>>; 2]>,
+    shown_generation: Option<u64>,
+}
+
+impl RunView {
+    pub fn new(
+        bound: Rect,
+        board: Rc<RefCell<BoardView>>,
+        pause: Rc<Cell<bool>>,
+        theme: Theme,
+        toasts: Rc<RefCell<ToastView>>,
+    ) -> RunView {
+        let (x, y, width) = (bound.x(), bound.y(), bound.width());
+        let row = |i: i32| y + i * 40;
+        let mut components = ViewChain::new();
+
+        let text = |bound: Rect, text: &str| {
+            TextView::new(
+                bound,
+                text.to_string(),
+                fonts::ARK_PIXEL_FONT,
+                18,
+                theme.get_text_color(),
+            )
+            .wrap()
+        };
+        // Rejected values are reported besides marking the input
+        let input = |i: i32, on_submit: SubmitCallback| {
+            let toasts = toasts.clone();
+            TextInputView::new(
+                Rect::new(x + 70, row(i), width - 70, 30),
+                String::new(),
+                fonts::ARK_PIXEL_FONT,
+                18,
+                theme.get_text_color(),
+                Box::new(move |text| on_submit(text).inspect_err(|e| toasts.borrow_mut().warn(e))),
+            )
+            .wrap()
+        };
+        let run: Rc<RefCell<Option<GenerationRun>>> = Rc::new(RefCell::new(None));
+
+        let generation_text = text(Rect::new(x, row(0) + 6, width, 30), "");
+        components.add_view(generation_text.clone());
+        let report_texts = Rc::new([
+            text(Rect::new(x, row(4) + 6, width, 24), ""),
+            text(Rect::new(x, row(4) + 30, width, 24), ""),
+        ]);
+        for report_text in report_texts.iter() {
+            components.add_view(report_text.clone());
+        }
+
+        let step: Rc<dyn Fn()> = Rc::new({
+            let board = board.clone();
+            let run = run.clone();
+            let report_texts = report_texts.clone();
+            move || {
+                if !pause.get() || run.borrow().is_some() {
+                    return;
+                }
+                let started = Instant::now();
+                board.borrow_mut().step();
+                RunView::report(&report_texts, 1, started.elapsed());
+            }
+        });
+        let step_bound = Rect::new(x, row(1) + 6, width, 30);
+        components.add_view(text(step_bound, "Step"));
+        let step_button = ButtonView::new(
+            step_bound,
+            Box::new({
+                let step = step.clone();
+                move || step()
+            }),
+        )
+        .wrap();
+        components.add_view(step_button);
+
+        // Both inputs start a run to a target generation
+        let start_run: Rc<dyn Fn(u64)> = Rc::new({
+            let run = run.clone();
+            let report_texts = report_texts.clone();
+            move |target: u64| {
+                *run.borrow_mut() = Some(GenerationRun {
+                    target,
+                    generations: 0,
+                    elapsed: Duration::ZERO,
+                });
+                report_texts[0]
+                    .borrow_mut()
+                    .set_text(format!("Running to {}", target));
+                report_texts[1].borrow_mut().set_text(String::new());
+            }
+        });
+
+        components.add_view(text(Rect::new(x, row(2) + 6, 70, 30), "Run"));
+        components.add_view(input(
+            2,
+            Box::new({
+                let board = board.clone();
+                let start_run = start_run.clone();
+                move |text| {
+                    let generations: u64 = text
+                        .trim()
+                        .parse()
+                        .ok()
+                        .filter(|&generations| generations > 0)
+                        .ok_or_else(|| format!("Invalid number of generations: {}", text))?;
+                    start_run(board.borrow().get_generation() + generations);
+                    Ok(())
+                }
+            }),
+        ));
+
+        components.add_view(text(Rect::new(x, row(3) + 6, 70, 30), "Until"));
+        components.add_view(input(
+            3,
+            Box::new({
+                let board = board.clone();
+                let start_run = start_run.clone();
+                move |text| {
+                    let target: u64 = text
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid generation: {}", text))?;
+                    let generation = board.borrow().get_generation();
+                    if target <= generation {
+                        return Err(format!("The board is already at generation {}", generation));
+                    }
+                    start_run(target);
+                    Ok(())
+                }
+            }),
+        ));
+
+        RunView {
+            bound,
+            board,
+            components,
+            step,
+            start_run,
+            run,
+            generation_text,
+            report_texts,
+            shown_generation: None,
+        }
+    }
+
+    /// Advances one generation if the board is paused and no run is in
+    /// progress.
+    pub fn step(&self) {
+        (self.step)();
+    }
+
+    /// Starts running to a generation, if the board is not past it yet.
+    pub fn run_until(&self, target: u64) {
+        if target > self.board.borrow().get_generation() {
+            (self.start_run)(target);
+        }
+    }
+
+    /// Whether a run is in progress, during which the board should not
+    /// advance on ticks.
+    pub fn is_running(&self) -> bool {
+        self.run.borrow().is_some()
+    }
+
+    /// Advances the run in progress for at most `budget`, reporting the time
+    /// it took once it reaches its target.
+    pub fn advance(&mut self, budget: Duration) {
+        let mut run = self.run.borrow_mut();
+        let Some(current) = run.as_mut() else {
+            return;
+        };
+        let started = Instant::now();
+        let mut board = self.board.borrow_mut();
+        while board.get_generation() < current.target && started.elapsed() < budget {
+            board.step();
+            current.generations += 1;
+        }
+        current.elapsed += started.elapsed();
+
+        if board.get_generation() >= current.target {
+            RunView::report(&self.report_texts, current.generations, current.elapsed);
+            *run = None;
+        }
+    }
+
+    fn report(report_texts: &[Rc<RefCell<TextView>>; 2], generations: u64, elapsed: Duration) {
+        let generations = match generations {
+            1 => "1 generation".to_string(),
+            generations => format!("{} generations", generations),
+        };
+        let elapsed = if elapsed < Duration::from_secs(1) {
+            format!("in {:.2} ms", elapsed.as_secs_f64() * 1000.0)
+        } else {
+            format!("in {:.2} s", elapsed.as_secs_f64())
+        };
+        report_texts[0].borrow_mut().set_text(generations);
+        report_texts[1].borrow_mut().set_text(elapsed);
+    }
+}
+
+impl View for RunView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let generation = self.board.borrow().get_generation();
+        if self.shown_generation != Some(generation) {
+            self.shown_generation = Some(generation);
+            self.generation_text
+                .borrow_mut()
+                .set_text(format!("Generation: {}", generation));
+        }
+        self.components
+            .render(canvas, texture_creator, font_manager)
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.bound
+    }
+
+    fn has_focus(&self) -> bool {
+        self.components.has_focus()
+    }
+
+    fn on_key_down(&mut self, key: Keycode) {
+        self.components.on_key_down(key);
+    }
+
+    fn on_text_input(&mut self, text: &str) {
+        self.components.on_text_input(text);
+    }
+
+    fn on_mouse_button_down(&mut self, button: sdl2::mouse::MouseButton, x: i32, y: i32) {
+        self.components.on_mouse_button_down(button, x, y);
+    }
+}
@@ -0,0 +1,141 @@
+use std::{cell::RefCell, rc::Rc};
+
+use sdl2::{keyboard::Keycode, rect::Rect};
+
+use crate::engine::{
+    resource_manager::FontManager,
+    view::{TextInputView, TextView, View, ViewChain},
+};
+use crate::fonts;
+
+use super::board::BoardView;
+use super::pattern::apgcode;
+use super::theme::Theme;
+use super::toast::ToastView;
+
+/// Names the object selected on the board by its apgcode, in a box that
+/// also takes apgcodes to paste their objects.
+pub struct SelectionView {
+    bound: Rect,
+    board: Rc<RefCell<BoardView>>,
+    components: ViewChain,
+    description_text: Rc<RefCell<TextView>>,
+    code_input: Rc<RefCell<TextInputView>>,
+    // The apgcode of the selection, which submitting as is pastes nothing
+    code: Rc<RefCell<String>>,
+    // The selected cells the texts were last updated for
+    shown: Option<Option<Vec<u8>>>,
+}
+
+impl SelectionView {
+    pub fn new(
+        bound: Rect,
+        board: Rc<RefCell<BoardView>>,
+        theme: Theme,
+        toasts: Rc<RefCell<ToastView>>,
+    ) -> SelectionView {
+        let (x, y, width) = (bound.x(), bound.y(), bound.width());
+        let code = Rc::new(RefCell::new(String::new()));
+        let description_text = TextView::new(
+            Rect::new(x, y, width, 20),
+            String::new(),
+            fonts::ARK_PIXEL_FONT,
+            14,
+            theme.get_text_color(),
+        )
+        .wrap();
+        let code_input = TextInputView::new(
+            Rect::new(x, y + 24, width, 28),
+            String::new(),
+            fonts::ARK_PIXEL_FONT,
+            14,
+            theme.get_text_color(),
+            Box::new({
+                let board = board.clone();
+                let code = code.clone();
+                move |text| {
+                    if text.trim().is_empty() || text.trim() == *code.borrow() {
+                        return Ok(());
+                    }
+                    board
+                        .borrow_mut()
+                        .paste_code(text)
+                        .map_err(|e| e.to_string())
+                        .inspect_err(|e| toasts.borrow_mut().warn(e))
+                }
+            }),
+        )
+        .wrap();
+
+        let mut components = ViewChain::new();
+        components.add_view(description_text.clone());
+        components.add_view(code_input.clone());
+        SelectionView {
+            bound,
+            board,
+            components,
+            description_text,
+            code_input,
+            code,
+            shown: None,
+        }
+    }
+
+    /// Identifies the selected cells again if they changed.
+    fn update(&mut self) {
+        let board = self.board.borrow();
+        let selected = board.get_selected_pattern();
+        let cells = selected.as_ref().map(|pattern| pattern.cells.clone());
+        if self.shown.as_ref() == Some(&cells) {
+            return;
+        }
+        self.shown = Some(cells);
+
+        let (description, code) = match selected {
+            None => ("Paste an apgcode:".to_string(), String::new()),
+            Some(pattern) => match apgcode::identify(&pattern, board.get_rule()) {
+                Ok(object) => (object.describe(), object.code),
+                Err(e) => (e, String::new()),
+            },
+        };
+        self.description_text.borrow_mut().set_text(description);
+        // Codes being typed are left alone
+        if !self.code_input.borrow().has_focus() {
+            self.code_input.borrow_mut().set_text(code.clone());
+        }
+        *self.code.borrow_mut() = code;
+    }
+}
+
+impl View for SelectionView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.update();
+        self.components
+            .render(canvas, texture_creator, font_manager)
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.bound
+    }
+
+    fn has_focus(&self) -> bool {
+        self.components.has_focus()
+    }
+
+    fn on_key_down(&mut self, key: Keycode) {
+        self.components.on_key_down(key);
+    }
+
+    fn on_text_input(&mut self, text: &str) {
+        self.components.on_text_input(text);
+    }
+
+    fn on_mouse_button_down(&mut self, button: sdl2::mouse::MouseButton, x: i32, y: i32) {
+        self.components.on_mouse_button_down(button, x, y);
+    }
+}
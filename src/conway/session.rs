@@ -0,0 +1,140 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use toml_edit::{value, Array, Document};
+
+use super::board::BoardView;
+use super::error::ConwayError;
+use super::pattern::save;
+
+/// Where autosaves go, under the user's state directory.
+const SESSION_PATH: [&str; 2] = ["conway-rs", "session"];
+const SESSION_FILE: &str = "session.toml";
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// The most cells the autosaved board and undo history may have.
+const MAX_AUTOSAVE_CELLS: usize = 1 << 24;
+
+/// Autosaves the board and its undo history while it has unsaved changes,
+/// so a session that ended without saving them can be restored. An autosave
+/// is a directory of saves, the board last, and a `session.toml` with their
+/// generations.
+pub struct Session {
+    directory: Option<PathBuf>,
+    last_autosave: Instant,
+    // The version of the board last autosaved
+    saved_version: Option<u64>,
+}
+
+impl Session {
+    pub fn new() -> Session {
+        let directory = dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| SESSION_PATH.iter().fold(dir, |path, part| path.join(part)));
+        Session {
+            directory,
+            last_autosave: Instant::now(),
+            saved_version: None,
+        }
+    }
+
+    /// Whether a previous session left an autosave.
+    pub fn has_autosave(&self) -> bool {
+        self.directory
+            .as_ref()
+            .is_some_and(|directory| directory.join(SESSION_FILE).exists())
+    }
+
+    /// Restores the board and its undo history from the autosave.
+    pub fn restore(&mut self, board: &mut BoardView) -> Result<(), ConwayError> {
+        let invalid = || ConwayError::Validation("Invalid autosave".to_string());
+        let directory = self.directory.as_ref().ok_or_else(invalid)?;
+        let path = directory.join(SESSION_FILE);
+        let document = std::fs::read_to_string(&path)
+            .map_err(ConwayError::io(&path))?
+            .parse::<Document>()
+            .map_err(|e| ConwayError::Validation(format!("Invalid autosave: {}", e)))?;
+        let generations = document
+            .get("generations")
+            .and_then(|generations| generations.as_array())
+            .ok_or_else(invalid)?;
+        let mut snapshots = Vec::with_capacity(generations.len());
+        for (i, generation) in generations.iter().enumerate() {
+            let generation = generation
+                .as_integer()
+                .and_then(|generation| u64::try_from(generation).ok())
+                .ok_or_else(invalid)?;
+            let path = directory.join(format!("{}.conway", i));
+            let content = std::fs::read_to_string(&path).map_err(ConwayError::io(&path))?;
+            let pattern = save::parse(&content, board.get_rule()).map_err(|e| e.in_file(&path))?;
+            snapshots.push((pattern, generation));
+        }
+        board
+            .restore_snapshots(snapshots)
+            .map_err(ConwayError::Validation)?;
+        self.saved_version = Some(board.get_version());
+        Ok(())
+    }
+
+    /// Autosaves the board if it changed and was not autosaved for a while.
+    /// Once its changes are saved to a file the autosave is removed.
+    pub fn autosave(&mut self, board: &BoardView) -> Result<(), ConwayError> {
+        if !board.is_modified() {
+            if self.saved_version.is_some() {
+                self.clear()?;
+            }
+            return Ok(());
+        }
+        if self.saved_version == Some(board.get_version())
+            || self.last_autosave.elapsed() < AUTOSAVE_INTERVAL
+        {
+            return Ok(());
+        }
+        self.save(board)
+    }
+
+    /// Autosaves the board now.
+    pub fn save(&mut self, board: &BoardView) -> Result<(), ConwayError> {
+        self.last_autosave = Instant::now();
+        let Some(directory) = &self.directory else {
+            return Ok(());
+        };
+        // Write to a new directory first, so a crash while writing keeps the
+        // last autosave
+        let new_directory = directory.with_extension("new");
+        if new_directory.exists() {
+            std::fs::remove_dir_all(&new_directory).map_err(ConwayError::io(&new_directory))?;
+        }
+        std::fs::create_dir_all(&new_directory).map_err(ConwayError::io(&new_directory))?;
+        let snapshots = board.get_snapshots(MAX_AUTOSAVE_CELLS);
+        let mut generations = Array::new();
+        for (i, (pattern, generation)) in snapshots.iter().enumerate() {
+            let path = new_directory.join(format!("{}.conway", i));
+            std::fs::write(&path, save::write(pattern)).map_err(ConwayError::io(&path))?;
+            generations.push(*generation as i64);
+        }
+        let mut document = Document::new();
+        document["generations"] = value(generations);
+        let path = new_directory.join(SESSION_FILE);
+        std::fs::write(&path, document.to_string()).map_err(ConwayError::io(&path))?;
+
+        if directory.exists() {
+            std::fs::remove_dir_all(directory).map_err(ConwayError::io(directory))?;
+        }
+        std::fs::rename(&new_directory, directory).map_err(ConwayError::io(directory))?;
+        self.saved_version = Some(board.get_version());
+        Ok(())
+    }
+
+    /// Removes the autosave.
+    pub fn clear(&mut self) -> Result<(), ConwayError> {
+        self.saved_version = None;
+        match &self.directory {
+            Some(directory) if directory.exists() => {
+                std::fs::remove_dir_all(directory).map_err(ConwayError::io(directory))
+            }
+            _ => Ok(()),
+        }
+    }
+}
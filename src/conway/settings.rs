@@ -0,0 +1,271 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use sdl2::rect::Rect;
+
+use crate::engine::{
+    resource_manager::FontManager,
+    view::{text_input::SubmitCallback, ButtonView, TextInputView, TextView, View, ViewChain},
+};
+use crate::fonts;
+
+use super::board::BoardView;
+use super::game_state::ResizeAnchor;
+use super::rule::Rule;
+use super::theme::Theme;
+use super::toast::ToastView;
+use super::topology::{Topology, TwistedEdges};
+
+/// The topologies the edges button cycles through.
+const TOPOLOGIES: [Topology; 6] = [
+    Topology::Plane,
+    Topology::TORUS,
+    Topology::KleinBottle(TwistedEdges::TopBottom),
+    Topology::CrossSurface,
+    Topology::Sphere,
+    Topology::Mirror,
+];
+
+/// Sidebar controls for the size, topology and rule of the board. Changes
+/// apply to the board as soon as they are submitted, and the controls follow
+/// the board when it changes otherwise, e.g. when a file is loaded.
+pub struct SettingsView {
+    bound: Rect,
+    board: Rc<RefCell<BoardView>>,
+    components: ViewChain,
+    width_input: Rc<RefCell<TextInputView>>,
+    height_input: Rc<RefCell<TextInputView>>,
+    rule_input: Rc<RefCell<TextInputView>>,
+    topology_text: Rc<RefCell<TextView>>,
+    // The width, height and rule the inputs were last updated to
+    shown: (usize, usize, String),
+}
+
+impl SettingsView {
+    pub fn new(
+        bound: Rect,
+        board: Rc<RefCell<BoardView>>,
+        theme: Theme,
+        toasts: Rc<RefCell<ToastView>>,
+    ) -> SettingsView {
+        let (x, y, width) = (bound.x(), bound.y(), bound.width());
+        let row = |i: i32| y + i * 40;
+        let mut components = ViewChain::new();
+
+        let label = |i: i32, text: &str| {
+            TextView::new(
+                Rect::new(x, row(i) + 6, width, 30),
+                text.to_string(),
+                fonts::ARK_PIXEL_FONT,
+                18,
+                theme.get_text_color(),
+            )
+            .wrap()
+        };
+        // Rejected values are reported besides marking the input
+        let input = |i: i32, on_submit: SubmitCallback| {
+            let toasts = toasts.clone();
+            TextInputView::new(
+                Rect::new(x + 70, row(i), width - 70, 30),
+                String::new(),
+                fonts::ARK_PIXEL_FONT,
+                18,
+                theme.get_text_color(),
+                Box::new(move |text| on_submit(text).inspect_err(|e| toasts.borrow_mut().warn(e))),
+            )
+            .wrap()
+        };
+        let anchor = Rc::new(Cell::new(ResizeAnchor::TopLeft));
+
+        components.add_view(label(0, "Width"));
+        let width_input = input(
+            0,
+            Box::new({
+                let board = board.clone();
+                let anchor = anchor.clone();
+                move |text| {
+                    let width = text
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid width: {}", text))?;
+                    let mut board = board.borrow_mut();
+                    let height = board.get_height();
+                    board.set_size(width, height, anchor.get())
+                }
+            }),
+        );
+        components.add_view(width_input.clone());
+
+        components.add_view(label(1, "Height"));
+        let height_input = input(
+            1,
+            Box::new({
+                let board = board.clone();
+                let anchor = anchor.clone();
+                move |text| {
+                    let height = text
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid height: {}", text))?;
+                    let mut board = board.borrow_mut();
+                    let width = board.get_width();
+                    board.set_size(width, height, anchor.get())
+                }
+            }),
+        );
+        components.add_view(height_input.clone());
+
+        let anchor_bound = Rect::new(x, row(2) + 6, width, 30);
+        let anchor_text = TextView::new(
+            anchor_bound,
+            "Anchor: Top-left".to_string(),
+            fonts::ARK_PIXEL_FONT,
+            18,
+            theme.get_text_color(),
+        )
+        .wrap();
+        components.add_view(anchor_text.clone());
+        let anchor_button = ButtonView::new(
+            anchor_bound,
+            Box::new(move || {
+                let (next, name) = match anchor.get() {
+                    ResizeAnchor::TopLeft => (ResizeAnchor::Center, "Center"),
+                    ResizeAnchor::Center => (ResizeAnchor::TopLeft, "Top-left"),
+                };
+                anchor.set(next);
+                anchor_text
+                    .borrow_mut()
+                    .set_text(format!("Anchor: {}", name));
+            }),
+        )
+        .wrap();
+        components.add_view(anchor_button);
+
+        let topology_bound = Rect::new(x, row(3) + 6, width, 30);
+        let topology_text = TextView::new(
+            topology_bound,
+            String::new(),
+            fonts::ARK_PIXEL_FONT,
+            18,
+            theme.get_text_color(),
+        )
+        .wrap();
+        components.add_view(topology_text.clone());
+        let topology_button = ButtonView::new(
+            topology_bound,
+            Box::new({
+                let board = board.clone();
+                move || {
+                    let mut board = board.borrow_mut();
+                    let current = board.get_topology();
+                    let start = TOPOLOGIES
+                        .iter()
+                        .position(|&topology| topology == current)
+                        .unwrap_or(TOPOLOGIES.len() - 1);
+                    // Skip the topologies the board cannot have, like a
+                    // sphere on a board that is not square
+                    for i in 1..TOPOLOGIES.len() {
+                        let topology = TOPOLOGIES[(start + i) % TOPOLOGIES.len()];
+                        if board.set_topology(topology).is_ok() {
+                            break;
+                        }
+                    }
+                }
+            }),
+        )
+        .wrap();
+        components.add_view(topology_button);
+
+        components.add_view(label(4, "Rule"));
+        let rule_input = input(
+            4,
+            Box::new({
+                let board = board.clone();
+                move |text| {
+                    let rule: Rule = text.parse()?;
+                    board.borrow_mut().set_rule(rule);
+                    Ok(())
+                }
+            }),
+        );
+        components.add_view(rule_input.clone());
+
+        SettingsView {
+            bound,
+            board,
+            components,
+            width_input,
+            height_input,
+            rule_input,
+            topology_text,
+            shown: (0, 0, String::new()),
+        }
+    }
+
+    /// Updates the controls whose board setting has changed, leaving the
+    /// ones being edited alone.
+    fn sync(&mut self) {
+        let board = self.board.borrow();
+        let (width, height, topology, rule) = (
+            board.get_width(),
+            board.get_height(),
+            board.get_topology(),
+            board.get_rule().to_string(),
+        );
+        let update = |input: &Rc<RefCell<TextInputView>>, text: String| {
+            let mut input = input.borrow_mut();
+            if !input.has_focus() {
+                input.set_text(text);
+            }
+        };
+        if width != self.shown.0 {
+            update(&self.width_input, width.to_string());
+        }
+        if height != self.shown.1 {
+            update(&self.height_input, height.to_string());
+        }
+        if rule != self.shown.2 {
+            update(&self.rule_input, rule.clone());
+        }
+        let topology_name = format!("Edges: {}", topology.get_name());
+        if self.topology_text.borrow().get_text() != topology_name {
+            self.topology_text.borrow_mut().set_text(topology_name);
+        }
+        self.shown = (width, height, rule);
+    }
+}
+
+impl View for SettingsView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.sync();
+        self.components
+            .render(canvas, texture_creator, font_manager)
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.bound
+    }
+
+    fn has_focus(&self) -> bool {
+        self.components.has_focus()
+    }
+
+    fn on_key_down(&mut self, key: sdl2::keyboard::Keycode) {
+        self.components.on_key_down(key);
+    }
+
+    fn on_text_input(&mut self, text: &str) {
+        self.components.on_text_input(text);
+    }
+
+    fn on_mouse_button_down(&mut self, button: sdl2::mouse::MouseButton, x: i32, y: i32) {
+        self.components.on_mouse_button_down(button, x, y);
+    }
+}
@@ -0,0 +1,290 @@
+//! Exports the current generation as an SVG or PNG image cropped to its live
+//! cells, and screenshots of the whole window.
+
+use std::{fmt::Write, ops::Range, path::Path};
+
+use sdl2::{pixels::PixelFormatEnum, render::Canvas, video::Window};
+
+use super::config::Config;
+use super::error::ConwayError;
+use super::export::{self, ExportOptions};
+use super::game_state::{ConwayGameState, DEAD};
+use super::raster::{
+    check_image_size, get_palette, get_region_size, get_row_shift, is_hexagonal, rasterize_region,
+};
+use super::theme::Theme;
+
+/// The most cells an SVG draws, as each gets an element of its own.
+const MAX_SVG_CELLS: usize = 1 << 20;
+
+/// Exports the board as an image by the extension of the path: `.svg` gets
+/// a rectangle per live cell, and `.png` a square of pixels per cell. Both
+/// are cropped to the live cells.
+pub fn export_image(
+    state: &ConwayGameState,
+    path: &Path,
+    options: &ExportOptions,
+) -> Result<(), ConwayError> {
+    options.validate().map_err(ConwayError::Validation)?;
+    let (columns, rows) = get_bounding_box(state);
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+    let content = match extension.as_deref() {
+        Some("svg") => {
+            let cells = columns.len() * rows.len();
+            if cells > MAX_SVG_CELLS {
+                return Err(ConwayError::Validation(format!(
+                    "{} cells are too many for an SVG, it can draw at most {}",
+                    cells, MAX_SVG_CELLS
+                )));
+            }
+            write_svg(state, columns, rows, options).into_bytes()
+        }
+        Some("png") => {
+            check_image_size(get_region_size(
+                state,
+                options.cell_size,
+                columns.len(),
+                rows.len(),
+            ))?;
+            let image = rasterize_region(state, options.cell_size, columns, rows);
+            let palette = get_palette(state.get_rule(), options.theme).concat();
+            let mut content = Vec::new();
+            export::write_png(
+                &mut content,
+                image.width,
+                image.height,
+                &palette,
+                &image.pixels,
+            )?;
+            content
+        }
+        _ => {
+            return Err(ConwayError::Validation(format!(
+                "Images are exported to .svg or .png files, not {}",
+                path.display()
+            )))
+        }
+    };
+    std::fs::write(path, content).map_err(ConwayError::io(path))
+}
+
+/// Loads the board as the window would and exports it as an image, without
+/// opening a window.
+pub fn export_image_headless(config: &Config, path: &Path) -> Result<(), ConwayError> {
+    let board = export::load_headless(config, config.get_seed())?;
+    export_image(board.get_game_state(), path, config.get_export_options())
+}
+
+/// Saves what the window shows as a PNG.
+pub fn save_screenshot(canvas: &Canvas<Window>, path: &Path) -> Result<(), ConwayError> {
+    let (width, height) = canvas.output_size().map_err(ConwayError::Sdl)?;
+    let pixels = canvas
+        .read_pixels(None, PixelFormatEnum::RGB24)
+        .map_err(ConwayError::Sdl)?;
+    let encoding = |e: png::EncodingError| ConwayError::Validation(e.to_string());
+    let mut content = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut content, width, height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(encoding)?;
+        writer.write_image_data(&pixels).map_err(encoding)?;
+    }
+    std::fs::write(path, content).map_err(ConwayError::io(path))
+}
+
+/// The columns and rows holding the cells that are not dead, or the whole
+/// board if it is empty.
+fn get_bounding_box(state: &ConwayGameState) -> (Range<usize>, Range<usize>) {
+    let width = state.get_width();
+    let mut bounds: Option<(Range<usize>, Range<usize>)> = None;
+    for (i, _) in state
+        .get_cells()
+        .iter()
+        .enumerate()
+        .filter(|(_, &cell)| cell != DEAD)
+    {
+        let (x, y) = (i % width, i / width);
+        bounds = Some(match bounds {
+            Some((columns, rows)) => (
+                columns.start.min(x)..columns.end.max(x + 1),
+                rows.start.min(y)..rows.end.max(y + 1),
+            ),
+            None => (x..x + 1, y..y + 1),
+        });
+    }
+    bounds.unwrap_or((0..width, 0..state.get_height()))
+}
+
+/// Draws the cells in a range of columns and rows as an SVG, with the cells
+/// of each state grouped by their color.
+fn write_svg(
+    state: &ConwayGameState,
+    columns: Range<usize>,
+    rows: Range<usize>,
+    options: &ExportOptions,
+) -> String {
+    let cell_size = options.cell_size;
+    let shift = if is_hexagonal(state) {
+        cell_size / 2
+    } else {
+        0
+    };
+    let width = columns.len() * cell_size + shift;
+    let height = rows.len() * cell_size;
+    let palette = get_palette(state.get_rule(), options.theme);
+    let color = |[r, g, b]: [u8; 3]| format!("#{:02x}{:02x}{:02x}", r, g, b);
+
+    // Writing to a string cannot fail
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" shape-rendering="crispEdges">"#,
+        w = width,
+        h = height
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<rect width="{}" height="{}" fill="{}"/>"#,
+        width,
+        height,
+        color(palette[DEAD as usize])
+    )
+    .unwrap();
+
+    let cells = state.get_cells();
+    for (state_index, &state_color) in palette.iter().enumerate().skip(1) {
+        let mut group = String::new();
+        for (i, y) in rows.clone().enumerate() {
+            let row_shift = get_row_shift(state, y, cell_size);
+            for (j, x) in columns.clone().enumerate() {
+                if cells[y * state.get_width() + x] as usize == state_index {
+                    writeln!(
+                        group,
+                        r#"<rect x="{}" y="{}" width="{s}" height="{s}"/>"#,
+                        j * cell_size + row_shift,
+                        i * cell_size,
+                        s = cell_size
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        if !group.is_empty() {
+            writeln!(svg, r#"<g fill="{}">"#, color(state_color)).unwrap();
+            svg.push_str(&group);
+            svg.push_str("</g>\n");
+        }
+    }
+
+    if options.grid {
+        let mut path = String::new();
+        for i in 0..=rows.len() {
+            write!(path, "M0 {}H{}", i * cell_size, width).unwrap();
+        }
+        if shift == 0 {
+            for j in 0..=columns.len() {
+                write!(path, "M{} 0V{}", j * cell_size, height).unwrap();
+            }
+        } else {
+            // The rows of hexagonal boards are offset, so each row gets its
+            // own borders
+            for (i, y) in rows.clone().enumerate() {
+                let row_shift = get_row_shift(state, y, cell_size);
+                for j in 0..=columns.len() {
+                    write!(
+                        path,
+                        "M{} {}v{}",
+                        j * cell_size + row_shift,
+                        i * cell_size,
+                        cell_size
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        writeln!(
+            svg,
+            r#"<path d="{}" stroke="{}" stroke-width="1" fill="none"/>"#,
+            path,
+            color(get_grid_color(options.theme))
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A color between the text and the background of the theme, closer to the
+/// background.
+fn get_grid_color(theme: Theme) -> [u8; 3] {
+    let (r, g, b) = theme.get_background_color().rgb();
+    let background = [r, g, b];
+    let (r, g, b) = theme.get_text_color().rgb();
+    let foreground = [r, g, b];
+    let mix = |i: usize| ((foreground[i] as u16 + 3 * background[i] as u16) / 4) as u8;
+    [mix(0), mix(1), mix(2)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conway::export::{DEFAULT_FRAME_DELAY, MAX_CELL_SIZE};
+    use crate::conway::game_state::ALIVE;
+    use crate::conway::topology::Topology;
+
+    /// A board with live cells in opposite corners, so the whole of it is
+    /// exported.
+    fn corners(size: usize) -> ConwayGameState {
+        let mut state = ConwayGameState::new(size, size, Topology::Plane, Default::default());
+        state.set_cell(0, 0, ALIVE);
+        state.set_cell(size - 1, size - 1, ALIVE);
+        state
+    }
+
+    fn options(cell_size: usize) -> ExportOptions {
+        ExportOptions {
+            frames: 1,
+            cell_size,
+            frame_delay: DEFAULT_FRAME_DELAY,
+            grid: false,
+            theme: Theme::default(),
+        }
+    }
+
+    #[test]
+    fn rejects_images_too_large_to_draw() {
+        let path = std::env::temp_dir().join("conway-rs-too-large.png");
+        assert!(matches!(
+            export_image(&corners(4096), &path, &options(MAX_CELL_SIZE)),
+            Err(ConwayError::Validation(_))
+        ));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn rejects_svgs_with_too_many_cells() {
+        let path = std::env::temp_dir().join("conway-rs-too-large.svg");
+        assert!(matches!(
+            export_image(&corners(2048), &path, &options(1)),
+            Err(ConwayError::Validation(_))
+        ));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn crops_svgs_to_the_live_cells() {
+        let mut state = ConwayGameState::new(16, 16, Topology::Plane, Default::default());
+        for x in 4..7 {
+            state.set_cell(x, 8, ALIVE);
+        }
+        let (columns, rows) = get_bounding_box(&state);
+        assert_eq!((columns.clone(), rows.clone()), (4..7, 8..9));
+        let svg = write_svg(&state, columns, rows, &options(4));
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="12" height="4""#));
+        assert_eq!(svg.matches(r#"width="4" height="4""#).count(), 3);
+    }
+}
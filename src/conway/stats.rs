@@ -0,0 +1,63 @@
+use std::{cell::RefCell, rc::Rc};
+
+use sdl2::rect::Rect;
+
+use crate::engine::{
+    frame_limiter::FrameLimiter,
+    resource_manager::FontManager,
+    view::{TextView, View},
+};
+use crate::fonts;
+
+use super::theme::Theme;
+
+/// Shows the measured frame and tick rates of a frame limiter.
+pub struct StatsView {
+    fps_limiter: Rc<RefCell<FrameLimiter>>,
+    text: TextView,
+    // The rounded rates the text shows
+    shown: Option<(u64, u64)>,
+}
+
+impl StatsView {
+    pub fn new(bound: Rect, fps_limiter: Rc<RefCell<FrameLimiter>>, theme: Theme) -> StatsView {
+        StatsView {
+            fps_limiter,
+            text: TextView::new(
+                bound,
+                String::new(),
+                fonts::ARK_PIXEL_FONT,
+                18,
+                theme.get_text_color(),
+            ),
+            shown: None,
+        }
+    }
+}
+
+impl View for StatsView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rates = {
+            let fps_limiter = self.fps_limiter.borrow();
+            (
+                fps_limiter.get_fps().round() as u64,
+                fps_limiter.get_tps().round() as u64,
+            )
+        };
+        if self.shown != Some(rates) {
+            self.shown = Some(rates);
+            self.text
+                .set_text(format!("{} FPS, {} TPS", rates.0, rates.1));
+        }
+        self.text.render(canvas, texture_creator, font_manager)
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.text.get_bound()
+    }
+}
@@ -0,0 +1,48 @@
+use std::{fmt, str::FromStr};
+
+use sdl2::pixels::Color;
+
+/// The colors of the window around the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn get_background_color(&self) -> Color {
+        match self {
+            Theme::Light => Color::WHITE,
+            Theme::Dark => Color::RGB(32, 32, 32),
+        }
+    }
+
+    pub fn get_text_color(&self) -> Color {
+        match self {
+            Theme::Light => Color::BLACK,
+            Theme::Dark => Color::RGB(224, 224, 224),
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            _ => Err(format!("Unknown theme: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Theme::Light => write!(f, "light"),
+            Theme::Dark => write!(f, "dark"),
+        }
+    }
+}
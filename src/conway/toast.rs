@@ -0,0 +1,235 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use log::Level;
+use sdl2::{mouse::MouseButton, pixels::Color, rect::Rect};
+
+use crate::engine::{
+    resource_manager::FontManager,
+    view::{TextView, View},
+};
+use crate::fonts;
+
+use super::theme::Theme;
+
+/// How long a toast is shown unless it is dismissed first.
+const TOAST_DURATION: Duration = Duration::from_secs(8);
+/// Older toasts are dismissed to show more than this many.
+const MAX_TOASTS: usize = 4;
+/// The most lines a message wraps to, the rest is only logged.
+const MAX_LINES: usize = 3;
+const FONT_SIZE: u16 = 14;
+const LINE_HEIGHT: i32 = 18;
+const PADDING: i32 = 10;
+const ERROR_COLOR: Color = Color::RGB(200, 0, 0);
+const WARNING_COLOR: Color = Color::RGB(230, 150, 0);
+
+struct Toast {
+    level: Level,
+    // Progress toasts stay until they are ended
+    progress: bool,
+    lines: Vec<String>,
+    shown: Instant,
+    bound: Rect,
+    texts: Vec<TextView>,
+}
+
+/// Notifications of errors, stacked up from the bottom of the view with the
+/// newest last. Every message is logged as it is shown, and each toast is
+/// dismissed by clicking it or after a while. A single progress toast
+/// updates in place instead, and is only logged as it starts.
+pub struct ToastView {
+    bound: Rect,
+    theme: Theme,
+    toasts: VecDeque<Toast>,
+}
+
+impl ToastView {
+    pub fn new(bound: Rect, theme: Theme) -> ToastView {
+        ToastView {
+            bound,
+            theme,
+            toasts: VecDeque::new(),
+        }
+    }
+
+    pub fn error(&mut self, message: impl fmt::Display) {
+        self.show(Level::Error, message.to_string());
+    }
+
+    pub fn warn(&mut self, message: impl fmt::Display) {
+        self.show(Level::Warn, message.to_string());
+    }
+
+    /// Shows the progress of a long task, replacing the previous progress.
+    pub fn progress(&mut self, message: impl fmt::Display) {
+        let message = message.to_string();
+        let lines = wrap(&message, self.get_columns());
+        match self.toasts.iter_mut().find(|toast| toast.progress) {
+            Some(toast) => {
+                if toast.lines != lines {
+                    toast.lines = lines;
+                    self.layout();
+                }
+            }
+            None => {
+                log::info!("{}", message);
+                self.push(Level::Info, message, true);
+            }
+        }
+    }
+
+    /// Dismisses the progress toast.
+    pub fn end_progress(&mut self) {
+        let count = self.toasts.len();
+        self.toasts.retain(|toast| !toast.progress);
+        if self.toasts.len() != count {
+            self.layout();
+        }
+    }
+
+    fn show(&mut self, level: Level, message: String) {
+        log::log!(level, "{}", message);
+        self.push(level, message, false);
+    }
+
+    fn push(&mut self, level: Level, message: String, progress: bool) {
+        self.toasts.push_back(Toast {
+            level,
+            progress,
+            lines: wrap(&message, self.get_columns()),
+            shown: Instant::now(),
+            bound: self.bound,
+            texts: Vec::new(),
+        });
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.layout();
+    }
+
+    /// How many characters fit on a line of a toast.
+    fn get_columns(&self) -> usize {
+        let columns = (self.bound.width() as i32 - 2 * PADDING) / (FONT_SIZE as i32 / 2);
+        columns.max(1) as usize
+    }
+
+    /// Whether a toast is at the point, so clicking there dismisses it.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.toasts
+            .iter()
+            .any(|toast| toast.bound.contains_point((x, y)))
+    }
+
+    /// Stacks the toasts up from the bottom of the view.
+    fn layout(&mut self) {
+        let mut bottom = self.bound.bottom();
+        for toast in self.toasts.iter_mut().rev() {
+            let height = toast.lines.len() as i32 * LINE_HEIGHT + 2 * PADDING;
+            toast.bound = Rect::new(
+                self.bound.x(),
+                bottom - height,
+                self.bound.width(),
+                height as u32,
+            );
+            toast.texts = toast
+                .lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    TextView::new(
+                        Rect::new(
+                            toast.bound.x() + PADDING,
+                            toast.bound.y() + PADDING + i as i32 * LINE_HEIGHT,
+                            toast.bound.width() - 2 * PADDING as u32,
+                            LINE_HEIGHT as u32,
+                        ),
+                        line.clone(),
+                        fonts::ARK_PIXEL_FONT,
+                        FONT_SIZE,
+                        self.theme.get_text_color(),
+                    )
+                })
+                .collect();
+            bottom -= height + PADDING;
+        }
+    }
+}
+
+/// Wraps a message at spaces to lines of at most `columns` characters,
+/// cutting off the lines past `MAX_LINES`.
+fn wrap(message: &str, columns: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in message.split_whitespace() {
+        let mut word = word.to_string();
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= columns => {
+                line.push(' ');
+                line.push_str(&word);
+                continue;
+            }
+            _ => {}
+        }
+        // Words longer than a line, like paths, are split
+        while word.chars().count() > columns {
+            let rest = word.split_off(word.char_indices().nth(columns).unwrap().0);
+            lines.push(word);
+            word = rest;
+        }
+        lines.push(word);
+    }
+    if lines.len() > MAX_LINES {
+        lines.truncate(MAX_LINES);
+        lines[MAX_LINES - 1].push_str("...");
+    }
+    lines
+}
+
+impl View for ToastView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let count = self.toasts.len();
+        self.toasts
+            .retain(|toast| toast.progress || toast.shown.elapsed() < TOAST_DURATION);
+        if self.toasts.len() != count {
+            self.layout();
+        }
+
+        for toast in &mut self.toasts {
+            canvas.set_draw_color(self.theme.get_background_color());
+            canvas.fill_rect(toast.bound)?;
+            canvas.set_draw_color(match toast.level {
+                Level::Error => ERROR_COLOR,
+                Level::Warn => WARNING_COLOR,
+                _ => self.theme.get_text_color(),
+            });
+            canvas.draw_rect(toast.bound)?;
+            for text in &mut toast.texts {
+                text.render(canvas, texture_creator, font_manager)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.bound
+    }
+
+    fn on_mouse_button_down(&mut self, _button: MouseButton, x: i32, y: i32) {
+        if let Some(i) = self
+            .toasts
+            .iter()
+            .position(|toast| toast.bound.contains_point((x, y)))
+        {
+            self.toasts.remove(i);
+            self.layout();
+        }
+    }
+}
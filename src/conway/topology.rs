@@ -0,0 +1,413 @@
+use std::{fmt, str::FromStr};
+
+/// The pair of edges a Klein bottle joins with a twist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TwistedEdges {
+    TopBottom,
+    LeftRight,
+}
+
+/// How the edges of the board are joined, after Golly's bounded grids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Cells beyond the edges are dead.
+    #[default]
+    Plane,
+    /// Opposite edges are joined, or only the left and right ones
+    /// (`horizontal`) or the top and bottom ones (`vertical`) for a
+    /// cylinder. Crossing the top or bottom edge moves `shift.0` cells to the
+    /// right, crossing the left or right edge `shift.1` cells down.
+    Torus {
+        horizontal: bool,
+        vertical: bool,
+        shift: (isize, isize),
+    },
+    /// Opposite edges are joined, one pair of them with a twist.
+    KleinBottle(TwistedEdges),
+    /// Opposite edges are joined, both pairs with a twist.
+    CrossSurface,
+    /// The top edge is joined to the left edge and the bottom edge to the
+    /// right edge. Only square boards can be spheres.
+    Sphere,
+    /// The edges reflect the cells next to them.
+    Mirror,
+}
+
+/// How one axis of a bounded grid is joined, e.g. `32*` or `32+5`.
+struct AxisSpec {
+    joined: bool,
+    twisted: bool,
+    shift: isize,
+}
+
+impl AxisSpec {
+    /// Parses the size, twist and shift of an axis. The size only matters
+    /// when it is 0, for an axis whose edges are not joined.
+    fn parse(spec: &str) -> Option<AxisSpec> {
+        let (size, rest) = spec.split_at(
+            spec.find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(spec.len()),
+        );
+        let (twisted, shift) = match rest.strip_prefix('*') {
+            Some(shift) => (true, shift),
+            None => (false, rest),
+        };
+        let shift = match shift {
+            "" => 0,
+            _ if shift.starts_with(['+', '-']) => shift.parse().ok()?,
+            _ => return None,
+        };
+        Some(AxisSpec {
+            joined: size.parse::<usize>() != Ok(0),
+            twisted,
+            shift,
+        })
+    }
+
+    fn format(joined: bool, shift: isize) -> String {
+        match (joined, shift) {
+            (false, _) => "0".to_string(),
+            (true, 0) => String::new(),
+            (true, shift) => format!("{:+}", shift),
+        }
+    }
+}
+
+impl Topology {
+    /// A torus joining both pairs of edges without a shift.
+    pub const TORUS: Topology = Topology::Torus {
+        horizontal: true,
+        vertical: true,
+        shift: (0, 0),
+    };
+
+    /// A name to show for the topology.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Topology::Plane => "Plane",
+            Topology::Torus {
+                horizontal: true,
+                vertical: true,
+                shift: (0, 0),
+            } => "Torus",
+            Topology::Torus {
+                horizontal: true,
+                vertical: true,
+                ..
+            } => "Shifted torus",
+            Topology::Torus { .. } => "Cylinder",
+            Topology::KleinBottle(_) => "Klein bottle",
+            Topology::CrossSurface => "Cross-surface",
+            Topology::Sphere => "Sphere",
+            Topology::Mirror => "Mirror",
+        }
+    }
+
+    /// Whether a board of the given size can have this topology.
+    pub fn validate(&self, width: usize, height: usize) -> Result<(), String> {
+        if *self == Topology::Sphere && width != height {
+            return Err(format!(
+                "A sphere needs a square board, not {}x{}",
+                width, height
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether cells are only joined to cells of the same row or column
+    /// across the edges, so the rows and columns can be treated separately.
+    pub fn is_axis_aligned(&self) -> bool {
+        matches!(
+            self,
+            Topology::Plane | Topology::Mirror | Topology::Torus { shift: (0, 0), .. }
+        )
+    }
+
+    /// Whether the left and right, and the top and bottom edges are joined
+    /// to each other without a twist.
+    pub fn get_periodic_axes(&self) -> (bool, bool) {
+        match *self {
+            Topology::Torus {
+                horizontal,
+                vertical,
+                ..
+            } => (horizontal, vertical),
+            _ => (false, false),
+        }
+    }
+
+    /// Maps a position that may lie beyond the edges onto the board, or
+    /// None if it is outside of a plane.
+    pub fn wrap(&self, x: isize, y: isize, width: usize, height: usize) -> Option<(usize, usize)> {
+        let (w, h) = (width as isize, height as isize);
+        let inside = |x: isize, y: isize| (0..w).contains(&x) && (0..h).contains(&y);
+        let (x, y) = match *self {
+            Topology::Plane => (x, y),
+            Topology::Torus {
+                horizontal,
+                vertical,
+                shift: (shift_x, shift_y),
+            } => {
+                let wrap_x = |x: isize, y: isize| match horizontal {
+                    true => (x.rem_euclid(w), y + x.div_euclid(w) * shift_y),
+                    false => (x, y),
+                };
+                let wrap_y = |x: isize, y: isize| match vertical {
+                    true => (x + y.div_euclid(h) * shift_x, y.rem_euclid(h)),
+                    false => (x, y),
+                };
+                // The shift of one axis moves along the other, which is
+                // wrapped afterwards
+                if shift_x != 0 {
+                    let (x, y) = wrap_y(x, y);
+                    wrap_x(x, y)
+                } else {
+                    let (x, y) = wrap_x(x, y);
+                    wrap_y(x, y)
+                }
+            }
+            Topology::KleinBottle(TwistedEdges::TopBottom) => {
+                let x = if y.div_euclid(h) % 2 != 0 {
+                    w - 1 - x
+                } else {
+                    x
+                };
+                (x.rem_euclid(w), y.rem_euclid(h))
+            }
+            Topology::KleinBottle(TwistedEdges::LeftRight) => {
+                let y = if x.div_euclid(w) % 2 != 0 {
+                    h - 1 - y
+                } else {
+                    y
+                };
+                (x.rem_euclid(w), y.rem_euclid(h))
+            }
+            Topology::CrossSurface => {
+                let (mut wrapped_x, mut wrapped_y) = (x.rem_euclid(w), y.rem_euclid(h));
+                if y.div_euclid(h) % 2 != 0 {
+                    wrapped_x = w - 1 - wrapped_x;
+                }
+                if x.div_euclid(w) % 2 != 0 {
+                    wrapped_y = h - 1 - wrapped_y;
+                }
+                (wrapped_x, wrapped_y)
+            }
+            Topology::Sphere => {
+                // Crossing an edge swaps the axes, so far positions may need
+                // a few crossings
+                let (mut x, mut y) = (x, y);
+                for _ in 0..4 {
+                    (x, y) = if y < 0 {
+                        (-y - 1, x)
+                    } else if y >= h {
+                        (w - 1 - (y - h), x)
+                    } else if x < 0 {
+                        (y, -x - 1)
+                    } else if x >= w {
+                        (y, h - 1 - (x - w))
+                    } else {
+                        break;
+                    };
+                }
+                (x, y)
+            }
+            Topology::Mirror => {
+                let reflect = |v: isize, size: isize| {
+                    let v = v.rem_euclid(2 * size);
+                    if v < size {
+                        v
+                    } else {
+                        2 * size - 1 - v
+                    }
+                };
+                (reflect(x, w), reflect(y, h))
+            }
+        };
+        inside(x, y).then_some((x as usize, y as usize))
+    }
+
+    /// The bounded grid suffix Golly adds to the rules of patterns, e.g.
+    /// `T32,24`, `T32+5,24` or `K32*,24`. Golly has no mirror grid, so
+    /// mirrors have none and are only kept in `.conway` saves.
+    pub fn format_bounded_grid(&self, width: usize, height: usize) -> Option<String> {
+        let grid = match *self {
+            Topology::Torus {
+                horizontal,
+                vertical,
+                shift,
+            } => {
+                let axis = |size: usize, joined: bool, shift: isize| match joined {
+                    true => format!("{}{}", size, AxisSpec::format(true, shift)),
+                    false => "0".to_string(),
+                };
+                format!(
+                    "T{},{}",
+                    axis(width, horizontal, shift.0),
+                    axis(height, vertical, shift.1)
+                )
+            }
+            Topology::KleinBottle(TwistedEdges::TopBottom) => format!("K{}*,{}", width, height),
+            Topology::KleinBottle(TwistedEdges::LeftRight) => format!("K{},{}*", width, height),
+            Topology::Sphere => format!("S{}", width),
+            Topology::Mirror => return None,
+            _ => format!("{}{},{}", self, width, height),
+        };
+        Some(grid)
+    }
+}
+
+impl FromStr for Topology {
+    type Err = String;
+
+    /// Parses a topology in Golly's bounded grid syntax: `P` (plane), `T`
+    /// (torus), `K` (Klein bottle), `C` (cross-surface) and `S` (sphere),
+    /// and `M` for mirror, which only this app reads. Golly's sizes, as in
+    /// `T32,24`, may follow and are ignored as the board has its own, except
+    /// that a torus with a size of 0 is a cylinder, e.g. `T,0` only joins the
+    /// left and right edges. A torus may shift one pair of edges, as in
+    /// `T+5,`. An asterisk marks the twisted edges of a Klein bottle, `K*,`
+    /// or `K,*`, which default to the top and bottom ones.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("Invalid topology: {}", s);
+        let mut chars = s.chars();
+        let letter = chars.next().ok_or("Missing topology")?;
+        let sizes = chars.as_str();
+
+        let (width, height) = sizes.split_once(',').unwrap_or((sizes, sizes));
+        let (x, y) = AxisSpec::parse(width)
+            .zip(AxisSpec::parse(height))
+            .ok_or_else(invalid)?;
+        if letter.eq_ignore_ascii_case(&'T') {
+            // A shift moves along the edges it joins, and only one pair of
+            // edges can have one
+            let shifts_valid = (x.shift == 0 || y.joined)
+                && (y.shift == 0 || x.joined)
+                && (x.shift == 0 || y.shift == 0);
+            if x.twisted || y.twisted || !(x.joined || y.joined) || !shifts_valid {
+                return Err(invalid());
+            }
+            return Ok(Topology::Torus {
+                horizontal: x.joined,
+                vertical: y.joined,
+                shift: (x.shift, y.shift),
+            });
+        }
+        if !(x.joined && y.joined) {
+            return Err(format!("Unbounded axes are not supported: {}", s));
+        }
+        if x.shift != 0 || y.shift != 0 {
+            return Err(invalid());
+        }
+
+        let topology = match (letter.to_ascii_uppercase(), (x.twisted, y.twisted)) {
+            ('P', (false, false)) => Topology::Plane,
+            ('K', (_, false)) => Topology::KleinBottle(TwistedEdges::TopBottom),
+            ('K', (false, true)) => Topology::KleinBottle(TwistedEdges::LeftRight),
+            ('C', (false, false)) => Topology::CrossSurface,
+            ('S', (false, false)) if !sizes.contains(',') => Topology::Sphere,
+            ('M', (false, false)) => Topology::Mirror,
+            _ => return Err(invalid()),
+        };
+        Ok(topology)
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let topology = match *self {
+            Topology::Torus {
+                horizontal,
+                vertical,
+                shift,
+            } if *self != Topology::TORUS => {
+                return write!(
+                    f,
+                    "T{},{}",
+                    AxisSpec::format(horizontal, shift.0),
+                    AxisSpec::format(vertical, shift.1)
+                );
+            }
+            Topology::Plane => "P",
+            Topology::Torus { .. } => "T",
+            Topology::KleinBottle(TwistedEdges::TopBottom) => "K",
+            Topology::KleinBottle(TwistedEdges::LeftRight) => "K,*",
+            Topology::CrossSurface => "C",
+            Topology::Sphere => "S",
+            Topology::Mirror => "M",
+        };
+        write!(f, "{}", topology)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOPOLOGIES: [Topology; 9] = [
+        Topology::Plane,
+        Topology::TORUS,
+        Topology::Torus {
+            horizontal: true,
+            vertical: false,
+            shift: (0, 0),
+        },
+        Topology::Torus {
+            horizontal: true,
+            vertical: true,
+            shift: (5, 0),
+        },
+        Topology::Torus {
+            horizontal: true,
+            vertical: true,
+            shift: (0, -3),
+        },
+        Topology::KleinBottle(TwistedEdges::TopBottom),
+        Topology::KleinBottle(TwistedEdges::LeftRight),
+        Topology::CrossSurface,
+        Topology::Sphere,
+    ];
+
+    #[test]
+    fn round_trips_topologies() {
+        for topology in TOPOLOGIES.into_iter().chain([Topology::Mirror]) {
+            assert_eq!(topology.to_string().parse(), Ok(topology));
+        }
+    }
+
+    #[test]
+    fn round_trips_bounded_grids() {
+        for topology in TOPOLOGIES {
+            let grid = topology.format_bounded_grid(32, 32).unwrap();
+            assert_eq!(grid.parse(), Ok(topology), "{}", grid);
+        }
+        assert_eq!(
+            Topology::Torus {
+                horizontal: true,
+                vertical: false,
+                shift: (0, 0),
+            }
+            .format_bounded_grid(32, 24)
+            .as_deref(),
+            Some("T32,0")
+        );
+        assert_eq!(
+            Topology::KleinBottle(TwistedEdges::LeftRight)
+                .format_bounded_grid(32, 24)
+                .as_deref(),
+            Some("K32,24*")
+        );
+    }
+
+    #[test]
+    fn mirrors_have_no_bounded_grid() {
+        assert_eq!(Topology::Mirror.format_bounded_grid(32, 24), None);
+    }
+
+    #[test]
+    fn rejects_invalid_topologies() {
+        for topology in ["", "X", "T0,0", "T+1,+1", "P+1,", "S32,24", "K*,*"] {
+            assert!(topology.parse::<Topology>().is_err(), "{}", topology);
+        }
+    }
+}
@@ -12,6 +12,7 @@ use self::error::EngineError;
 use self::sdlcontext::SdlContext;
 
 use resource_manager::FontManager;
+use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::video::Window;
 
@@ -28,6 +29,15 @@ pub struct Engine {
 
 impl Engine {
     pub fn run_app<T: App>(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.run_app_with(T::create)
+    }
+
+    /// Runs an app made from the viewport by `create`, for apps that need
+    /// more than the viewport to be made.
+    pub fn run_app_with<T: App>(
+        &mut self,
+        create: impl FnOnce(Rect) -> T,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         if let EngineState::Init = self.state {
             self.state = EngineState::Running;
 
@@ -35,7 +45,7 @@ impl Engine {
 
             let mut font_manager = FontManager::new(&self.context.ttf_context);
 
-            let mut app = T::create(self.canvas.viewport());
+            let mut app = create(self.canvas.viewport());
             app.run(
                 &mut self.context.sdl_context,
                 &mut self.canvas,
@@ -81,6 +91,7 @@ pub struct EngineBuilder {
     window_title: Option<String>,
     window_width: Option<u32>,
     window_height: Option<u32>,
+    vsync: Option<bool>,
 }
 
 impl EngineBuilder {
@@ -89,6 +100,7 @@ impl EngineBuilder {
             window_title: None,
             window_width: None,
             window_height: None,
+            vsync: None,
         }
     }
 
@@ -103,12 +115,18 @@ impl EngineBuilder {
         self
     }
 
-    pub fn build(self) -> Result<Engine, Box<dyn std::error::Error>> {
-        let sdl_context = sdl2::init()?;
-        let ttf_context = sdl2::ttf::init()?;
-        let image_context = sdl2::image::init(sdl2::image::InitFlag::PNG)?;
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.vsync = Some(vsync);
+        self
+    }
+
+    pub fn build(self) -> Result<Engine, EngineError> {
+        let sdl_context = sdl2::init().map_err(EngineError::sdl)?;
+        let ttf_context = sdl2::ttf::init().map_err(EngineError::sdl)?;
+        let image_context =
+            sdl2::image::init(sdl2::image::InitFlag::PNG).map_err(EngineError::sdl)?;
 
-        let video_subsystem = sdl_context.video()?;
+        let video_subsystem = sdl_context.video().map_err(EngineError::sdl)?;
         let window = video_subsystem
             .window(
                 &self.window_title.unwrap_or("SDL2 Window".into()),
@@ -117,9 +135,14 @@ impl EngineBuilder {
             )
             .position_centered()
             .opengl()
-            .build()?;
+            .build()
+            .map_err(EngineError::sdl)?;
 
-        let canvas = window.into_canvas().build()?;
+        let mut canvas_builder = window.into_canvas();
+        if self.vsync.unwrap_or(false) {
+            canvas_builder = canvas_builder.present_vsync();
+        }
+        let canvas = canvas_builder.build().map_err(EngineError::sdl)?;
 
         let context = SdlContext::new(sdl_context, ttf_context, image_context, video_subsystem);
 
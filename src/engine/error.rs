@@ -2,14 +2,24 @@ use std::{error, fmt};
 
 #[derive(Debug, Clone)]
 pub enum EngineError {
+    /// The engine was started twice, or stopped while not running.
     StateError,
     SdlError(String),
 }
 
+impl EngineError {
+    /// Wraps the errors of SDL calls, for `map_err`.
+    pub fn sdl(e: impl fmt::Display) -> EngineError {
+        EngineError::SdlError(e.to_string())
+    }
+}
+
 impl fmt::Display for EngineError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // TODO: Display detailed error message
-        write!(f, "EngineError: {:?}", self)
+        match self {
+            EngineError::StateError => write!(f, "The engine is not in a state to do this"),
+            EngineError::SdlError(message) => write!(f, "SDL error: {}", message),
+        }
     }
 }
 
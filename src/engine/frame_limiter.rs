@@ -1,73 +1,440 @@
-use std::time::{Duration, SystemTime, SystemTimeError};
+use std::time::{Duration, Instant};
 
-pub struct FrameLimiter {
+/// At most this many frames' worth of ticks, or ticks when they are slower
+/// than frames, are caught up after a stall. The rest are dropped.
+const MAX_CATCH_UP: u32 = 2;
+/// How often the measured frame and tick rates are updated.
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The source of time of a [`FrameLimiter`], replaceable for tests.
+pub trait Clock {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+}
+
+/// The monotonic system clock.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+impl<C: Clock> Clock for &C {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration);
+    }
+}
+
+/// Counts renders and ticks to measure their rates.
+struct FrameStats {
+    start_time: Instant,
+    renders: u32,
+    ticks: u64,
+    fps: f64,
+    tps: f64,
+}
+
+/// Paces renders and ticks with a fixed timestep. Ticks accumulate the
+/// elapsed time and are handed out once a whole tick interval has passed,
+/// so rounding never makes them drift, and renders are scheduled on a fixed
+/// grid of render intervals.
+pub struct FrameLimiter<C: Clock = SystemClock> {
+    clock: C,
     render_rate: u32,
-    tick_rate: u32,
+    tick_rate: f64,
+    // Whether ticks run as fast as possible instead of at `tick_rate`
+    unlimited: bool,
+    // Whether presenting a frame waits for the display
+    vsync: bool,
     render_interval: Duration,
     tick_interval: Duration,
-    previous_render_time: SystemTime,
-    previous_tick_time: SystemTime,
+    next_render_time: Instant,
+    previous_update_time: Instant,
+    // Time elapsed that has not been handed out as ticks yet
+    tick_accumulator: Duration,
+    // The number of ticks in a batch when unlimited, adapted to how long
+    // the previous batches took
+    batch_size: u32,
+    // When the last unlimited batch was handed out, if it has not been timed
+    batch_start_time: Option<Instant>,
+    stats: FrameStats,
 }
 
+/// What to do next, with the number of ticks to run. Ticks faster than the
+/// render rate are batched, so several of them run per frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FrameStepType {
     Render,
-    Tick,
-    RenderAndTick,
+    Tick(u32),
+    RenderAndTick(u32),
 }
 
 impl FrameLimiter {
-    pub fn new(render_rate: u32, tick_rate: u32) -> FrameLimiter {
+    pub fn new(render_rate: u32, tick_rate: f64) -> FrameLimiter {
+        FrameLimiter::with_clock(render_rate, tick_rate, SystemClock)
+    }
+}
+
+impl<C: Clock> FrameLimiter<C> {
+    pub fn with_clock(render_rate: u32, tick_rate: f64, clock: C) -> FrameLimiter<C> {
+        let now = clock.now();
+        let render_interval = Duration::from_secs_f64(1.0 / render_rate as f64);
         FrameLimiter {
-            render_rate: render_rate,
-            tick_rate: tick_rate,
-            render_interval: Duration::from_nanos(1_000_000_000u64 / render_rate as u64),
-            tick_interval: Duration::from_nanos(1_000_000_000u64 / tick_rate as u64),
-            previous_render_time: SystemTime::now(),
-            previous_tick_time: SystemTime::now(),
+            clock,
+            render_rate,
+            tick_rate,
+            unlimited: false,
+            vsync: false,
+            render_interval,
+            tick_interval: Duration::from_secs_f64(1.0 / tick_rate),
+            next_render_time: now + render_interval,
+            previous_update_time: now,
+            tick_accumulator: Duration::ZERO,
+            batch_size: 1,
+            batch_start_time: None,
+            stats: FrameStats {
+                start_time: now,
+                renders: 0,
+                ticks: 0,
+                fps: 0.0,
+                tps: 0.0,
+            },
         }
     }
 
-    pub fn step(&mut self) -> Result<FrameStepType, SystemTimeError> {
+    /// Waits until a render or tick is due and returns what to do.
+    pub fn step(&mut self) -> FrameStepType {
+        let step_type = if self.unlimited {
+            self.step_unlimited()
+        } else {
+            self.step_limited()
+        };
+        let now = self.clock.now();
+        match step_type {
+            FrameStepType::Render => self.record(now, 1, 0),
+            FrameStepType::Tick(ticks) => self.record(now, 0, ticks),
+            FrameStepType::RenderAndTick(ticks) => self.record(now, 1, ticks),
+        }
+        step_type
+    }
+
+    fn step_limited(&mut self) -> FrameStepType {
+        // Ticks faster than renders wait for the next render
+        let batched = self.tick_interval < self.render_interval;
         loop {
-            let render_elapsed_time = self.previous_render_time.elapsed()?;
-            let tick_elapsed_time = self.previous_tick_time.elapsed()?;
-
-            if render_elapsed_time >= self.render_interval
-                && tick_elapsed_time >= self.tick_interval
-            {
-                self.previous_render_time = SystemTime::now();
-                self.previous_tick_time = SystemTime::now();
-                break Ok(FrameStepType::RenderAndTick);
-            } else if render_elapsed_time >= self.render_interval {
-                self.previous_render_time = SystemTime::now();
-                break Ok(FrameStepType::Render);
-            } else if tick_elapsed_time >= self.tick_interval {
-                self.previous_tick_time = SystemTime::now();
-                break Ok(FrameStepType::Tick);
+            let now = self.clock.now();
+            self.accumulate(now);
+            let render_time = self.get_render_deadline();
+            let render_due = now >= render_time;
+            let ticks =
+                (self.tick_accumulator.as_secs_f64() / self.tick_interval.as_secs_f64()) as u32;
+
+            if render_due || (ticks > 0 && !batched) {
+                self.tick_accumulator -= self.tick_interval * ticks;
+                if !render_due {
+                    return FrameStepType::Tick(ticks);
+                }
+                self.schedule_render(now);
+                return match ticks {
+                    0 => FrameStepType::Render,
+                    ticks => FrameStepType::RenderAndTick(ticks),
+                };
             }
 
-            std::thread::sleep(
-                (self.render_interval - render_elapsed_time)
-                    .min(self.tick_interval - tick_elapsed_time),
-            );
+            let mut wait = render_time - now;
+            if !batched {
+                wait = wait.min(self.tick_interval - self.tick_accumulator);
+            }
+            self.clock.sleep(wait);
+        }
+    }
+
+    /// Alternates renders with batches of ticks, growing the batches while
+    /// they take less than half a frame and shrinking them when they take
+    /// more, so the UI stays responsive.
+    fn step_unlimited(&mut self) -> FrameStepType {
+        let now = self.clock.now();
+        let target = self.render_interval / 2;
+        if let Some(batch_start_time) = self.batch_start_time.take() {
+            let batch_time = now - batch_start_time;
+            if batch_time < target / 2 {
+                self.batch_size = self.batch_size.saturating_mul(2);
+            } else if batch_time > target {
+                self.batch_size = (self.batch_size / 2).max(1);
+            }
+        }
+
+        if now >= self.get_render_deadline() {
+            self.schedule_render(now);
+            return FrameStepType::Render;
+        }
+        self.batch_start_time = Some(now);
+        FrameStepType::Tick(self.batch_size)
+    }
+
+    /// Adds the time elapsed since the last update to the ticks, dropping
+    /// what exceeds the catch-up limit.
+    fn accumulate(&mut self, now: Instant) {
+        self.tick_accumulator += now - self.previous_update_time;
+        self.previous_update_time = now;
+        let limit = self.tick_interval.max(self.render_interval) * MAX_CATCH_UP;
+        self.tick_accumulator = self.tick_accumulator.min(limit);
+    }
+
+    /// When the next render is due. Presenting a frame with vsync waits for
+    /// the display, so renders are handed out half a frame early instead of
+    /// sleeping until they are due.
+    fn get_render_deadline(&self) -> Instant {
+        if self.vsync {
+            self.next_render_time - self.render_interval / 2
+        } else {
+            self.next_render_time
+        }
+    }
+
+    /// Moves the render schedule one frame ahead, or restarts it if renders
+    /// fell more than a frame behind, so missed frames are skipped rather
+    /// than rendered in a burst.
+    fn schedule_render(&mut self, now: Instant) {
+        self.next_render_time += self.render_interval;
+        if self.next_render_time <= now {
+            self.next_render_time = now + self.render_interval;
+        }
+    }
+
+    fn record(&mut self, now: Instant, renders: u32, ticks: u32) {
+        let stats = &mut self.stats;
+        stats.renders += renders;
+        stats.ticks += ticks as u64;
+        let elapsed = now - stats.start_time;
+        if elapsed >= STATS_INTERVAL {
+            stats.fps = stats.renders as f64 / elapsed.as_secs_f64();
+            stats.tps = stats.ticks as f64 / elapsed.as_secs_f64();
+            stats.start_time = now;
+            stats.renders = 0;
+            stats.ticks = 0;
         }
     }
 
     pub fn set_render_rate(&mut self, render_rate: u32) {
         self.render_rate = render_rate;
-        self.render_interval = Duration::from_nanos(1_000_000_000u64 / render_rate as u64);
+        self.render_interval = Duration::from_secs_f64(1.0 / render_rate as f64);
+        self.next_render_time = self.clock.now() + self.render_interval;
+    }
+
+    pub fn get_render_rate(&self) -> u32 {
+        self.render_rate
     }
 
-    pub fn set_tick_rate(&mut self, tick_rate: u32) {
+    pub fn set_tick_rate(&mut self, tick_rate: f64) {
         self.tick_rate = tick_rate;
-        self.tick_interval = Duration::from_nanos(1_000_000_000u64 / tick_rate as u64);
+        self.tick_interval = Duration::from_secs_f64(1.0 / tick_rate);
     }
 
-    pub fn get_tick_rate(&self) -> u32 {
+    pub fn get_tick_rate(&self) -> f64 {
         self.tick_rate
     }
 
+    /// Makes ticks run as fast as possible, ignoring the tick rate.
+    pub fn set_unlimited(&mut self, unlimited: bool) {
+        if unlimited != self.unlimited {
+            self.unlimited = unlimited;
+            self.batch_size = 1;
+            self.batch_start_time = None;
+            self.tick_accumulator = Duration::ZERO;
+            self.previous_update_time = self.clock.now();
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.unlimited
+    }
+
+    /// Tells the limiter whether presenting a frame waits for the display.
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+    }
+
+    pub fn is_vsync(&self) -> bool {
+        self.vsync
+    }
+
+    /// The measured number of renders per second.
+    pub fn get_fps(&self) -> f64 {
+        self.stats.fps
+    }
+
+    /// The measured number of ticks per second.
+    pub fn get_tps(&self) -> f64 {
+        self.stats.tps
+    }
+
+    pub fn get_render_interval(&self) -> Duration {
+        self.render_interval
+    }
+
     pub fn get_rate_ratio(&self) -> f32 {
         self.tick_interval.as_secs_f32() / self.render_interval.as_secs_f32()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// A clock that only moves when slept on or advanced by hand.
+    struct FakeClock {
+        start: Instant,
+        elapsed: Cell<Duration>,
+    }
+
+    impl FakeClock {
+        fn new() -> FakeClock {
+            FakeClock {
+                start: Instant::now(),
+                elapsed: Cell::new(Duration::ZERO),
+            }
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.start + self.elapsed.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.elapsed.set(self.elapsed.get() + duration);
+        }
+    }
+
+    /// Steps for `duration` of fake time, returning the renders and ticks.
+    fn run(limiter: &mut FrameLimiter<&FakeClock>, duration: Duration) -> (u32, u64) {
+        let end = limiter.clock.now() + duration;
+        let (mut renders, mut ticks) = (0, 0);
+        loop {
+            let step_type = limiter.step();
+            if limiter.clock.now() > end {
+                break;
+            }
+            match step_type {
+                FrameStepType::Render => renders += 1,
+                FrameStepType::Tick(n) => ticks += n as u64,
+                FrameStepType::RenderAndTick(n) => {
+                    renders += 1;
+                    ticks += n as u64;
+                }
+            }
+        }
+        (renders, ticks)
+    }
+
+    #[test]
+    fn renders_and_ticks_at_their_rates() {
+        let clock = FakeClock::new();
+        let mut limiter = FrameLimiter::with_clock(60, 5.0, &clock);
+        let (renders, ticks) = run(&mut limiter, Duration::from_millis(10_005));
+        assert_eq!(renders, 600);
+        assert_eq!(ticks, 50);
+    }
+
+    #[test]
+    fn fractional_tick_rates_do_not_drift() {
+        let clock = FakeClock::new();
+        let mut limiter = FrameLimiter::with_clock(60, 0.1, &clock);
+        let (_, ticks) = run(&mut limiter, Duration::from_millis(100_005));
+        assert_eq!(ticks, 10);
+
+        // An interval that does not divide a frame evenly
+        let mut limiter = FrameLimiter::with_clock(60, 7.0, &clock);
+        let (_, ticks) = run(&mut limiter, Duration::from_millis(60_005));
+        assert_eq!(ticks, 420);
+    }
+
+    #[test]
+    fn fast_ticks_are_batched_into_frames() {
+        let clock = FakeClock::new();
+        let mut limiter = FrameLimiter::with_clock(50, 10_000.0, &clock);
+        for _ in 0..10 {
+            assert_eq!(limiter.step(), FrameStepType::RenderAndTick(200));
+        }
+    }
+
+    #[test]
+    fn catch_up_is_limited_after_a_stall() {
+        let clock = FakeClock::new();
+        let mut limiter = FrameLimiter::with_clock(50, 1000.0, &clock);
+        run(&mut limiter, Duration::from_secs(1));
+
+        clock.sleep(Duration::from_secs(5));
+        // Two frames' worth of ticks at most, and a single render
+        assert_eq!(limiter.step(), FrameStepType::RenderAndTick(40));
+        let now = clock.now();
+        assert_eq!(limiter.step(), FrameStepType::RenderAndTick(20));
+        assert_eq!(clock.now() - now, Duration::from_millis(20));
+    }
+
+    #[test]
+    fn slow_steps_do_not_delay_the_schedule() {
+        let clock = FakeClock::new();
+        let mut limiter = FrameLimiter::with_clock(50, 1.0, &clock);
+        let mut renders = 0;
+        while clock.now() - clock.start < Duration::from_secs(1) {
+            if let FrameStepType::Render | FrameStepType::RenderAndTick(_) = limiter.step() {
+                renders += 1;
+            }
+            // Rendering takes a part of every frame
+            clock.sleep(Duration::from_millis(7));
+        }
+        assert_eq!(renders, 50);
+    }
+
+    #[test]
+    fn vsync_renders_early() {
+        let clock = FakeClock::new();
+        let mut limiter = FrameLimiter::with_clock(50, 1.0, &clock);
+        limiter.set_vsync(true);
+        limiter.step();
+        assert_eq!(clock.now() - clock.start, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn unlimited_batches_adapt_to_their_duration() {
+        let clock = FakeClock::new();
+        let mut limiter = FrameLimiter::with_clock(50, 1.0, &clock);
+        limiter.set_unlimited(true);
+        // Each tick takes 1 ms, so a batch of half a frame has 10 ticks
+        let mut sizes = Vec::new();
+        for _ in 0..40 {
+            match limiter.step() {
+                FrameStepType::Tick(ticks) => {
+                    sizes.push(ticks);
+                    clock.sleep(Duration::from_millis(ticks as u64));
+                }
+                _ => clock.sleep(Duration::from_millis(1)),
+            }
+        }
+        assert!(sizes.iter().all(|&size| size <= 16));
+        assert!(sizes[sizes.len() - 3..].iter().all(|&size| size >= 5));
+    }
+
+    #[test]
+    fn measures_frame_and_tick_rates() {
+        let clock = FakeClock::new();
+        let mut limiter = FrameLimiter::with_clock(60, 30.0, &clock);
+        run(&mut limiter, Duration::from_millis(2500));
+        assert!((limiter.get_fps() - 60.0).abs() < 1.0);
+        assert!((limiter.get_tps() - 30.0).abs() < 1.0);
+    }
+}
@@ -2,6 +2,7 @@ pub mod button;
 pub mod chain;
 pub mod image;
 pub mod text;
+pub mod text_input;
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -13,6 +14,7 @@ pub use self::button::ButtonView;
 pub use self::chain::ViewChain;
 pub use self::image::ImageView;
 pub use self::text::TextView;
+pub use self::text_input::TextInputView;
 
 use super::resource_manager::FontManager;
 
@@ -41,21 +43,38 @@ pub trait View {
             &Event::MouseButtonDown {
                 x, y, mouse_btn, ..
             } => self.on_mouse_button_down(mouse_btn, x, y),
+            &Event::MouseButtonUp {
+                x, y, mouse_btn, ..
+            } => self.on_mouse_button_up(mouse_btn, x, y),
             &Event::MouseMotion { x, y, .. } => self.on_mouse_motion(x, y),
+            Event::TextInput { text, .. } => self.on_text_input(text),
             _ => {}
         }
     }
 
     fn on_tick(&mut self) {}
 
+    /// Whether the view takes the keyboard input, e.g. while text is typed.
+    fn has_focus(&self) -> bool {
+        false
+    }
+
     fn on_key_down(&mut self, key: sdl2::keyboard::Keycode) {
         let _ = key;
     }
+    fn on_text_input(&mut self, text: &str) {
+        let _ = text;
+    }
     fn on_mouse_button_down(&mut self, button: MouseButton, x: i32, y: i32) {
         let _ = button;
         let _ = x;
         let _ = y;
     }
+    fn on_mouse_button_up(&mut self, button: MouseButton, x: i32, y: i32) {
+        let _ = button;
+        let _ = x;
+        let _ = y;
+    }
     fn on_mouse_motion(&mut self, x: i32, y: i32) {
         let _ = x;
         let _ = y;
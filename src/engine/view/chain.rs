@@ -48,18 +48,34 @@ impl View for ViewChain {
         }
     }
 
+    fn has_focus(&self) -> bool {
+        self.views.iter().any(|view| view.borrow().has_focus())
+    }
+
     fn on_key_down(&mut self, _key: sdl2::keyboard::Keycode) {
         for view in &mut self.views {
             view.borrow_mut().on_key_down(_key);
         }
     }
 
+    fn on_text_input(&mut self, text: &str) {
+        for view in &mut self.views {
+            view.borrow_mut().on_text_input(text);
+        }
+    }
+
     fn on_mouse_button_down(&mut self, button: sdl2::mouse::MouseButton, x: i32, y: i32) {
         for view in &mut self.views {
             view.borrow_mut().on_mouse_button_down(button, x, y);
         }
     }
 
+    fn on_mouse_button_up(&mut self, button: sdl2::mouse::MouseButton, x: i32, y: i32) {
+        for view in &mut self.views {
+            view.borrow_mut().on_mouse_button_up(button, x, y);
+        }
+    }
+
     fn on_mouse_motion(&mut self, _x: i32, _y: i32) {
         for view in &mut self.views {
             view.borrow_mut().on_mouse_motion(_x, _y);
@@ -43,6 +43,10 @@ impl TextView {
         self.render_bound.take();
     }
 
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
     pub fn set_text(&mut self, text: String) {
         self.text = text;
         self.destroy_texture();
@@ -0,0 +1,187 @@
+use sdl2::{keyboard::Keycode, pixels::Color, rect::Rect, render::Texture};
+
+use crate::engine::resource_manager::FontDetails;
+
+use super::View;
+
+const BORDER_COLOR: Color = Color::RGB(160, 160, 160);
+const INVALID_TEXT_COLOR: Color = Color::RGB(200, 0, 0);
+const TEXT_PADDING: i32 = 4;
+
+/// Called with the text when it is submitted, returning why it is invalid.
+pub type SubmitCallback = Box<dyn Fn(&str) -> Result<(), String>>;
+
+/// A single line text box. Clicking it focuses it, and the text is submitted
+/// when Enter is pressed or another view is clicked.
+pub struct TextInputView {
+    bound: Rect,
+    text: String,
+    font: &'static [u8],
+    size: u16,
+    color: Color,
+    focused: bool,
+    // Whether the last submitted text was rejected
+    invalid: bool,
+    on_submit: SubmitCallback,
+    texture: Option<Texture>,
+}
+
+impl TextInputView {
+    pub fn new(
+        bound: Rect,
+        text: String,
+        font: &'static [u8],
+        size: u16,
+        color: Color,
+        on_submit: SubmitCallback,
+    ) -> TextInputView {
+        TextInputView {
+            bound,
+            text,
+            font,
+            size,
+            color,
+            focused: false,
+            invalid: false,
+            on_submit,
+            texture: None,
+        }
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.invalid = false;
+        self.destroy_texture();
+    }
+
+    fn set_focused(&mut self, focused: bool) {
+        if self.focused != focused {
+            self.focused = focused;
+            self.destroy_texture();
+        }
+    }
+
+    fn submit(&mut self) {
+        self.set_focused(false);
+        self.invalid = (self.on_submit)(&self.text).is_err();
+        self.destroy_texture();
+    }
+
+    fn destroy_texture(&mut self) {
+        if let Some(texture) = self.texture.take() {
+            unsafe {
+                texture.destroy();
+            }
+        }
+    }
+}
+
+impl Drop for TextInputView {
+    fn drop(&mut self) {
+        self.destroy_texture();
+    }
+}
+
+impl View for TextInputView {
+    fn render(
+        &mut self,
+        canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+        texture_creator: &sdl2::render::TextureCreator<sdl2::video::WindowContext>,
+        font_manager: &mut crate::engine::resource_manager::FontManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // A focused box is outlined in the color of its text
+        canvas.set_draw_color(if self.focused {
+            self.color
+        } else {
+            BORDER_COLOR
+        });
+        canvas.draw_rect(self.bound)?;
+
+        // A caret follows the text while it is being edited
+        let text = if self.focused {
+            format!("{}_", self.text)
+        } else {
+            self.text.clone()
+        };
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        if self.texture.is_none() {
+            let font = font_manager.load(&FontDetails {
+                content: self.font,
+                size: self.size,
+            })?;
+            let color = if self.invalid {
+                INVALID_TEXT_COLOR
+            } else {
+                self.color
+            };
+            let surface = font.render(&text).blended(color)?;
+            self.texture = Some(texture_creator.create_texture_from_surface(&surface)?);
+        }
+
+        // Text wider than the box is cut off on the left, so the end being
+        // typed stays visible
+        let texture = self.texture.as_ref().unwrap();
+        let query = texture.query();
+        let width = query
+            .width
+            .min(self.bound.width().saturating_sub(2 * TEXT_PADDING as u32));
+        let height = query.height.min(self.bound.height());
+        canvas.copy(
+            texture,
+            Rect::new((query.width - width) as i32, 0, width, height),
+            Rect::new(
+                self.bound.x() + TEXT_PADDING,
+                self.bound.y() + (self.bound.height() - height) as i32 / 2,
+                width,
+                height,
+            ),
+        )?;
+        Ok(())
+    }
+
+    fn get_bound(&self) -> Rect {
+        self.bound
+    }
+
+    fn has_focus(&self) -> bool {
+        self.focused
+    }
+
+    fn on_key_down(&mut self, key: Keycode) {
+        if !self.focused {
+            return;
+        }
+        match key {
+            Keycode::Backspace => {
+                self.text.pop();
+                self.destroy_texture();
+            }
+            Keycode::Return | Keycode::KpEnter => self.submit(),
+            _ => {}
+        }
+    }
+
+    fn on_text_input(&mut self, text: &str) {
+        if self.focused {
+            self.text += text;
+            self.destroy_texture();
+        }
+    }
+
+    fn on_mouse_button_down(&mut self, button: sdl2::mouse::MouseButton, x: i32, y: i32) {
+        if let sdl2::mouse::MouseButton::Left = button {
+            if self.bound.contains_point((x, y)) {
+                self.set_focused(true);
+            } else if self.focused {
+                self.submit();
+            }
+        }
+    }
+}
@@ -1,9 +1,47 @@
-use conway_rs::{conway::ConwayApp, engine::EngineBuilder};
+use conway_rs::{
+    conway::{census_headless, export_headless, export_image_headless, Config, ConwayApp},
+    engine::EngineBuilder,
+};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() {
+    // Warnings and errors are logged unless RUST_LOG says otherwise
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    if let Err(e) = run() {
+        log::error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::load();
+    if let Some(path) = config.get_export_path() {
+        let frames = export_headless(&config, path)?;
+        println!("Exported {} frames to {}", frames, path.display());
+        return Ok(());
+    }
+    if let Some(path) = config.get_image_path() {
+        export_image_headless(&config, path)?;
+        println!("Exported the board to {}", path.display());
+        return Ok(());
+    }
+
+    if let Some(path) = config.get_census_path() {
+        let census = census_headless(&config, path)?;
+        println!(
+            "Counted {} objects of {} kinds, settled at generation {}, to {}",
+            census.get_total(),
+            census.tallies.len(),
+            census.settled,
+            path.display()
+        );
+        return Ok(());
+    }
+
+    let (width, height) = config.get_window_size();
     let mut engine = EngineBuilder::new()
         .window_title("Conway's Game of Life".into())
-        .window_size(800, 600)
+        .window_size(width, height)
+        .vsync(true)
         .build()?;
-    engine.run_app::<ConwayApp>()
+    engine.run_app_with(|viewport| ConwayApp::new(viewport, config))
 }